@@ -0,0 +1,63 @@
+//! Cost of every circuit with the backend the game is built with: the size of the constraint system
+//! is printed, the setup, proving and verification are measured.
+//! Run with `cargo bench --bench circuits`
+
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use battleships::{
+    circuit::report::{
+        board_input, count_constraints, field_input, sample_circuits, sample_shot_history,
+        shot_history_input, BOARD_DECLARATION, FIELD_DECLARATION, SHOT_HISTORY,
+    },
+    crypto::{
+        backend::{Backend, ProvingBackend},
+        proofs::PublicInput,
+    },
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+type Field = <Backend as ProvingBackend>::Field;
+
+fn bench_circuit<C: ConstraintSynthesizer<Field> + Clone>(
+    c: &mut Criterion,
+    name: &str,
+    circuit: C,
+    input: PublicInput<Field>,
+) {
+    let count = count_constraints(circuit.clone()).unwrap();
+    println!(
+        "{name}: {} constraints, {} witness variables, {} instance variables",
+        count.constraints, count.witness_variables, count.instance_variables
+    );
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.bench_function("setup", |b| {
+        b.iter(|| Backend::setup(circuit.clone(), &mut rng).unwrap())
+    });
+
+    let (pk, vk) = Backend::setup(circuit.clone(), &mut rng).unwrap();
+    let pvk = Backend::prepare(&vk);
+    let proof = Backend::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    assert!(Backend::verify(&pvk, &input.0, &proof).unwrap());
+    group.bench_function("prove", |b| {
+        b.iter(|| Backend::prove(&pk, circuit.clone(), &mut rng).unwrap())
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| Backend::verify(&pvk, &input.0, &proof).unwrap())
+    });
+    group.finish();
+}
+
+fn circuits(c: &mut Criterion) {
+    let (board, field) = sample_circuits();
+    bench_circuit(c, BOARD_DECLARATION, board, board_input(&board));
+    bench_circuit(c, FIELD_DECLARATION, field, field_input(&field));
+    let history = sample_shot_history(board);
+    let input = shot_history_input(&history).unwrap();
+    bench_circuit(c, SHOT_HISTORY, history, input);
+}
+
+criterion_group!(benches, circuits);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//! Proving and verification times of the circuits on BLS12-381 and BN254.
+//! Run with `cargo bench --bench curves`
+
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use battleships::{
+    circuit::report::{
+        board_input, field_input, sample_circuits, BOARD_DECLARATION, FIELD_DECLARATION,
+    },
+    crypto::{
+        backend::{Groth16Backend, NamedCurve, ProvingBackend},
+        proofs::PublicInput,
+    },
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Generate the keys once, then measure proving and verification of the circuit
+fn bench_circuit<E: NamedCurve, C: ConstraintSynthesizer<E::ScalarField> + Clone>(
+    c: &mut Criterion,
+    name: &str,
+    circuit: C,
+    input: PublicInput<E::ScalarField>,
+) {
+    let mut rng = StdRng::seed_from_u64(1);
+    let (pk, vk) = Groth16Backend::<E>::setup(circuit.clone(), &mut rng).unwrap();
+    let pvk = Groth16Backend::<E>::prepare(&vk);
+    let proof = Groth16Backend::<E>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    assert!(Groth16Backend::<E>::verify(&pvk, &input.0, &proof).unwrap());
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("prove", E::KIND.name()), |b| {
+        b.iter(|| Groth16Backend::<E>::prove(&pk, circuit.clone(), &mut rng).unwrap())
+    });
+    group.bench_function(BenchmarkId::new("verify", E::KIND.name()), |b| {
+        b.iter(|| Groth16Backend::<E>::verify(&pvk, &input.0, &proof).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_curve<E: NamedCurve>(c: &mut Criterion) {
+    let (board, field) = sample_circuits();
+    bench_circuit::<E, _>(c, BOARD_DECLARATION, board, board_input(&board));
+    bench_circuit::<E, _>(c, FIELD_DECLARATION, field, field_input(&field));
+}
+
+fn curves(c: &mut Criterion) {
+    bench_curve::<Bls12_381>(c);
+    bench_curve::<Bn254>(c);
+}
+
+criterion_group!(benches, curves);
+criterion_main!(benches);
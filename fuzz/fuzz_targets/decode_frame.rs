@@ -0,0 +1,18 @@
+#![no_main]
+
+use battleships::{
+    logic::{spectator::SpectatorEvent, GameMessage},
+    net::{
+        codec::{decode_frame, WireFormat, DEFAULT_MAX_FRAME_SIZE},
+        message::Message,
+    },
+};
+use libfuzzer_sys::fuzz_target;
+
+// Decoding arbitrary bytes received from a peer must never panic
+fuzz_target!(|data: &[u8]| {
+    for format in [WireFormat::Bincode, WireFormat::Json] {
+        let _ = decode_frame::<Message<GameMessage>>(data, format, DEFAULT_MAX_FRAME_SIZE);
+        let _ = decode_frame::<Message<SpectatorEvent>>(data, format, DEFAULT_MAX_FRAME_SIZE);
+    }
+});
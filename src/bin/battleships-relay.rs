@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use async_std::task::block_on;
+use battleships::{
+    net::{
+        connection::ConnectionLimits,
+        relay::{run_relay, RelayLimits},
+    },
+    utils::log::get_print_logger,
+};
+use clap::Parser;
+
+/// Relay pairing players who cannot connect to each other directly.
+/// It only forwards the traffic and never takes part in the game
+#[derive(Debug, Parser)]
+#[clap(name = "battleships-relay", version)]
+struct Cli {
+    /// Address to listen on
+    #[clap(default_value = "0.0.0.0:4100")]
+    addr: String,
+    /// Seconds given to a player for sending its request
+    #[clap(long, default_value_t = ConnectionLimits::default().read_timeout.as_secs())]
+    read_timeout: u64,
+    /// Rooms waiting for the second player at the same time
+    #[clap(long, default_value_t = RelayLimits::default().max_rooms)]
+    max_rooms: usize,
+    /// Connections allowed to be waiting for their request, further ones are closed
+    #[clap(long, default_value_t = RelayLimits::default().max_pending)]
+    max_pending: usize,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let limits = ConnectionLimits {
+        read_timeout: Duration::from_secs(cli.read_timeout),
+        ..Default::default()
+    };
+    let relay_limits = RelayLimits {
+        max_rooms: cli.max_rooms,
+        max_pending: cli.max_pending,
+    };
+
+    if let Err(e) = block_on(run_relay(
+        &cli.addr,
+        limits,
+        relay_limits,
+        get_print_logger(),
+    )) {
+        println!("Relay stopped: {}", e.message);
+    }
+}
@@ -1,3 +1,6 @@
 pub mod board_declaration_circuit;
 pub mod commons;
 pub mod field_declaration_circuit;
+pub mod game_certificate_circuit;
+pub mod report;
+pub mod shot_history_circuit;
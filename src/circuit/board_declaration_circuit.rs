@@ -1,5 +1,4 @@
-use ark_bls12_381::Fr;
-use ark_groth16::Groth16;
+use ark_ff::PrimeField;
 use ark_r1cs_std::alloc::AllocVar;
 use ark_r1cs_std::boolean::Boolean;
 use ark_r1cs_std::eq::EqGadget;
@@ -9,24 +8,26 @@ use ark_r1cs_std::select::CondSelectGadget;
 use ark_r1cs_std::uint8::UInt8;
 use ark_relations::ns;
 use ark_relations::r1cs::{ConstraintSystemRef, Result};
-use ark_serialize::CanonicalSerialize;
-use ark_snark::CircuitSpecificSetupSNARK;
 use ark_std::rand::SeedableRng;
 use ark_std::{iterable::Iterable, rand::rngs::StdRng};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::cmp::Ordering;
-use std::fs::File;
 
-pub type Curve = ark_bls12_381::Bls12_381;
-pub type CircuitField = Fr;
+pub use crate::circuit::commons::CircuitField;
+pub use crate::crypto::backend::Curve;
 
 use crate::circuit::commons::ShipVars;
+use crate::crypto::backend::{Backend, Parameters, ProvingBackend};
+use crate::crypto::keys::write_keys;
 use crate::model::{Board, Direction, Ship};
+use crate::utils::log::{Log, Logger};
+use crate::utils::result::Res;
 
 use super::commons::SHIPS_SIZES;
 use super::commons::{compute_hash, create_ship_vars};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct BoardDeclarationCircuit {
     pub board: Board,
     pub salt: [u8; 32],
@@ -57,28 +58,26 @@ impl From<Board> for BoardDeclarationCircuit {
     }
 }
 
-impl ark_relations::r1cs::ConstraintSynthesizer<CircuitField> for BoardDeclarationCircuit {
-    fn generate_constraints(self, cs: ConstraintSystemRef<CircuitField>) -> Result<()> {
+impl<F: PrimeField> ark_relations::r1cs::ConstraintSynthesizer<F> for BoardDeclarationCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
         // Generate needed constants
         // constans[i] -- constant representing i
-        let constants: Vec<FpVar<CircuitField>> = (0..=5)
-            .map(|number| {
-                FpVar::new_constant(ns!(cs, "constant"), CircuitField::from(number)).unwrap()
-            })
+        let constants: Vec<FpVar<F>> = (0..=5u8)
+            .map(|number| FpVar::new_constant(ns!(cs, "constant"), F::from(number)).unwrap())
             .collect();
-        let ten = FpVar::new_constant(ns!(cs, "10"), CircuitField::from(10))?;
+        let ten = FpVar::new_constant(ns!(cs, "10"), F::from(10u8))?;
 
         // Create private variables for each ship
-        let mut ships_vars: [ShipVars; 15] = self
+        let mut ships_vars: [ShipVars<F>; 15] = self
             .board
             .ships
             .map(|ship| create_ship_vars(&ship, &cs).unwrap());
         // Create private variable for hash salt
-        let salt_vars: [UInt8<CircuitField>; 32] = self
+        let salt_vars: [UInt8<F>; 32] = self
             .salt
             .map(|bit| UInt8::new_witness(ns!(cs, "salt"), || Ok(bit)).unwrap());
         // Create input for hash of ships
-        let hash_vars: [UInt8<CircuitField>; 32] = self
+        let hash_vars: [UInt8<F>; 32] = self
             .hash
             .map(|bit| UInt8::new_input(ns!(cs, "hash"), || Ok(bit)).unwrap());
 
@@ -145,7 +144,10 @@ impl ark_relations::r1cs::ConstraintSynthesizer<CircuitField> for BoardDeclarati
     }
 }
 
-fn enforce_ships_not_touching(ship1: &ShipVars, ship2: &ShipVars) -> Result<()> {
+fn enforce_ships_not_touching<F: PrimeField>(
+    ship1: &ShipVars<F>,
+    ship2: &ShipVars<F>,
+) -> Result<()> {
     // Ship1 needs to be either above, below, left or right the forbidden zone of ship2
 
     //-------------------
@@ -212,7 +214,9 @@ fn enforce_ships_not_touching(ship1: &ShipVars, ship2: &ShipVars) -> Result<()>
     Boolean::enforce_equal(&result_condition, &Boolean::TRUE)
 }
 
-pub fn generate_keys() {
+/// Generate the keys of the circuit, with their checksums, in the directory.
+/// A universal setup derives them from the shared parameters
+pub fn generate_keys(logger: &Logger, dir: &str, params: &Parameters) -> Res<()> {
     let mut rng = StdRng::seed_from_u64(1);
 
     let mut ships = [Ship {
@@ -237,15 +241,12 @@ pub fn generate_keys() {
 
     let now = std::time::Instant::now();
 
-    let (pk, vk) = Groth16::<Curve>::setup(dummy_circuit, &mut rng).unwrap();
+    let (pk, vk) = Backend::setup_with_parameters(dummy_circuit, params, &mut rng)?;
 
-    println!("Keys generated");
-    let elapsed = now.elapsed();
-    println!("Elapsed: {:.2?}", elapsed);
+    logger.info(&format!(
+        "Keys generated in {dir}, elapsed: {:.2?}",
+        now.elapsed()
+    ))?;
 
-    let vk_file = File::create("keys/board_declaration/vk.bin").unwrap();
-    vk.serialize_uncompressed(vk_file).unwrap();
-
-    let pk_file = File::create("keys/board_declaration/pk.bin").unwrap();
-    pk.serialize_uncompressed(pk_file).unwrap();
+    write_keys::<Backend>(dir, &vk, &pk)
 }
@@ -1,34 +1,43 @@
-use ark_bls12_381::Fr;
 use ark_crypto_primitives::crh::sha256::constraints::{DigestVar, Sha256Gadget};
+use ark_ff::{BigInteger, PrimeField};
 use ark_r1cs_std::alloc::AllocVar;
 use ark_r1cs_std::boolean::Boolean;
 use ark_r1cs_std::eq::EqGadget;
 use ark_r1cs_std::fields::fp::FpVar;
 use ark_r1cs_std::fields::FieldVar;
+use ark_r1cs_std::select::CondSelectGadget;
 use ark_r1cs_std::uint8::UInt8;
-use ark_r1cs_std::ToBytesGadget;
+use ark_r1cs_std::{R1CSVar, ToBitsGadget, ToBytesGadget};
 use ark_relations::ns;
 use ark_relations::r1cs::{ConstraintSystemRef, Result};
 use std::cmp::Ordering;
 
-pub type CircuitField = Fr;
-
+use crate::crypto::backend::{Backend, ProvingBackend};
 use crate::model::Ship;
 
+/// Field of the circuits in the curve the game is built with
+pub type CircuitField = <Backend as ProvingBackend>::Field;
+
 pub const SHIPS_SIZES: [usize; 15] = [1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 4, 4, 5];
 
-pub struct ShipVars {
-    pub x: FpVar<CircuitField>,
-    pub y: FpVar<CircuitField>,
-    pub size: FpVar<CircuitField>,
+/// Coordinates and their sums stay below 2^COORDINATE_BITS, as ships are declared with bytes
+const COORDINATE_BITS: usize = 10;
+
+pub struct ShipVars<F: PrimeField> {
+    pub x: FpVar<F>,
+    pub y: FpVar<F>,
+    pub size: FpVar<F>,
     pub size_numerical: Option<usize>,
-    pub direction: FpVar<CircuitField>,
-    pub is_vertical: Boolean<CircuitField>,
+    pub direction: FpVar<F>,
+    pub is_vertical: Boolean<F>,
 }
 
-pub fn create_ship_vars(ship: &Ship, cs: &ConstraintSystemRef<CircuitField>) -> Result<ShipVars> {
+pub fn create_ship_vars<F: PrimeField>(
+    ship: &Ship,
+    cs: &ConstraintSystemRef<F>,
+) -> Result<ShipVars<F>> {
     let direction = FpVar::new_witness(ns!(cs, "shipDirection"), || {
-        Ok(CircuitField::from(ship.direction as u8))
+        Ok(F::from(ship.direction as u8))
     })?;
     // direction <= 1
     FpVar::enforce_cmp(&direction, &FpVar::one(), Ordering::Less, true)?;
@@ -36,9 +45,9 @@ pub fn create_ship_vars(ship: &Ship, cs: &ConstraintSystemRef<CircuitField>) ->
     let is_vertical = FpVar::is_eq(&direction, &FpVar::zero()).unwrap();
 
     Ok(ShipVars {
-        x: FpVar::new_witness(ns!(cs, "shipX"), || Ok(CircuitField::from(ship.x)))?,
-        y: FpVar::new_witness(ns!(cs, "shipY"), || Ok(CircuitField::from(ship.y)))?,
-        size: FpVar::new_witness(ns!(cs, "shipSize"), || Ok(CircuitField::from(ship.size)))?,
+        x: FpVar::new_witness(ns!(cs, "shipX"), || Ok(F::from(ship.x)))?,
+        y: FpVar::new_witness(ns!(cs, "shipY"), || Ok(F::from(ship.y)))?,
+        size: FpVar::new_witness(ns!(cs, "shipSize"), || Ok(F::from(ship.size)))?,
         // To be set later
         size_numerical: None,
         direction,
@@ -46,11 +55,11 @@ pub fn create_ship_vars(ship: &Ship, cs: &ConstraintSystemRef<CircuitField>) ->
     })
 }
 
-pub fn compute_hash(
-    ships_vars: &[ShipVars; 15],
-    salt_vars: &[UInt8<CircuitField>; 32],
-) -> Result<DigestVar<CircuitField>> {
-    let mut hash_gadget: Sha256Gadget<CircuitField> = Sha256Gadget::default();
+pub fn compute_hash<F: PrimeField>(
+    ships_vars: &[ShipVars<F>; 15],
+    salt_vars: &[UInt8<F>; 32],
+) -> Result<DigestVar<F>> {
+    let mut hash_gadget: Sha256Gadget<F> = Sha256Gadget::default();
     // Hash every ship
     ships_vars.iter().for_each(|ship_vars| {
         hash_gadget
@@ -69,8 +78,107 @@ pub fn compute_hash(
 }
 
 // Need to also check if the var is less than 8 bytes long
-pub fn cast_fp_var_to_uint8(var: &FpVar<CircuitField>) -> Result<UInt8<CircuitField>> {
+pub fn cast_fp_var_to_uint8<F: PrimeField>(var: &FpVar<F>) -> Result<UInt8<F>> {
     let bytes = FpVar::to_bytes(var)?;
     // to_bytes function returns [var, 0, 0, 0, ...] -- a vector of UInt8 of len 32 (in case of not too large var)
     Ok(bytes[0].clone())
 }
+
+/// Ship allocated as bytes, so the hash binds its whole values
+pub struct ShipBytes<F: PrimeField> {
+    pub bytes: [UInt8<F>; 4],
+    pub x: FpVar<F>,
+    pub y: FpVar<F>,
+    pub size: FpVar<F>,
+    pub is_vertical: Boolean<F>,
+}
+
+pub fn create_ship_bytes<F: PrimeField>(
+    ship: &Ship,
+    cs: &ConstraintSystemRef<F>,
+) -> Result<ShipBytes<F>> {
+    let bytes = [ship.x, ship.y, ship.size, ship.direction as u8]
+        .map(|byte| UInt8::new_witness(ns!(cs, "ship"), || Ok(byte)).unwrap());
+    let [x, y, size, direction] = bytes.clone().map(|byte| byte_to_fp_var(&byte).unwrap());
+    let is_vertical = direction.is_zero()?;
+    Ok(ShipBytes {
+        bytes,
+        x,
+        y,
+        size,
+        is_vertical,
+    })
+}
+
+/// Hash of the ships and the salt, the same as `compute_hash` gives for the ship variables
+pub fn compute_bytes_hash<F: PrimeField>(
+    ships: &[ShipBytes<F>],
+    salt_vars: &[UInt8<F>; 32],
+) -> Result<DigestVar<F>> {
+    let mut hash_gadget = Sha256Gadget::<F>::default();
+    for ship in ships {
+        hash_gadget.update(&ship.bytes)?;
+    }
+    hash_gadget.update(salt_vars)?;
+    hash_gadget.finalize()
+}
+
+fn byte_to_fp_var<F: PrimeField>(byte: &UInt8<F>) -> Result<FpVar<F>> {
+    Boolean::le_bits_to_fp_var(&byte.to_bits_le()?)
+}
+
+/// a <= b, for values below 2^COORDINATE_BITS. Cheaper than `FpVar::is_cmp`, which works on the whole field
+fn is_le_small<F: PrimeField>(a: &FpVar<F>, b: &FpVar<F>) -> Result<Boolean<F>> {
+    let cs = a.cs().or(b.cs());
+    // b - a + 2^k has the bit k set exactly if a <= b
+    let shifted = b - a + FpVar::constant(F::from(1u64 << COORDINATE_BITS));
+    let bits = (0..=COORDINATE_BITS)
+        .map(|i| {
+            Boolean::new_witness(ns!(cs, "bit"), || {
+                Ok(shifted.value()?.into_bigint().get_bit(i))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(&shifted)?;
+    Ok(bits[COORDINATE_BITS].clone())
+}
+
+/// Bits of a shot coordinate, enough for the fields 1 to 10
+pub const SHOT_COORDINATE_BITS: usize = 4;
+
+/// Little-endian bits of a shot coordinate, which has to be at most ten. Zero marks an unused slot
+pub fn shot_coordinate_bits<F: PrimeField>(var: &FpVar<F>) -> Result<Vec<Boolean<F>>> {
+    let cs = var.cs();
+    let bits = (0..SHOT_COORDINATE_BITS)
+        .map(|i| Boolean::new_witness(ns!(cs, "bit"), || Ok(var.value()?.into_bigint().get_bit(i))))
+        .collect::<Result<Vec<_>>>()?;
+    Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(var)?;
+    enforce_at_most_ten(&bits)?;
+    Ok(bits)
+}
+
+/// A 4-bit number is above 10 if its top bit is set together with the next one, or with both lowest ones
+pub fn enforce_at_most_ten<F: PrimeField>(bits: &[Boolean<F>]) -> Result<()> {
+    let above_ten = bits[3].and(&bits[2].or(&bits[1].and(&bits[0])?)?)?;
+    above_ten.enforce_equal(&Boolean::FALSE)
+}
+
+/// Whether the ship covers the field. The field coordinates have to be below 2^COORDINATE_BITS
+pub fn is_ship_occupying_field<F: PrimeField>(
+    ship: &ShipBytes<F>,
+    field_x: &FpVar<F>,
+    field_y: &FpVar<F>,
+) -> Result<Boolean<F>> {
+    // A vertical ship has the column of the field and spans its row, a horizontal one the other way round
+    let ship_across = FpVar::conditionally_select(&ship.is_vertical, &ship.x, &ship.y)?;
+    let ship_along = FpVar::conditionally_select(&ship.is_vertical, &ship.y, &ship.x)?;
+    let field_across = FpVar::conditionally_select(&ship.is_vertical, field_x, field_y)?;
+    let field_along = FpVar::conditionally_select(&ship.is_vertical, field_y, field_x)?;
+
+    let ship_end = &ship_along + &ship.size - FpVar::one();
+    Boolean::kary_and(&[
+        ship_across.is_eq(&field_across)?,
+        is_le_small(&ship_along, &field_along)?,
+        is_le_small(&field_along, &ship_end)?,
+    ])
+}
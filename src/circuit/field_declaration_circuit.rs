@@ -1,5 +1,4 @@
-use ark_bls12_381::Fr;
-use ark_groth16::Groth16;
+use ark_ff::PrimeField;
 use ark_r1cs_std::alloc::AllocVar;
 use ark_r1cs_std::boolean::Boolean;
 use ark_r1cs_std::eq::EqGadget;
@@ -9,18 +8,19 @@ use ark_r1cs_std::select::CondSelectGadget;
 use ark_r1cs_std::uint8::UInt8;
 use ark_relations::ns;
 use ark_relations::r1cs::{ConstraintSystemRef, Result};
-use ark_serialize::CanonicalSerialize;
-use ark_snark::CircuitSpecificSetupSNARK;
 use ark_std::rand::rngs::StdRng;
 use ark_std::rand::SeedableRng;
 use std::cmp::Ordering;
-use std::fs::File;
 
-pub type Curve = ark_bls12_381::Bls12_381;
-pub type CircuitField = Fr;
+pub use crate::circuit::commons::CircuitField;
+pub use crate::crypto::backend::Curve;
 
 use crate::circuit::commons::ShipVars;
+use crate::crypto::backend::{Backend, Parameters, ProvingBackend};
+use crate::crypto::keys::write_keys;
 use crate::model::{Board, Direction, FieldState, Ship};
+use crate::utils::log::{Log, Logger};
+use crate::utils::result::Res;
 
 use super::board_declaration_circuit::BoardDeclarationCircuit;
 use super::commons::{compute_hash, create_ship_vars};
@@ -49,34 +49,32 @@ impl From<(BoardDeclarationCircuit, u8, u8)> for FieldDeclarationCircuit {
     }
 }
 
-impl ark_relations::r1cs::ConstraintSynthesizer<CircuitField> for FieldDeclarationCircuit {
-    fn generate_constraints(self, cs: ConstraintSystemRef<CircuitField>) -> Result<()> {
+impl<F: PrimeField> ark_relations::r1cs::ConstraintSynthesizer<F> for FieldDeclarationCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
         // Generate needed constant
-        let ten = FpVar::new_constant(ns!(cs, "10"), CircuitField::from(10))?;
+        let ten = FpVar::new_constant(ns!(cs, "10"), F::from(10u8))?;
 
         // Create private variables for each ship
-        let ships_vars: [ShipVars; 15] = self
+        let ships_vars: [ShipVars<F>; 15] = self
             .board
             .ships
             .map(|ship| create_ship_vars(&ship, &cs).unwrap());
         // Create private variable for hash salt
-        let salt_vars: [UInt8<CircuitField>; 32] = self
+        let salt_vars: [UInt8<F>; 32] = self
             .salt
             .map(|bit| UInt8::new_witness(ns!(cs, "salt"), || Ok(bit)).unwrap());
         // Create input for hash of ships
-        let hash_vars: [UInt8<CircuitField>; 32] = self
+        let hash_vars: [UInt8<F>; 32] = self
             .hash
             .map(|bit| UInt8::new_input(ns!(cs, "hash"), || Ok(bit)).unwrap());
 
         // Create input for field coordinates
-        let field_x_var =
-            FpVar::new_input(ns!(cs, "field_x"), || Ok(CircuitField::from(self.field_x)))?;
-        let field_y_var =
-            FpVar::new_input(ns!(cs, "field_y"), || Ok(CircuitField::from(self.field_y)))?;
+        let field_x_var = FpVar::new_input(ns!(cs, "field_x"), || Ok(F::from(self.field_x)))?;
+        let field_y_var = FpVar::new_input(ns!(cs, "field_y"), || Ok(F::from(self.field_y)))?;
 
         // Create input for the field state
         let field_state_var = FpVar::new_input(ns!(cs, "field_state"), || {
-            Ok(CircuitField::from(self.field_state as u8))
+            Ok(F::from(self.field_state as u8))
         })?;
 
         //--------------------------
@@ -106,7 +104,7 @@ impl ark_relations::r1cs::ConstraintSynthesizer<CircuitField> for FieldDeclarati
 
         //-------------------------------
         // Check if the field state is correct
-        let mut is_field_occupied: Boolean<CircuitField> = Boolean::FALSE;
+        let mut is_field_occupied: Boolean<F> = Boolean::FALSE;
 
         // For every ship check if it occupies given field
         ships_vars.iter().for_each(|ship_vars| {
@@ -123,11 +121,11 @@ impl ark_relations::r1cs::ConstraintSynthesizer<CircuitField> for FieldDeclarati
     }
 }
 
-fn is_ship_occupying_field(
-    ship_vars: &ShipVars,
-    field_x_var: &FpVar<CircuitField>,
-    field_y_var: &FpVar<CircuitField>,
-) -> Result<Boolean<CircuitField>> {
+fn is_ship_occupying_field<F: PrimeField>(
+    ship_vars: &ShipVars<F>,
+    field_x_var: &FpVar<F>,
+    field_y_var: &FpVar<F>,
+) -> Result<Boolean<F>> {
     // ship.x <= field_x <= ship.right_x && ship.y <= field_y <= ship.lower_y
     // If vertical then ship.right_x = ship.x, ship.lower_y = ship.y + ship.size - 1;
     // If horizontal then ship.right_x = ship.x + ship.size - 1, ship.lower_y = ship.y,
@@ -158,7 +156,9 @@ fn is_ship_occupying_field(
     Boolean::and(&x_condition, &y_condition)
 }
 
-pub fn generate_keys() {
+/// Generate the keys of the circuit, with their checksums, in the directory.
+/// A universal setup derives them from the shared parameters
+pub fn generate_keys(logger: &Logger, dir: &str, params: &Parameters) -> Res<()> {
     let mut rng = StdRng::seed_from_u64(1);
 
     let ships = [Ship {
@@ -179,15 +179,12 @@ pub fn generate_keys() {
 
     let now = std::time::Instant::now();
 
-    let (pk, vk) = Groth16::<Curve>::setup(dummy_circuit, &mut rng).unwrap();
+    let (pk, vk) = Backend::setup_with_parameters(dummy_circuit, params, &mut rng)?;
 
-    println!("Keys generated");
-    let elapsed = now.elapsed();
-    println!("Elapsed: {:.2?}", elapsed);
+    logger.info(&format!(
+        "Keys generated in {dir}, elapsed: {:.2?}",
+        now.elapsed()
+    ))?;
 
-    let vk_file = File::create("keys/field_declaration/vk.bin").unwrap();
-    vk.serialize_uncompressed(vk_file).unwrap();
-
-    let pk_file = File::create("keys/field_declaration/pk.bin").unwrap();
-    pk.serialize_uncompressed(pk_file).unwrap();
+    write_keys::<Backend>(dir, &vk, &pk)
 }
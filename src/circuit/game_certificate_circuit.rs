@@ -0,0 +1,277 @@
+use ark_bls12_377::constraints::PairingVar;
+use ark_bls12_377::Bls12_377;
+use ark_bw6_761::BW6_761;
+use ark_crypto_primitives::snark::{BooleanInputVar, SNARKGadget};
+use ark_ff::PrimeField;
+use ark_groth16::constraints::{Groth16VerifierGadget, PreparedVerifyingKeyVar, ProofVar};
+use ark_groth16::PreparedVerifyingKey;
+use ark_r1cs_std::alloc::AllocVar;
+use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::fields::FieldVar;
+use ark_relations::ns;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, Result, SynthesisError};
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::SeedableRng;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::crypto::backend::{Groth16Backend, ProvingBackend};
+use crate::crypto::keys::write_keys;
+use crate::crypto::proofs::PublicInput;
+use crate::model::FieldState;
+use crate::utils::log::{Log, Logger};
+use crate::utils::result::{Er, Res};
+
+use super::commons::{enforce_at_most_ten, SHIPS_SIZES, SHOT_COORDINATE_BITS};
+use super::shot_history_circuit::MAX_SHOTS;
+
+/// Backend of the game proofs composed into a certificate. The game has to be built with the `bls12-377` feature
+pub type InnerBackend = Groth16Backend<Bls12_377>;
+/// Backend of the certificates. The scalar field of BW6-761 is the base field of BLS12-377,
+/// so the proofs of the game can be verified inside its circuits
+pub type CertificateBackend = Groth16Backend<BW6_761>;
+pub type CertificateField = <CertificateBackend as ProvingBackend>::Field;
+
+pub type InnerProof = <InnerBackend as ProvingBackend>::Proof;
+type Verifier = Groth16VerifierGadget<Bls12_377, PairingVar>;
+
+/// Commitment of one player with the proofs about it, all made during the game
+#[derive(Clone, Debug)]
+pub struct CertifiedBoard {
+    pub hash: [u8; 32],
+    /// Proof of `BoardDeclarationCircuit`
+    pub board_proof: InnerProof,
+    /// Shots fired at the board, with the answers of its owner
+    pub shots: Vec<(u8, u8, FieldState)>,
+    /// Proof of `ShotHistoryCircuit` for the shots
+    pub history_proof: InnerProof,
+}
+
+/// Proves that both boards were valid, that every answer agreed with its board, that no field was shot twice
+/// and who sank all the ships of the opponent, by verifying the proofs made during the game
+#[derive(Clone, Debug)]
+pub struct GameCertificateCircuit {
+    pub board_vk: PreparedVerifyingKey<Bls12_377>,
+    pub history_vk: PreparedVerifyingKey<Bls12_377>,
+    pub host: CertifiedBoard,
+    pub client: CertifiedBoard,
+    pub host_won: bool,
+    /// Digest of the game id and the names of the players, see `players_digest`
+    pub players: [u8; 32],
+}
+
+/// Public inputs of the certificate: each hash packed into one field element, then the winner and the players
+#[derive(Debug, Clone, Copy)]
+pub struct CertificateInput {
+    pub host_hash: [u8; 32],
+    pub client_hash: [u8; 32],
+    pub host_won: bool,
+    pub players: [u8; 32],
+}
+
+impl From<CertificateInput> for PublicInput<CertificateField> {
+    fn from(value: CertificateInput) -> Self {
+        PublicInput(vec![
+            CertificateField::from_le_bytes_mod_order(&value.host_hash),
+            CertificateField::from_le_bytes_mod_order(&value.client_hash),
+            CertificateField::from(value.host_won),
+            CertificateField::from_le_bytes_mod_order(&value.players),
+        ])
+    }
+}
+
+/// Binds a certificate to the game and to the names of the host and the client
+pub fn players_digest(game_id: &str, host: &str, client: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    // Lengths keep the boundaries of the names, so they cannot be shifted from one to the other
+    for part in [game_id, host, client] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+struct ShotBits<F: PrimeField> {
+    x: Vec<Boolean<F>>,
+    y: Vec<Boolean<F>>,
+    state: Boolean<F>,
+}
+
+impl ConstraintSynthesizer<CertificateField> for GameCertificateCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<CertificateField>) -> Result<()> {
+        let board_vk =
+            PreparedVerifyingKeyVar::new_constant(ns!(cs, "board_vk"), self.board_vk.clone())?;
+        let history_vk =
+            PreparedVerifyingKeyVar::new_constant(ns!(cs, "history_vk"), self.history_vk.clone())?;
+
+        let host_sunk = enforce_board(&cs, &board_vk, &history_vk, &self.host)?;
+        let client_sunk = enforce_board(&cs, &board_vk, &history_vk, &self.client)?;
+
+        // The winner sank all the ships of the opponent and still has some afloat
+        let host_won = Boolean::new_input(ns!(cs, "host_won"), || Ok(self.host_won))?;
+        host_won.enforce_equal(&client_sunk)?;
+        host_won.not().enforce_equal(&host_sunk)?;
+
+        // Takes part in no other constraint, the QAP reduction of Groth16 still binds every public input to the proof
+        let _players = FpVar::new_input(ns!(cs, "players"), || {
+            Ok(CertificateField::from_le_bytes_mod_order(&self.players))
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Verify the proofs about the board and check the shots fired at it. Returns whether all its ships were sunk
+fn enforce_board(
+    cs: &ConstraintSystemRef<CertificateField>,
+    board_vk: &PreparedVerifyingKeyVar<Bls12_377, PairingVar>,
+    history_vk: &PreparedVerifyingKeyVar<Bls12_377, PairingVar>,
+    board: &CertifiedBoard,
+) -> Result<Boolean<CertificateField>> {
+    if board.shots.len() > MAX_SHOTS {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+
+    // The hash is public, the inner proofs take it bit by bit
+    let hash_bits = bits_witness(cs, &board.hash, 8)?;
+    let hash = FpVar::new_input(ns!(cs, "hash"), || {
+        Ok(CertificateField::from_le_bytes_mod_order(&board.hash))
+    })?;
+    Boolean::le_bits_to_fp_var(&hash_bits)?.enforce_equal(&hash)?;
+    let hash_input: Vec<Vec<Boolean<CertificateField>>> =
+        hash_bits.iter().map(|bit| vec![bit.clone()]).collect();
+
+    let board_proof =
+        ProofVar::new_witness(ns!(cs, "board_proof"), || Ok(board.board_proof.clone()))?;
+    Verifier::verify_with_processed_vk(
+        board_vk,
+        &BooleanInputVar::new(hash_input.clone()),
+        &board_proof,
+    )?
+    .enforce_equal(&Boolean::TRUE)?;
+
+    // Unused slots hold the shot (0, 0), as in the public input of the history proof
+    let padding = (0, 0, FieldState::Empty);
+    let shots = board
+        .shots
+        .iter()
+        .chain(std::iter::repeat(&padding))
+        .take(MAX_SHOTS)
+        .map(|&(x, y, state)| {
+            Ok(ShotBits {
+                x: bits_witness(cs, &[x], SHOT_COORDINATE_BITS)?,
+                y: bits_witness(cs, &[y], SHOT_COORDINATE_BITS)?,
+                state: Boolean::new_witness(
+                    ns!(cs, "state"),
+                    || Ok(state == FieldState::Occupied),
+                )?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut history_input = hash_input;
+    for shot in &shots {
+        history_input.push(shot.x.clone());
+        history_input.push(shot.y.clone());
+        history_input.push(vec![shot.state.clone()]);
+    }
+    let history_proof =
+        ProofVar::new_witness(ns!(cs, "history_proof"), || Ok(board.history_proof.clone()))?;
+    Verifier::verify_with_processed_vk(
+        history_vk,
+        &BooleanInputVar::new(history_input),
+        &history_proof,
+    )?
+    .enforce_equal(&Boolean::TRUE)?;
+
+    let used = shots
+        .iter()
+        .map(|shot| Boolean::kary_or(&shot.x))
+        .collect::<Result<Vec<_>>>()?;
+    for (shot, used) in shots.iter().zip(&used) {
+        // 1 <= x, y <= 10 for the shots, the padding is all zeros
+        enforce_at_most_ten(&shot.x)?;
+        enforce_at_most_ten(&shot.y)?;
+        Boolean::kary_or(&shot.y)?.enforce_equal(used)?;
+        shot.state
+            .and(&used.not())?
+            .enforce_equal(&Boolean::FALSE)?;
+    }
+    // Shots fill the first slots
+    for pair in used.windows(2) {
+        pair[1]
+            .and(&pair[0].not())?
+            .enforce_equal(&Boolean::FALSE)?;
+    }
+
+    // No field is shot twice
+    let fields = shots
+        .iter()
+        .map(|shot| Boolean::le_bits_to_fp_var(&[shot.x.clone(), shot.y.clone()].concat()))
+        .collect::<Result<Vec<_>>>()?;
+    for j in 1..fields.len() {
+        for i in 0..j {
+            fields[i].conditional_enforce_not_equal(&fields[j], &used[j])?;
+        }
+    }
+
+    let hits = shots.iter().fold(FpVar::zero(), |sum, shot| {
+        sum + FpVar::from(shot.state.clone())
+    });
+    let ship_fields: usize = SHIPS_SIZES.iter().sum();
+    hits.is_eq(&FpVar::constant(CertificateField::from(ship_fields as u64)))
+}
+
+/// Little-endian bits of the bytes, `bits` of each byte
+fn bits_witness<F: PrimeField>(
+    cs: &ConstraintSystemRef<F>,
+    bytes: &[u8],
+    bits: usize,
+) -> Result<Vec<Boolean<F>>> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..bits).map(move |i| byte >> i & 1 == 1))
+        .map(|bit| Boolean::new_witness(ns!(cs, "bit"), || Ok(bit)))
+        .collect()
+}
+
+/// Generate the keys of the certificates for the verifying keys of the game circuits
+pub fn generate_keys(
+    logger: &Logger,
+    dir: &str,
+    board_vk: &PreparedVerifyingKey<Bls12_377>,
+    history_vk: &PreparedVerifyingKey<Bls12_377>,
+) -> Res<()> {
+    // Anyone knowing the randomness of the setup could certify any winner
+    let mut rng = StdRng::from_rng(OsRng).map_err(|e| Er {
+        message: format!("Could not seed the setup: {e}"),
+    })?;
+
+    let dummy_board = CertifiedBoard {
+        hash: [0; 32],
+        board_proof: InnerProof::default(),
+        shots: vec![],
+        history_proof: InnerProof::default(),
+    };
+    let dummy_circuit = GameCertificateCircuit {
+        board_vk: board_vk.clone(),
+        history_vk: history_vk.clone(),
+        host: dummy_board.clone(),
+        client: dummy_board,
+        host_won: true,
+        players: [0; 32],
+    };
+
+    let now = std::time::Instant::now();
+
+    let (pk, vk) = CertificateBackend::setup(dummy_circuit, &mut rng)?;
+
+    logger.info(&format!(
+        "Keys generated in {dir}, elapsed: {:.2?}",
+        now.elapsed()
+    ))?;
+
+    write_keys::<CertificateBackend>(dir, &vk, &pk)
+}
@@ -0,0 +1,200 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use ark_ff::PrimeField;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    crypto::{backend::ProvingBackend, proofs::PublicInput},
+    model::{Board, Direction, Ship},
+    utils::result::{Er, Res},
+};
+
+use super::{
+    board_declaration_circuit::BoardDeclarationCircuit,
+    field_declaration_circuit::FieldDeclarationCircuit,
+    shot_history_circuit::{ShotHistoryCircuit, ShotHistoryInput},
+};
+
+pub const BOARD_DECLARATION: &str = "board_declaration";
+pub const FIELD_DECLARATION: &str = "field_declaration";
+pub const SHOT_HISTORY: &str = "shot_history";
+
+/// Size of the constraint system of a circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstraintCount {
+    pub constraints: usize,
+    pub witness_variables: usize,
+    /// Public inputs, including the constant one
+    pub instance_variables: usize,
+}
+
+/// Synthesize the circuit without creating keys, to measure its size
+pub fn count_constraints<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+) -> Res<ConstraintCount> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    if !cs.is_satisfied()? {
+        return Err(Er {
+            message: "The sample circuit is not satisfied".to_owned(),
+        });
+    }
+    Ok(ConstraintCount {
+        constraints: cs.num_constraints(),
+        witness_variables: cs.num_witness_variables(),
+        instance_variables: cs.num_instance_variables(),
+    })
+}
+
+/// Cost of a circuit. Times are measured only if requested, the setup takes minutes
+#[derive(Debug, Clone)]
+pub struct CircuitReport {
+    pub circuit: String,
+    pub count: ConstraintCount,
+    pub setup: Option<Duration>,
+    pub prove: Option<Duration>,
+    pub verify: Option<Duration>,
+}
+
+impl CircuitReport {
+    pub fn header() -> String {
+        format!(
+            "{:<18} {:>11} {:>9} {:>7} {:>10} {:>10} {:>10}",
+            "circuit", "constraints", "witness", "inputs", "setup", "prove", "verify"
+        )
+    }
+}
+
+impl Display for CircuitReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = |t: Option<Duration>| t.map_or("-".to_owned(), |t| format!("{t:.2?}"));
+        write!(
+            f,
+            "{:<18} {:>11} {:>9} {:>7} {:>10} {:>10} {:>10}",
+            self.circuit,
+            self.count.constraints,
+            self.count.witness_variables,
+            self.count.instance_variables,
+            time(self.setup),
+            time(self.prove),
+            time(self.verify)
+        )
+    }
+}
+
+/// Count the constraints of the circuit and, with `timed`, measure a setup, a proof and its verification
+pub fn measure<B: ProvingBackend, C: ConstraintSynthesizer<B::Field> + Clone>(
+    name: &str,
+    circuit: C,
+    input: &PublicInput<B::Field>,
+    timed: bool,
+) -> Res<CircuitReport> {
+    let mut report = CircuitReport {
+        circuit: name.to_owned(),
+        count: count_constraints(circuit.clone())?,
+        setup: None,
+        prove: None,
+        verify: None,
+    };
+    if !timed {
+        return Ok(report);
+    }
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let now = Instant::now();
+    let (pk, vk) = B::setup(circuit.clone(), &mut rng)?;
+    report.setup = Some(now.elapsed());
+    let pvk = B::prepare(&vk);
+
+    let now = Instant::now();
+    let proof = B::prove(&pk, circuit, &mut rng)?;
+    report.prove = Some(now.elapsed());
+
+    let now = Instant::now();
+    let valid = B::verify(&pvk, &input.0, &proof)?;
+    report.verify = Some(now.elapsed());
+    if !valid {
+        return Err(Er {
+            message: format!("The proof of the sample {name} circuit does not verify"),
+        });
+    }
+    Ok(report)
+}
+
+/// Reports of all the circuits, proven with the backend
+pub fn report_all<B: ProvingBackend>(timed: bool) -> Res<Vec<CircuitReport>> {
+    let (board, field) = sample_circuits();
+    let history = sample_shot_history(board);
+    let history_input = shot_history_input(&history)?;
+    Ok(vec![
+        measure::<B, _>(BOARD_DECLARATION, board, &board_input(&board), timed)?,
+        measure::<B, _>(FIELD_DECLARATION, field, &field_input(&field), timed)?,
+        measure::<B, _>(SHOT_HISTORY, history, &history_input, timed)?,
+    ])
+}
+
+/// A valid placement of all the ships
+pub fn sample_board() -> Board {
+    use Direction::{Horizontal as H, Vertical as V};
+    let ships = [
+        (1, 1, 1, V),
+        (1, 3, 1, V),
+        (1, 5, 1, V),
+        (1, 7, 1, V),
+        (1, 9, 1, V),
+        (3, 1, 2, V),
+        (3, 4, 2, V),
+        (3, 7, 2, V),
+        (3, 10, 2, H),
+        (5, 1, 3, V),
+        (5, 5, 3, V),
+        (6, 10, 3, H),
+        (7, 1, 4, V),
+        (9, 1, 4, V),
+        (10, 6, 5, V),
+    ]
+    .map(|(x, y, size, direction)| Ship {
+        x,
+        y,
+        size,
+        direction,
+    });
+    Board { ships }
+}
+
+/// Satisfied instances of the circuits, declaring the sample board and one of its fields
+pub fn sample_circuits() -> (BoardDeclarationCircuit, FieldDeclarationCircuit) {
+    let board = BoardDeclarationCircuit::from(sample_board());
+    (board, FieldDeclarationCircuit::from((board, 3, 4)))
+}
+
+/// Answers to shots at every field of the board
+pub fn sample_shot_history(board: BoardDeclarationCircuit) -> ShotHistoryCircuit {
+    let shots = (1..=10)
+        .flat_map(|x| (1..=10).map(move |y| (x, y)))
+        .map(|(x, y)| (x, y, board.board.get_field_state(x, y)))
+        .collect();
+    ShotHistoryCircuit::from((board, shots))
+}
+
+pub fn board_input<F: PrimeField>(circuit: &BoardDeclarationCircuit) -> PublicInput<F> {
+    circuit.hash.to_vec().into()
+}
+
+pub fn field_input<F: PrimeField>(circuit: &FieldDeclarationCircuit) -> PublicInput<F> {
+    let input: PublicInput<F> = circuit.hash.to_vec().into();
+    input + circuit.field_x + circuit.field_y + circuit.field_state as u8
+}
+
+pub fn shot_history_input<F: PrimeField>(circuit: &ShotHistoryCircuit) -> Res<PublicInput<F>> {
+    ShotHistoryInput {
+        hash: circuit.hash,
+        shots: circuit.shots.clone(),
+    }
+    .into_public_input()
+}
@@ -0,0 +1,196 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::uint8::UInt8;
+use ark_relations::ns;
+use ark_relations::r1cs::{ConstraintSystemRef, Result, SynthesisError};
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::SeedableRng;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::commons::{
+    compute_bytes_hash, create_ship_bytes, is_ship_occupying_field, shot_coordinate_bits,
+    ShipBytes, SHIPS_SIZES,
+};
+use crate::crypto::backend::{Backend, Parameters, ProvingBackend};
+use crate::crypto::keys::write_keys;
+use crate::crypto::proofs::PublicInput;
+use crate::model::{Board, Direction, FieldState, Ship};
+use crate::utils::log::{Log, Logger};
+use crate::utils::result::{Er, Res};
+
+use super::board_declaration_circuit::BoardDeclarationCircuit;
+
+/// Shots covered by one proof. Every field can be shot at most once
+pub const MAX_SHOTS: usize = 100;
+
+/// Proves that all the answers given to the opponent's shots agree with the committed board,
+/// and that the shots are distinct fields of the board
+#[derive(Clone, Debug)]
+pub struct ShotHistoryCircuit {
+    pub board: Board,
+    pub salt: [u8; 32],
+    pub hash: [u8; 32],
+    /// Shots with the answers given to them, at most `MAX_SHOTS`
+    pub shots: Vec<(u8, u8, FieldState)>,
+}
+
+impl From<(BoardDeclarationCircuit, Vec<(u8, u8, FieldState)>)> for ShotHistoryCircuit {
+    fn from((board_circ, shots): (BoardDeclarationCircuit, Vec<(u8, u8, FieldState)>)) -> Self {
+        ShotHistoryCircuit {
+            board: board_circ.board,
+            salt: board_circ.salt,
+            hash: board_circ.hash,
+            shots,
+        }
+    }
+}
+
+/// Public inputs of the circuit: the board hash, then every shot and its answer.
+/// Unused slots are filled with the shot (0, 0), which is not a field of the board, after all the shots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShotHistoryInput {
+    pub hash: [u8; 32],
+    pub shots: Vec<(u8, u8, FieldState)>,
+}
+
+impl ShotHistoryInput {
+    pub fn into_public_input<F: PrimeField>(self) -> Res<PublicInput<F>> {
+        if self.shots.len() > MAX_SHOTS {
+            return Err(too_many_shots(self.shots.len()));
+        }
+        let mut input: PublicInput<F> = self.hash.to_vec().into();
+        let padding = (0, 0, FieldState::Empty);
+        for (x, y, state) in self
+            .shots
+            .into_iter()
+            .chain(std::iter::repeat(padding))
+            .take(MAX_SHOTS)
+        {
+            input = input + x + y + state as u8;
+        }
+        Ok(input)
+    }
+}
+
+fn too_many_shots(count: usize) -> Er {
+    Er {
+        message: format!("{count} shots cannot be proven at once, the limit is {MAX_SHOTS}"),
+    }
+}
+
+impl<F: PrimeField> ark_relations::r1cs::ConstraintSynthesizer<F> for ShotHistoryCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<()> {
+        if self.shots.len() > MAX_SHOTS {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let ships: Vec<ShipBytes<F>> = self
+            .board
+            .ships
+            .iter()
+            .map(|ship| create_ship_bytes(ship, &cs))
+            .collect::<Result<_>>()?;
+        let salt_vars: [UInt8<F>; 32] = self
+            .salt
+            .map(|byte| UInt8::new_witness(ns!(cs, "salt"), || Ok(byte)).unwrap());
+        let hash_vars: [UInt8<F>; 32] = self
+            .hash
+            .map(|byte| UInt8::new_input(ns!(cs, "hash"), || Ok(byte)).unwrap());
+
+        // Check that the ships are the committed ones
+        let digest = compute_bytes_hash(&ships, &salt_vars)?;
+        hash_vars.enforce_equal(&digest.0)?;
+
+        // Check every answer
+        let padding = (0, 0, FieldState::Empty);
+        let mut used = Vec::with_capacity(MAX_SHOTS);
+        let mut fields = Vec::with_capacity(MAX_SHOTS);
+        for &(x, y, state) in self
+            .shots
+            .iter()
+            .chain(std::iter::repeat(&padding))
+            .take(MAX_SHOTS)
+        {
+            let x = FpVar::new_input(ns!(cs, "shotX"), || Ok(F::from(x)))?;
+            let y = FpVar::new_input(ns!(cs, "shotY"), || Ok(F::from(y)))?;
+            let state = FpVar::new_input(ns!(cs, "state"), || Ok(F::from(state as u8)))?;
+
+            // 1 <= x, y <= 10 for the shots, the padding is all zeros
+            let x_bits = shot_coordinate_bits(&x)?;
+            let y_bits = shot_coordinate_bits(&y)?;
+            let is_used = Boolean::kary_or(&x_bits)?;
+            Boolean::kary_or(&y_bits)?.enforce_equal(&is_used)?;
+
+            // The padding is answered as empty
+            let occupied = ships
+                .iter()
+                .map(|ship| is_ship_occupying_field(ship, &x, &y))
+                .collect::<Result<Vec<_>>>()?;
+            let occupied = Boolean::kary_or(&occupied)?.and(&is_used)?;
+            state.enforce_equal(&occupied.into())?;
+
+            fields.push(Boolean::le_bits_to_fp_var(&[x_bits, y_bits].concat())?);
+            used.push(is_used);
+        }
+
+        // Shots fill the first slots
+        for pair in used.windows(2) {
+            pair[1]
+                .and(&pair[0].not())?
+                .enforce_equal(&Boolean::FALSE)?;
+        }
+
+        // No field is shot twice
+        for j in 1..fields.len() {
+            for i in 0..j {
+                fields[i].conditional_enforce_not_equal(&fields[j], &used[j])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Generate the keys of the circuit, with their checksums, in the directory.
+/// A universal setup derives them from the shared parameters
+pub fn generate_keys(logger: &Logger, dir: &str, params: &Parameters) -> Res<()> {
+    // Anyone knowing the randomness of the setup could prove answers disagreeing with the board
+    let mut rng = StdRng::from_rng(OsRng).map_err(|e| Er {
+        message: format!("Could not seed the setup: {e}"),
+    })?;
+
+    let mut ships = [Ship {
+        x: 1,
+        y: 1,
+        size: 1,
+        direction: Direction::Vertical,
+    }; 15];
+    SHIPS_SIZES
+        .iter()
+        .zip(0..15)
+        .for_each(|(ship_size, ship_index)| {
+            ships[ship_index].size = *ship_size as u8;
+        });
+
+    let dummy_circuit = ShotHistoryCircuit {
+        board: Board { ships },
+        salt: [0; 32],
+        hash: [0; 32],
+        shots: vec![],
+    };
+
+    let now = std::time::Instant::now();
+
+    let (pk, vk) = Backend::setup_with_parameters(dummy_circuit, params, &mut rng)?;
+
+    logger.info(&format!(
+        "Keys generated in {dir}, elapsed: {:.2?}",
+        now.elapsed()
+    ))?;
+
+    write_keys::<Backend>(dir, &vk, &pk)
+}
@@ -0,0 +1,229 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto::backend::BackendKind,
+    net::connection::ConnectionLimits,
+    utils::{
+        log::{file_sink::LOG_DIR, Level},
+        result::{Er, Res},
+    },
+};
+
+pub const CONFIG_FILE: &str = "config.toml";
+/// Subdirectory of the platform config directory, e.g. `~/.config/battleships` on Linux
+pub const CONFIG_DIR: &str = "battleships";
+
+/// Interface started when no subcommand is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UiKind {
+    #[default]
+    Gui,
+    Cli,
+    Tui,
+    Headless,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// The original blue look
+    #[default]
+    Ocean,
+    Dark,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ocean" => Ok(Theme::Ocean),
+            "dark" => Ok(Theme::Dark),
+            _ => Err(format!("Unknown theme `{s}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiConfig {
+    pub maximized: bool,
+    pub theme: Theme,
+    pub assets_dir: String,
+}
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        GuiConfig {
+            maximized: true,
+            theme: Theme::default(),
+            assets_dir: "assets".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Lowest level written to the log file
+    pub level: Level,
+    pub dir: String,
+    /// Size in bytes after which the log file is rotated
+    pub max_file_size: u64,
+    /// Number of rotated files kept
+    pub max_files: usize,
+    /// Print `tracing` events to the standard error
+    pub trace: bool,
+}
+
+/// Limits of the connections to other players and to the relay, see `ConnectionLimits`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetConfig {
+    /// Longer messages are refused, in bytes
+    pub max_frame_size: usize,
+    /// Seconds allowed for the rest of a started message and for a handshake
+    pub read_timeout: u64,
+    /// Seconds without any message after which the peer is considered dead
+    pub idle_timeout: u64,
+    /// Seconds without sending anything after which a ping is sent
+    pub heartbeat_interval: u64,
+}
+
+impl NetConfig {
+    pub fn limits(&self) -> ConnectionLimits {
+        ConnectionLimits {
+            max_frame_size: self.max_frame_size,
+            read_timeout: Duration::from_secs(self.read_timeout),
+            idle_timeout: Duration::from_secs(self.idle_timeout),
+            heartbeat_interval: Duration::from_secs(self.heartbeat_interval),
+        }
+    }
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        let limits = ConnectionLimits::default();
+        NetConfig {
+            max_frame_size: limits.max_frame_size,
+            read_timeout: limits.read_timeout.as_secs(),
+            idle_timeout: limits.idle_timeout.as_secs(),
+            heartbeat_interval: limits.heartbeat_interval.as_secs(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: Level::Debug,
+            dir: LOG_DIR.to_owned(),
+            max_file_size: 1024 * 1024,
+            max_files: 5,
+            trace: false,
+        }
+    }
+}
+
+/// Settings read from `config.toml`. Missing entries take their default values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub ui: UiKind,
+    /// Name used until another one is set in the game
+    pub player_name: Option<String>,
+    /// Directory of the zk-SNARK keys, unless given on the command line or in `BATTLESHIPS_KEYS_DIR`
+    pub keys_dir: Option<String>,
+    /// Address filled in when creating or joining a room
+    pub default_address: String,
+    /// Threads generating and verifying proofs, one per CPU core if not set
+    pub proof_workers: Option<usize>,
+    /// Prove all the answers to the opponent's shots at once when a game ends
+    pub history_proof: bool,
+    /// Generate the proofs of our fields in the background, before the opponent asks for them
+    pub precompute_proofs: bool,
+    /// Proof system of the keys and proofs, the opponent has to use the same one
+    pub proving_backend: BackendKind,
+    pub gui: GuiConfig,
+    pub log: LogConfig,
+    pub net: NetConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            ui: UiKind::default(),
+            player_name: None,
+            keys_dir: None,
+            default_address: "127.0.0.1:4000".to_owned(),
+            proof_workers: None,
+            history_proof: false,
+            precompute_proofs: false,
+            proving_backend: BackendKind::default(),
+            gui: GuiConfig::default(),
+            log: LogConfig::default(),
+            net: NetConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// `config.toml` in the platform config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Read the config file. A missing file gives the default config
+    pub fn load(path: &Path) -> Res<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content).map_err(|e| Er {
+                message: format!("Invalid config file {}: {}", path.display(), e.message),
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(content: &str) -> Res<Self> {
+        toml::from_str(content).map_err(|e| Er {
+            message: e.message().to_owned(),
+        })
+    }
+
+    pub fn to_toml(&self) -> Res<String> {
+        toml::to_string_pretty(self).map_err(|e| Er {
+            message: e.to_string(),
+        })
+    }
+}
+
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_toml() {
+            Ok(toml) => write!(f, "{toml}"),
+            Err(e) => write!(f, "{}", e.message),
+        }
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Set the config used by the whole game, after applying the command line flags. Can be done only once
+pub fn set_config(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The config set on start, or the default one
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
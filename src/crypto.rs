@@ -1,2 +1,5 @@
+pub mod backend;
+pub mod key_store;
 pub mod keys;
+pub mod marlin;
 pub mod proofs;
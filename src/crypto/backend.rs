@@ -0,0 +1,478 @@
+use std::{fmt::Debug, marker::PhantomData, str::FromStr};
+
+use ark_bls12_377::Bls12_377;
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_bw6_761::BW6_761;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{r1cs_to_qap::LibsnarkReduction, Groth16};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_snark::SNARK;
+use ark_std::rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::config,
+    utils::result::{Er, Res},
+};
+
+use super::marlin::{self, MarlinParameters, MarlinProof, MarlinProvingKey, MarlinVerifyingKey};
+
+/// Proof systems the game can prove with, chosen in the config. Both players have to use the same one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Needs a trusted setup for every circuit, but has the smallest proofs and the fastest verification
+    #[default]
+    Groth16,
+    /// One universal setup serves all the circuits, also after the rules change, at the cost of larger
+    /// and slower proofs
+    Marlin,
+}
+
+impl BackendKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::Groth16 => "groth16",
+            BackendKind::Marlin => "marlin",
+        }
+    }
+
+    /// Name announced in the feature list of the handshake
+    pub fn feature(&self) -> String {
+        format!("proof:{}", self.name())
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "groth16" => Ok(BackendKind::Groth16),
+            "marlin" => Ok(BackendKind::Marlin),
+            _ => Err(format!("Unknown proving backend `{s}`")),
+        }
+    }
+}
+
+/// Pairing-friendly curves the game can be built with, chosen with the `bn254` and `bls12-377` features
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CurveKind {
+    #[default]
+    #[serde(rename = "bls12-381")]
+    Bls12_381,
+    /// Faster proving at a lower security level
+    #[serde(rename = "bn254")]
+    Bn254,
+    /// Its proofs can be verified in circuits over BW6-761, which makes game certificates possible
+    #[serde(rename = "bls12-377")]
+    Bls12_377,
+    /// Only for the game certificates
+    #[serde(rename = "bw6-761")]
+    Bw6_761,
+}
+
+impl CurveKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CurveKind::Bls12_381 => "bls12-381",
+            CurveKind::Bn254 => "bn254",
+            CurveKind::Bls12_377 => "bls12-377",
+            CurveKind::Bw6_761 => "bw6-761",
+        }
+    }
+
+    /// Name announced in the feature list of the handshake
+    pub fn feature(&self) -> String {
+        format!("curve:{}", self.name())
+    }
+}
+
+/// Pairing engine with a name recorded in the key metadata
+pub trait NamedCurve: Pairing {
+    const KIND: CurveKind;
+}
+
+impl NamedCurve for Bls12_381 {
+    const KIND: CurveKind = CurveKind::Bls12_381;
+}
+
+impl NamedCurve for Bn254 {
+    const KIND: CurveKind = CurveKind::Bn254;
+}
+
+impl NamedCurve for Bls12_377 {
+    const KIND: CurveKind = CurveKind::Bls12_377;
+}
+
+impl NamedCurve for BW6_761 {
+    const KIND: CurveKind = CurveKind::Bw6_761;
+}
+
+/// SNARK generating and verifying the proofs of the circuits, over the curves the game can be built with
+pub trait ProvingBackend: Clone + Debug + Send + Sync + 'static {
+    const CURVE: CurveKind;
+
+    /// Field of the circuit variables
+    type Field: PrimeField;
+
+    /// Setup shared by the keys of all the circuits, empty when every circuit has its own setup
+    type Parameters: CanonicalSerialize + CanonicalDeserialize + Send + Sync;
+    type ProvingKey: CanonicalSerialize + CanonicalDeserialize + Send + Sync;
+    type VerifyingKey: CanonicalSerialize + CanonicalDeserialize + PartialEq + Send + Sync;
+    /// Verifying key preprocessed for faster verification
+    type PreparedVerifyingKey: CanonicalSerialize + CanonicalDeserialize + Send + Sync;
+    type Proof: CanonicalSerialize + CanonicalDeserialize + Clone + Debug + Default + Send + Sync;
+
+    fn kind() -> BackendKind;
+
+    /// Degree of the shared parameters the circuit needs, 0 when they are not used
+    fn parameters_degree<C: ConstraintSynthesizer<Self::Field>>(circuit: C) -> Res<usize>;
+
+    /// Shared parameters supporting circuits up to the degree
+    fn parameters(degree: usize, rng: &mut StdRng) -> Res<Self::Parameters>;
+
+    /// Keys of the circuit from the shared parameters
+    fn setup_with_parameters<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        params: &Self::Parameters,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)>;
+
+    /// Keys of the circuit, from a setup done only for it
+    fn setup<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)>;
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey;
+
+    fn prove<C: ConstraintSynthesizer<Self::Field>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<Self::Proof>;
+
+    fn verify(
+        pvk: &Self::PreparedVerifyingKey,
+        input: &[Self::Field],
+        proof: &Self::Proof,
+    ) -> Res<bool>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Groth16Backend<E: NamedCurve>(PhantomData<E>);
+
+impl<E: NamedCurve> ProvingBackend for Groth16Backend<E> {
+    const CURVE: CurveKind = E::KIND;
+
+    type Field = E::ScalarField;
+
+    type Parameters = ();
+    type ProvingKey = <Groth16<E> as SNARK<E::ScalarField>>::ProvingKey;
+    type VerifyingKey = <Groth16<E> as SNARK<E::ScalarField>>::VerifyingKey;
+    type PreparedVerifyingKey = <Groth16<E> as SNARK<E::ScalarField>>::ProcessedVerifyingKey;
+    type Proof = <Groth16<E> as SNARK<E::ScalarField>>::Proof;
+
+    fn kind() -> BackendKind {
+        BackendKind::Groth16
+    }
+
+    fn parameters_degree<C: ConstraintSynthesizer<Self::Field>>(_circuit: C) -> Res<usize> {
+        Ok(0)
+    }
+
+    fn parameters(_degree: usize, _rng: &mut StdRng) -> Res<Self::Parameters> {
+        Ok(())
+    }
+
+    fn setup_with_parameters<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        _params: &Self::Parameters,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        Self::setup(circuit, rng)
+    }
+
+    fn setup<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        Ok(Groth16::<E, LibsnarkReduction>::circuit_specific_setup(
+            circuit, rng,
+        )?)
+    }
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey {
+        ark_groth16::prepare_verifying_key(vk)
+    }
+
+    fn prove<C: ConstraintSynthesizer<Self::Field>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<Self::Proof> {
+        Ok(Groth16::<E, LibsnarkReduction>::prove(pk, circuit, rng)?)
+    }
+
+    fn verify(
+        pvk: &Self::PreparedVerifyingKey,
+        input: &[Self::Field],
+        proof: &Self::Proof,
+    ) -> Res<bool> {
+        Ok(Groth16::<E, LibsnarkReduction>::verify_with_processed_vk(
+            pvk, input, proof,
+        )?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarlinBackend<E: NamedCurve>(PhantomData<E>);
+
+impl<E: NamedCurve> ProvingBackend for MarlinBackend<E> {
+    const CURVE: CurveKind = E::KIND;
+
+    type Field = E::ScalarField;
+
+    type Parameters = MarlinParameters<E>;
+    type ProvingKey = MarlinProvingKey<E>;
+    type VerifyingKey = MarlinVerifyingKey<E>;
+    type PreparedVerifyingKey = MarlinVerifyingKey<E>;
+    type Proof = MarlinProof<E>;
+
+    fn kind() -> BackendKind {
+        BackendKind::Marlin
+    }
+
+    fn parameters_degree<C: ConstraintSynthesizer<Self::Field>>(circuit: C) -> Res<usize> {
+        marlin::degree(circuit)
+    }
+
+    fn parameters(degree: usize, rng: &mut StdRng) -> Res<Self::Parameters> {
+        marlin::parameters(degree, rng)
+    }
+
+    fn setup_with_parameters<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        params: &Self::Parameters,
+        _rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        marlin::setup(circuit, params)
+    }
+
+    fn setup<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        marlin::circuit_specific_setup(circuit, rng)
+    }
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey {
+        vk.clone()
+    }
+
+    fn prove<C: ConstraintSynthesizer<Self::Field>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<Self::Proof> {
+        marlin::prove(pk, circuit, rng)
+    }
+
+    fn verify(
+        pvk: &Self::PreparedVerifyingKey,
+        input: &[Self::Field],
+        proof: &Self::Proof,
+    ) -> Res<bool> {
+        marlin::verify(pvk, input, proof)
+    }
+}
+
+/// Value of the backend selected in the config. It is serialized without a tag,
+/// so the Groth16 keys and proofs keep their format, and read as the one of the selected backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selected<G, M> {
+    Groth16(G),
+    Marlin(M),
+}
+
+impl<G: Default, M: Default> Default for Selected<G, M> {
+    fn default() -> Self {
+        match config().proving_backend {
+            BackendKind::Groth16 => Selected::Groth16(G::default()),
+            BackendKind::Marlin => Selected::Marlin(M::default()),
+        }
+    }
+}
+
+impl<G: CanonicalSerialize, M: CanonicalSerialize> CanonicalSerialize for Selected<G, M> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        match self {
+            Selected::Groth16(value) => value.serialize_with_mode(writer, compress),
+            Selected::Marlin(value) => value.serialize_with_mode(writer, compress),
+        }
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        match self {
+            Selected::Groth16(value) => value.serialized_size(compress),
+            Selected::Marlin(value) => value.serialized_size(compress),
+        }
+    }
+}
+
+impl<G: Valid, M: Valid> Valid for Selected<G, M> {
+    fn check(&self) -> Result<(), SerializationError> {
+        match self {
+            Selected::Groth16(value) => value.check(),
+            Selected::Marlin(value) => value.check(),
+        }
+    }
+}
+
+impl<G: CanonicalDeserialize, M: CanonicalDeserialize> CanonicalDeserialize for Selected<G, M> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(match config().proving_backend {
+            BackendKind::Groth16 => {
+                Selected::Groth16(G::deserialize_with_mode(reader, compress, validate)?)
+            }
+            BackendKind::Marlin => {
+                Selected::Marlin(M::deserialize_with_mode(reader, compress, validate)?)
+            }
+        })
+    }
+}
+
+/// Backend chosen by `proving_backend` in the config, over the curve `E`
+#[derive(Debug, Clone, Copy)]
+pub struct ConfiguredBackend<E: NamedCurve>(PhantomData<E>);
+
+type G16<E> = Groth16Backend<E>;
+type Mar<E> = MarlinBackend<E>;
+
+impl<E: NamedCurve> ProvingBackend for ConfiguredBackend<E> {
+    const CURVE: CurveKind = E::KIND;
+
+    type Field = E::ScalarField;
+
+    type Parameters = Selected<(), MarlinParameters<E>>;
+    type ProvingKey = Selected<<G16<E> as ProvingBackend>::ProvingKey, MarlinProvingKey<E>>;
+    type VerifyingKey = Selected<<G16<E> as ProvingBackend>::VerifyingKey, MarlinVerifyingKey<E>>;
+    type PreparedVerifyingKey =
+        Selected<<G16<E> as ProvingBackend>::PreparedVerifyingKey, MarlinVerifyingKey<E>>;
+    type Proof = Selected<<G16<E> as ProvingBackend>::Proof, MarlinProof<E>>;
+
+    fn kind() -> BackendKind {
+        config().proving_backend
+    }
+
+    fn parameters_degree<C: ConstraintSynthesizer<Self::Field>>(circuit: C) -> Res<usize> {
+        match Self::kind() {
+            BackendKind::Groth16 => G16::<E>::parameters_degree(circuit),
+            BackendKind::Marlin => Mar::<E>::parameters_degree(circuit),
+        }
+    }
+
+    fn parameters(degree: usize, rng: &mut StdRng) -> Res<Self::Parameters> {
+        Ok(match Self::kind() {
+            BackendKind::Groth16 => Selected::Groth16(G16::<E>::parameters(degree, rng)?),
+            BackendKind::Marlin => Selected::Marlin(Mar::<E>::parameters(degree, rng)?),
+        })
+    }
+
+    fn setup_with_parameters<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        params: &Self::Parameters,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        match params {
+            Selected::Groth16(params) => {
+                let (pk, vk) = G16::<E>::setup_with_parameters(circuit, params, rng)?;
+                Ok((Selected::Groth16(pk), Selected::Groth16(vk)))
+            }
+            Selected::Marlin(params) => {
+                let (pk, vk) = Mar::<E>::setup_with_parameters(circuit, params, rng)?;
+                Ok((Selected::Marlin(pk), Selected::Marlin(vk)))
+            }
+        }
+    }
+
+    fn setup<C: ConstraintSynthesizer<Self::Field>>(
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<(Self::ProvingKey, Self::VerifyingKey)> {
+        match Self::kind() {
+            BackendKind::Groth16 => {
+                let (pk, vk) = G16::<E>::setup(circuit, rng)?;
+                Ok((Selected::Groth16(pk), Selected::Groth16(vk)))
+            }
+            BackendKind::Marlin => {
+                let (pk, vk) = Mar::<E>::setup(circuit, rng)?;
+                Ok((Selected::Marlin(pk), Selected::Marlin(vk)))
+            }
+        }
+    }
+
+    fn prepare(vk: &Self::VerifyingKey) -> Self::PreparedVerifyingKey {
+        match vk {
+            Selected::Groth16(vk) => Selected::Groth16(G16::<E>::prepare(vk)),
+            Selected::Marlin(vk) => Selected::Marlin(Mar::<E>::prepare(vk)),
+        }
+    }
+
+    fn prove<C: ConstraintSynthesizer<Self::Field>>(
+        pk: &Self::ProvingKey,
+        circuit: C,
+        rng: &mut StdRng,
+    ) -> Res<Self::Proof> {
+        Ok(match pk {
+            Selected::Groth16(pk) => Selected::Groth16(G16::<E>::prove(pk, circuit, rng)?),
+            Selected::Marlin(pk) => Selected::Marlin(Mar::<E>::prove(pk, circuit, rng)?),
+        })
+    }
+
+    fn verify(
+        pvk: &Self::PreparedVerifyingKey,
+        input: &[Self::Field],
+        proof: &Self::Proof,
+    ) -> Res<bool> {
+        match (pvk, proof) {
+            (Selected::Groth16(pvk), Selected::Groth16(proof)) => {
+                G16::<E>::verify(pvk, input, proof)
+            }
+            (Selected::Marlin(pvk), Selected::Marlin(proof)) => Mar::<E>::verify(pvk, input, proof),
+            _ => Err(Er {
+                message: "The proof was made with another proving backend".to_owned(),
+            }),
+        }
+    }
+}
+
+/// Curve the game is built with.
+/// `bls12-377` takes precedence over `bn254`, so a build with all the features supports certificates
+#[cfg(not(any(feature = "bn254", feature = "bls12-377")))]
+pub type Curve = Bls12_381;
+#[cfg(all(feature = "bn254", not(feature = "bls12-377")))]
+pub type Curve = Bn254;
+#[cfg(feature = "bls12-377")]
+pub type Curve = Bls12_377;
+
+/// Backend the game is built with, proving with the system selected in the config
+pub type Backend = ConfiguredBackend<Curve>;
+
+pub type Proof = <Backend as ProvingBackend>::Proof;
+pub type Parameters = <Backend as ProvingBackend>::Parameters;
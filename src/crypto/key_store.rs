@@ -0,0 +1,289 @@
+use std::{fmt::Display, fs, sync::RwLock};
+
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::{
+        board_declaration_circuit, field_declaration_circuit,
+        game_certificate_circuit::{self, CertificateBackend, InnerBackend},
+        report::{sample_circuits, sample_shot_history},
+        shot_history_circuit,
+    },
+    utils::{
+        log::{Log, Logger},
+        result::{Er, Res},
+    },
+};
+
+use super::backend::{Backend, BackendKind, CurveKind, Parameters, ProvingBackend};
+use super::keys::{
+    read_parameters, read_proving_key, read_verifying_key, verifying_keys_fingerprint,
+    write_parameters, KeyMeta, CHECKSUM_FILE, KEY_FILES,
+};
+
+pub const DEFAULT_KEYS_DIR: &str = "keys";
+/// Environment variable overriding the key directory of the config file
+pub const KEYS_DIR_ENV: &str = "BATTLESHIPS_KEYS_DIR";
+pub const BOARD_KEYS_SUBDIR: &str = "board_declaration";
+pub const FIELD_KEYS_SUBDIR: &str = "field_declaration";
+pub const HISTORY_KEYS_SUBDIR: &str = "shot_history";
+pub const CERTIFICATE_KEYS_SUBDIR: &str = "game_certificate";
+
+static KEYS_DIR: RwLock<Option<String>> = RwLock::new(None);
+
+/// Directory of the keys: the command line flag, the environment variable, the configured one or `keys`
+pub fn resolve_keys_dir(flag: Option<&str>, configured: Option<&str>) -> String {
+    flag.map(str::to_owned)
+        .or_else(|| std::env::var(KEYS_DIR_ENV).ok().filter(|d| !d.is_empty()))
+        .or_else(|| configured.map(str::to_owned))
+        .unwrap_or_else(|| DEFAULT_KEYS_DIR.to_owned())
+}
+
+pub fn set_keys_dir(dir: &str) {
+    *KEYS_DIR.write().unwrap_or_else(|e| e.into_inner()) = Some(dir.to_owned());
+}
+
+pub fn keys_dir() -> String {
+    KEYS_DIR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| DEFAULT_KEYS_DIR.to_owned())
+}
+
+pub fn board_keys_dir() -> String {
+    format!("{}/{BOARD_KEYS_SUBDIR}", keys_dir())
+}
+
+pub fn field_keys_dir() -> String {
+    format!("{}/{FIELD_KEYS_SUBDIR}", keys_dir())
+}
+
+pub fn history_keys_dir() -> String {
+    format!("{}/{HISTORY_KEYS_SUBDIR}", keys_dir())
+}
+
+pub fn certificate_keys_dir() -> String {
+    format!("{}/{CERTIFICATE_KEYS_SUBDIR}", keys_dir())
+}
+
+/// Whether the game proofs can be composed into certificates, which needs Groth16 proofs over BLS12-377
+pub fn certificates_supported() -> bool {
+    Backend::CURVE == CurveKind::Bls12_377 && Backend::kind() == BackendKind::Groth16
+}
+
+/// State of the keys of one circuit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyStatus {
+    pub circuit: String,
+    pub dir: String,
+    /// Key files which do not exist
+    pub missing: Vec<String>,
+    /// Whether `checksums.sha256` lets the keys be checked when loading them
+    pub has_checksums: bool,
+    /// Curve and proof system of the keys, if recorded
+    pub meta: Option<KeyMeta>,
+    /// The game can be played without these keys
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl KeyStatus {
+    pub fn of(circuit: &str, dir: &str) -> Self {
+        KeyStatus {
+            circuit: circuit.to_owned(),
+            dir: dir.to_owned(),
+            missing: KEY_FILES
+                .iter()
+                .filter(|name| fs::metadata(format!("{dir}/{name}")).is_err())
+                .map(|name| name.to_string())
+                .collect(),
+            has_checksums: fs::metadata(format!("{dir}/{CHECKSUM_FILE}")).is_ok(),
+            meta: KeyMeta::read(dir).ok().flatten(),
+            optional: false,
+        }
+    }
+
+    pub fn optional(circuit: &str, dir: &str) -> Self {
+        KeyStatus {
+            optional: true,
+            ..Self::of(circuit, dir)
+        }
+    }
+
+    /// Whether the keys exist, or are not needed to play
+    pub fn is_usable(&self) -> bool {
+        self.missing.is_empty() || (self.optional && self.missing.len() == KEY_FILES.len())
+    }
+}
+
+/// Keys of all the circuits, shown in the settings
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct KeysInfo {
+    pub dir: String,
+    pub circuits: Vec<KeyStatus>,
+    /// Fingerprint of all the verifying keys present. Players can only play together if theirs are equal
+    pub fingerprint: Option<String>,
+}
+
+impl KeysInfo {
+    pub fn collect() -> Self {
+        let (board, field) = (board_keys_dir(), field_keys_dir());
+        // The optional verifying keys count only if they are present
+        let optional = [history_keys_dir(), certificate_keys_dir()];
+        let mut fingerprinted = vec![board.as_str(), field.as_str()];
+        fingerprinted.extend(
+            (optional.iter())
+                .filter(|dir| fs::metadata(format!("{dir}/vk.bin")).is_ok())
+                .map(String::as_str),
+        );
+        KeysInfo {
+            dir: keys_dir(),
+            circuits: vec![
+                KeyStatus::of(BOARD_KEYS_SUBDIR, &board),
+                KeyStatus::of(FIELD_KEYS_SUBDIR, &field),
+                KeyStatus::optional(HISTORY_KEYS_SUBDIR, &history_keys_dir()),
+                KeyStatus::optional(CERTIFICATE_KEYS_SUBDIR, &certificate_keys_dir()),
+            ],
+            fingerprint: verifying_keys_fingerprint(&fingerprinted).ok(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.circuits.iter().all(KeyStatus::is_usable)
+    }
+}
+
+impl Display for KeysInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Key directory: {}", self.dir)?;
+        for status in &self.circuits {
+            let state = if status.missing.is_empty() {
+                "present".to_owned()
+            } else if status.is_usable() {
+                "not generated (optional)".to_owned()
+            } else {
+                format!("missing {}", status.missing.join(", "))
+            };
+            let checksums = if status.has_checksums {
+                "with checksums"
+            } else {
+                "without checksums"
+            };
+            write!(f, "  {:<18} {state}, {checksums}", status.circuit)?;
+            match status.meta {
+                Some(meta) => writeln!(f, ", {meta}")?,
+                None => writeln!(f)?,
+            }
+        }
+        match &self.fingerprint {
+            Some(fingerprint) => write!(f, "Fingerprint: {fingerprint}"),
+            None => write!(f, "Fingerprint: -"),
+        }
+    }
+}
+
+/// Parameters shared by the setups of the game circuits. A universal setup is read from the key directory,
+/// and generated only if it is missing or too small for the circuits. Every player has to derive the keys
+/// from the same parameters, so they are distributed with the keys
+pub fn universal_parameters(logger: &Logger) -> Res<Parameters> {
+    let (board, field) = sample_circuits();
+    let degree = [
+        Backend::parameters_degree(board)?,
+        Backend::parameters_degree(field)?,
+        Backend::parameters_degree(sample_shot_history(board))?,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(0);
+    // Anyone knowing the randomness of the setup could prove anything
+    let mut rng = StdRng::from_rng(OsRng).map_err(|e| Er {
+        message: format!("Could not seed the setup: {e}"),
+    })?;
+    if degree == 0 {
+        return Backend::parameters(degree, &mut rng);
+    }
+    let dir = keys_dir();
+    match read_parameters::<Backend>(&dir)? {
+        Some((supported, params)) if supported >= degree => {
+            logger.info(&format!("Using the universal parameters of {dir}"))?;
+            return Ok(params);
+        }
+        Some((supported, _)) => logger.info(&format!(
+            "The universal parameters of {dir} support degree {supported}, the circuits need {degree}"
+        ))?,
+        None => {}
+    }
+    let now = std::time::Instant::now();
+    let params = Backend::parameters(degree, &mut rng)?;
+    write_parameters::<Backend>(&dir, degree, &params)?;
+    logger.info(&format!(
+        "Universal parameters generated in {dir}, elapsed: {:.2?}",
+        now.elapsed()
+    ))?;
+    Ok(params)
+}
+
+/// Generate the keys of all the circuits in the key directory. Takes several minutes.
+/// The certificate keys are generated only if certificates are supported
+pub fn generate_all_keys(logger: &Logger) -> Res<()> {
+    let params = universal_parameters(logger)?;
+    board_declaration_circuit::generate_keys(logger, &board_keys_dir(), &params)?;
+    field_declaration_circuit::generate_keys(logger, &field_keys_dir(), &params)?;
+    shot_history_circuit::generate_keys(logger, &history_keys_dir(), &params)?;
+    if !certificates_supported() {
+        return Ok(());
+    }
+    game_certificate_circuit::generate_keys(
+        logger,
+        &certificate_keys_dir(),
+        &read_verifying_key::<InnerBackend>(logger, &board_keys_dir())?,
+        &read_verifying_key::<InnerBackend>(logger, &history_keys_dir())?,
+    )
+}
+
+/// Load every key, checking it against the checksums if they exist
+pub fn verify_all_keys(logger: &Logger) -> Res<()> {
+    let statuses = [
+        KeyStatus::of(&board_keys_dir(), &board_keys_dir()),
+        KeyStatus::of(&field_keys_dir(), &field_keys_dir()),
+        KeyStatus::optional(&history_keys_dir(), &history_keys_dir()),
+        KeyStatus::optional(&certificate_keys_dir(), &certificate_keys_dir()),
+    ];
+    for status in statuses {
+        let dir = status.dir.clone();
+        if status.missing.len() == KEY_FILES.len() && status.is_usable() {
+            logger.info(&format!("Optional keys at {dir} are not generated"))?;
+            continue;
+        }
+        if !status.missing.is_empty() {
+            return Err(keys_error(
+                &dir,
+                Er {
+                    message: format!("missing {}", status.missing.join(", ")),
+                },
+            ));
+        }
+        if dir == certificate_keys_dir() {
+            read_keys::<CertificateBackend>(logger, &dir)?;
+        } else {
+            read_keys::<Backend>(logger, &dir)?;
+        }
+        logger.info(&format!("Keys at {dir} are valid"))?;
+    }
+    Ok(())
+}
+
+fn read_keys<B: ProvingBackend>(logger: &Logger, dir: &str) -> Res<()> {
+    read_verifying_key::<B>(logger, dir).map_err(|e| keys_error(dir, e))?;
+    read_proving_key::<B>(logger, dir).map_err(|e| keys_error(dir, e))?;
+    Ok(())
+}
+
+fn keys_error(dir: &str, e: Er) -> Er {
+    Er {
+        message: format!("Invalid keys at {dir}: {}", e.message),
+    }
+}
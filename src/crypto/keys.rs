@@ -1,79 +1,432 @@
 use std::{
-    fs::File,
-    sync::{Arc, Condvar, Mutex},
-    thread,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
 };
 
-use ark_bls12_381::Config;
-use ark_ec::bls12::Bls12;
-use ark_groth16::{ProvingKey, VerifyingKey};
-use ark_serialize::CanonicalDeserialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::utils::{
     log::{Log, Logger},
-    result::Res,
+    result::{Er, Res},
+    worker_pool::{worker_pool, Priority},
 };
 
-type Vk = VerifyingKey<Bls12<Config>>;
-type Pk = ProvingKey<Bls12<Config>>;
+use super::backend::{Backend, BackendKind, CurveKind, ProvingBackend};
 
-#[derive(Debug, Clone)]
-pub struct ArkKeys {
-    mut_cond: Arc<(Mutex<Option<Res<Arc<(Vk, Pk)>>>>, Condvar)>,
+pub type Vk = <Backend as ProvingBackend>::VerifyingKey;
+pub type Pvk = <Backend as ProvingBackend>::PreparedVerifyingKey;
+pub type Pk = <Backend as ProvingBackend>::ProvingKey;
+
+/// Lists the SHA-256 digests of the key files, in the format of `sha256sum`
+pub const CHECKSUM_FILE: &str = "checksums.sha256";
+/// Parameters of a universal setup, shared by the keys of all the circuits
+pub const PARAMETERS_FILE: &str = "universal_parameters.bin";
+/// Key files covered by the checksums
+pub const KEY_FILES: [&str; 2] = ["vk.bin", "pk.bin"];
+/// Curve the keys were generated for
+pub const KEY_META_FILE: &str = "meta.toml";
+
+/// Result of loading a key, filled in by a background job
+type Slot<T> = Arc<(Mutex<Option<Res<Arc<T>>>>, Condvar)>;
+
+/// Keys of a circuit. The verifying key is loaded in the background right away,
+/// the much larger proving key only when the first proof is generated or `preload_proving_key` is called.
+/// Keys are loaded with `Priority::KeyLoading`, by a worker of the pool reserved for them if the others are busy
+pub struct ArkKeys<B: ProvingBackend = Backend> {
+    pub path: String,
+    logger: Logger,
+    vk: Slot<B::PreparedVerifyingKey>,
+    pk: Slot<B::ProvingKey>,
+    pk_requested: Arc<AtomicBool>,
 }
 
 impl ArkKeys {
+    /// Keys of the backend the game is built with
     pub fn load(logger: Logger, path: &str) -> Self {
-        let path1 = path.to_owned();
-        let keys1 = ArkKeys {
-            mut_cond: Arc::new((Mutex::new(None), Condvar::new())),
+        Self::load_for_backend(logger, path)
+    }
+}
+
+impl<B: ProvingBackend> ArkKeys<B> {
+    pub fn load_for_backend(logger: Logger, path: &str) -> Self {
+        let keys = Self {
+            path: path.to_owned(),
+            logger,
+            vk: Slot::default(),
+            pk: Slot::default(),
+            pk_requested: Arc::new(AtomicBool::new(false)),
         };
-        let keys2 = keys1.clone();
-
-        thread::spawn(move || {
-            let keys = read_keys(logger, &path1);
-            let mut l = keys2.mut_cond.0.lock().unwrap();
-            *l = Some(match keys {
-                Ok(ks) => Ok(Arc::new(ks)),
-                Err(e) => Err(e),
-            });
-            keys2.mut_cond.1.notify_all();
+
+        let keys1 = keys.clone();
+        worker_pool().execute(Priority::KeyLoading, move || {
+            fill(
+                &keys1.vk,
+                read_verifying_key::<B>(&keys1.logger, &keys1.path),
+            );
         });
 
-        keys1
+        keys
+    }
+
+    /// Start loading the proving key in the background, before it is needed.
+    /// The file is read once, its digest is checked while deserializing
+    pub fn preload_proving_key(&self) {
+        if self.pk_requested.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let keys = self.clone();
+        worker_pool().execute(Priority::KeyLoading, move || {
+            fill(&keys.pk, read_proving_key::<B>(&keys.logger, &keys.path));
+        });
     }
 
-    pub fn acquire(&mut self) -> Res<Arc<(Vk, Pk)>> {
-        let mut mutex = self.mut_cond.0.lock().unwrap();
+    /// Wait until `preload_proving_key` has loaded the proving key and checked its digest
+    pub fn proving_key_checked(&self) -> Res<()> {
+        wait(&self.pk).map(|_| ())
+    }
 
-        while mutex.is_none() {
-            mutex = self.mut_cond.1.wait(mutex).unwrap();
+    pub fn verifying_key(&mut self) -> Res<Arc<B::PreparedVerifyingKey>> {
+        wait(&self.vk)
+    }
+
+    /// Proving key, loaded in the calling thread unless it has already been requested
+    pub fn proving_key(&mut self) -> Res<Arc<B::ProvingKey>> {
+        if !self.pk_requested.swap(true, Ordering::SeqCst) {
+            fill(&self.pk, read_proving_key::<B>(&self.logger, &self.path));
         }
+        wait(&self.pk)
+    }
+}
 
-        match mutex.as_ref().unwrap() {
-            Ok(arc) => Ok(Arc::clone(arc)),
-            Err(e) => Err(e.clone()),
+impl<B: ProvingBackend> Clone for ArkKeys<B> {
+    fn clone(&self) -> Self {
+        ArkKeys {
+            path: self.path.clone(),
+            logger: self.logger.clone(),
+            vk: Arc::clone(&self.vk),
+            pk: Arc::clone(&self.pk),
+            pk_requested: Arc::clone(&self.pk_requested),
         }
     }
 }
 
-pub fn read_keys(logger: Logger, path: &str) -> Res<(Vk, Pk)> {
+impl<B: ProvingBackend> std::fmt::Debug for ArkKeys<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArkKeys").field("path", &self.path).finish()
+    }
+}
+
+fn fill<T>(slot: &Slot<T>, value: Res<T>) {
+    let mut l = slot.0.lock().unwrap_or_else(|e| e.into_inner());
+    *l = Some(value.map(Arc::new));
+    slot.1.notify_all();
+}
+
+fn wait<T>(slot: &Slot<T>) -> Res<Arc<T>> {
+    let mut mutex = slot.0.lock().unwrap_or_else(|e| e.into_inner());
+    while mutex.is_none() {
+        mutex = slot.1.wait(mutex).unwrap_or_else(|e| e.into_inner());
+    }
+
+    match mutex.as_ref().unwrap() {
+        Ok(arc) => Ok(Arc::clone(arc)),
+        Err(e) => Err(e.clone()),
+    }
+}
+
+/// Verifying key of the directory, prepared for verification
+pub fn read_verifying_key<B: ProvingBackend>(
+    logger: &Logger,
+    path: &str,
+) -> Res<B::PreparedVerifyingKey> {
     let now = std::time::Instant::now();
+    check_key_meta::<B>(logger, path)?;
+    let checksums = read_checksums(logger, path)?;
+    let vk: B::VerifyingKey = read_key_file(path, "vk.bin", &checksums)?;
+    // Preparing costs a single pairing, cheaper than checking a stored prepared key
+    let pvk = B::prepare(&vk);
 
-    let vk_file = File::open(format!("{}/vk.bin", path))?;
-    let vk = VerifyingKey::deserialize_uncompressed_unchecked(vk_file)?;
-    logger.log_message(&format!("{}/vk.bin deserialized", path))?;
+    logger.debug(&format!(
+        "Verifying key at {path} is loaded. Time: {:.2?}",
+        now.elapsed()
+    ))?;
+    Ok(pvk)
+}
 
-    let pk_file = File::open(format!("{}/pk.bin", path))?;
-    let pk: ProvingKey<ark_ec::bls12::Bls12<ark_bls12_381::Config>> =
-        ProvingKey::deserialize_uncompressed_unchecked(pk_file)?;
-    logger.log_message(&format!("{}/pk.bin deserialized", path))?;
+pub fn read_proving_key<B: ProvingBackend>(logger: &Logger, path: &str) -> Res<B::ProvingKey> {
+    check_key_meta::<B>(logger, path)?;
+    let checksums = read_checksums(logger, path)?;
+    load_proving_key::<B>(logger, path, &checksums)
+}
 
-    let elapsed = now.elapsed();
+/// Proving key of the directory, checked against the given checksums
+fn load_proving_key<B: ProvingBackend>(
+    logger: &Logger,
+    path: &str,
+    checksums: &HashMap<String, String>,
+) -> Res<B::ProvingKey> {
+    let now = std::time::Instant::now();
+    let pk = read_key_file(path, "pk.bin", checksums)?;
     logger.log_message(&format!(
-        "Keys at {} are loaded. Time: {:.2?}",
-        path, elapsed
+        "Proving key at {path} is loaded. Time: {:.2?}",
+        now.elapsed()
     ))?;
+    Ok(pk)
+}
+
+/// Write the keys, their metadata and checksums to the directory
+pub fn write_keys<B: ProvingBackend>(
+    path: &str,
+    vk: &B::VerifyingKey,
+    pk: &B::ProvingKey,
+) -> Res<()> {
+    fs::create_dir_all(path)?;
+    replace_key_file(path, "vk.bin", vk)?;
+    replace_key_file(path, "pk.bin", pk)?;
+    KeyMeta::of::<B>().write(path)?;
+    write_checksums(path)
+}
 
-    Ok((vk, pk))
+/// Universal parameters of the directory with the degree they support, `None` if there are none
+pub fn read_parameters<B: ProvingBackend>(path: &str) -> Res<Option<(usize, B::Parameters)>> {
+    let file = format!("{path}/{PARAMETERS_FILE}");
+    match File::open(&file) {
+        Ok(file) => Ok(Some(
+            <(usize, B::Parameters)>::deserialize_uncompressed_unchecked(BufReader::new(file))?,
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn write_parameters<B: ProvingBackend>(
+    path: &str,
+    degree: usize,
+    params: &B::Parameters,
+) -> Res<()> {
+    fs::create_dir_all(path)?;
+    replace_file(path, PARAMETERS_FILE, |writer| {
+        degree.serialize_uncompressed(&mut *writer)?;
+        Ok(params.serialize_uncompressed(writer)?)
+    })
+}
+
+/// Write the key to a new file renamed over the old one, which is never modified in place.
+/// A game mapping the old file into memory keeps reading its contents
+fn replace_key_file(path: &str, name: &str, key: &impl CanonicalSerialize) -> Res<()> {
+    replace_file(path, name, |writer| Ok(key.serialize_uncompressed(writer)?))
+}
+
+fn replace_file(
+    path: &str,
+    name: &str,
+    write: impl FnOnce(&mut BufWriter<File>) -> Res<()>,
+) -> Res<()> {
+    let file = format!("{path}/{name}");
+    let temporary = format!("{file}.tmp");
+    let mut writer = BufWriter::new(File::create(&temporary)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    fs::rename(&temporary, &file)?;
+    Ok(())
+}
+
+/// Contents of `meta.toml`, describing what the keys in the directory are for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMeta {
+    pub curve: CurveKind,
+    /// Missing in the keys generated before other proof systems than Groth16 were supported
+    #[serde(default)]
+    pub backend: BackendKind,
+}
+
+impl KeyMeta {
+    pub fn of<B: ProvingBackend>() -> Self {
+        KeyMeta {
+            curve: B::CURVE,
+            backend: B::kind(),
+        }
+    }
+
+    /// Metadata of the keys in the directory, `None` for keys generated before it was recorded
+    pub fn read(path: &str) -> Res<Option<Self>> {
+        let file = format!("{path}/{KEY_META_FILE}");
+        match fs::read_to_string(&file) {
+            Ok(content) => toml::from_str(&content).map(Some).map_err(|e| Er {
+                message: format!("Invalid {file}: {}", e.message()),
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Res<()> {
+        let content = toml::to_string(self).map_err(|e| Er {
+            message: e.to_string(),
+        })?;
+        fs::write(format!("{path}/{KEY_META_FILE}"), content)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for KeyMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} on {}", self.backend.name(), self.curve.name())
+    }
+}
+
+/// Refuse keys generated for another curve or proof system
+fn check_key_meta<B: ProvingBackend>(logger: &Logger, path: &str) -> Res<()> {
+    let expected = KeyMeta::of::<B>();
+    match KeyMeta::read(path)? {
+        Some(meta) if meta != expected => Err(Er {
+            message: format!(
+                "The keys at {path} are for {meta}, but the game proves with {expected}"
+            ),
+        }),
+        Some(_) => Ok(()),
+        // Only Groth16 keys were generated without the metadata
+        None if expected.backend != BackendKind::Groth16 => Err(Er {
+            message: format!("{path}/{KEY_META_FILE} is missing, the keys are not for {expected}"),
+        }),
+        None => logger.debug(&format!(
+            "{path}/{KEY_META_FILE} is missing, assuming the keys are for {expected}"
+        )),
+    }
+}
+
+/// Store the digests of the key files present in the directory
+pub fn write_checksums(path: &str) -> Res<()> {
+    let mut content = String::new();
+    for name in KEY_FILES {
+        let file = format!("{path}/{name}");
+        if fs::metadata(&file).is_ok() {
+            let mut reader = HashingReader::new(BufReader::new(File::open(&file)?));
+            io::copy(&mut reader, &mut io::sink())?;
+            content += &format!("{}  {name}\n", reader.digest());
+        }
+    }
+    fs::write(format!("{path}/{CHECKSUM_FILE}"), content)?;
+    Ok(())
+}
+
+/// Expected digests of the key files. Keys without the checksum file are loaded unchecked
+pub fn read_checksums(logger: &Logger, path: &str) -> Res<HashMap<String, String>> {
+    let content = match fs::read_to_string(format!("{path}/{CHECKSUM_FILE}")) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            logger.warn(&format!(
+                "{path}/{CHECKSUM_FILE} is missing, the keys are not checked for corruption"
+            ))?;
+            return Ok(HashMap::new());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.split_once("  ") {
+            Some((digest, name)) => Ok((name.trim().to_owned(), digest.to_lowercase())),
+            None => Err(Er {
+                message: format!("Malformed line in {path}/{CHECKSUM_FILE}: {line}"),
+            }),
+        })
+        .collect()
+}
+
+fn checksum_mismatch(file: &str) -> Er {
+    Er {
+        message: format!("{file} does not match its checksum, the key is corrupted. Download or generate the keys again"),
+    }
+}
+
+/// Deserialize the key file, checking its digest against the checksums
+#[cfg(not(feature = "mmap"))]
+fn read_key_file<T: CanonicalDeserialize>(
+    path: &str,
+    name: &str,
+    checksums: &HashMap<String, String>,
+) -> Res<T> {
+    let file = format!("{path}/{name}");
+    let mut reader = HashingReader::new(BufReader::new(File::open(&file)?));
+    let key = T::deserialize_uncompressed_unchecked(&mut reader);
+    if let Some(expected) = checksums.get(name) {
+        // The digest covers the whole file, even bytes after the key
+        io::copy(&mut reader, &mut io::sink())?;
+        if reader.digest() != *expected {
+            return Err(checksum_mismatch(&file));
+        }
+    }
+    Ok(key?)
+}
+
+/// Deserialize the memory mapped key file, checking its digest against the checksums
+#[cfg(feature = "mmap")]
+fn read_key_file<T: CanonicalDeserialize>(
+    path: &str,
+    name: &str,
+    checksums: &HashMap<String, String>,
+) -> Res<T> {
+    let file = format!("{path}/{name}");
+    // SAFETY: key files are never modified in place, `write_keys` renames new files over them
+    let map = unsafe { memmap2::Mmap::map(&File::open(&file)?)? };
+    if let Some(expected) = checksums.get(name) {
+        if hex_digest(Sha256::digest(&map[..]).as_slice()) != *expected {
+            return Err(checksum_mismatch(&file));
+        }
+    }
+    Ok(T::deserialize_uncompressed_unchecked(&map[..])?)
+}
+
+fn hex_digest(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Computes the digest of everything read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn digest(&self) -> String {
+        hex_digest(self.hasher.clone().finalize().as_slice())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Short fingerprint of the verifying keys stored in the given directories.
+/// Players can only play together if their fingerprints are equal
+pub fn verifying_keys_fingerprint(paths: &[&str]) -> Res<String> {
+    let mut hasher = Sha256::new();
+    for path in paths {
+        hasher.update(std::fs::read(format!("{}/vk.bin", path))?);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect())
 }
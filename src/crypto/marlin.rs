@@ -0,0 +1,731 @@
+use std::collections::BTreeMap;
+
+use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
+use ark_ec::pairing::Pairing;
+use ark_ff::{batch_inversion, One, PrimeField, Zero};
+use ark_poly::{
+    univariate::DensePolynomial, DenseUVPolynomial, EvaluationDomain, Evaluations,
+    Radix2EvaluationDomain,
+};
+use ark_poly_commit::{
+    challenge::ChallengeGenerator,
+    kzg10,
+    marlin_pc::{self, MarlinKZG10},
+    LabeledCommitment, LabeledPolynomial, PCRandomness, PolynomialCommitment, QuerySet,
+};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+use crate::utils::result::{Er, Res};
+
+type Poly<E> = DensePolynomial<<E as Pairing>::ScalarField>;
+/// The sponge only names the type of the opening challenges, which are drawn from `Transcript`
+type Pc<E> = MarlinKZG10<E, Poly<E>, PoseidonSponge<<E as Pairing>::ScalarField>>;
+type Domain<F> = Radix2EvaluationDomain<F>;
+type Matrix<F> = Vec<Vec<(F, usize)>>;
+
+/// Powers of a secret shared by the keys of all the circuits, sized for the largest of them
+pub type MarlinParameters<E> = kzg10::UniversalParams<E>;
+
+const MATRICES: [&str; 3] = ["a", "b", "c"];
+const INDEX_POLYNOMIALS: [&str; 4] = ["row", "col", "row_col", "val"];
+/// Polynomials committed by the prover, in the order of the proof
+const PROOF_POLYNOMIALS: [&str; 13] = [
+    "w", "z_a", "z_b", "mask", "t", "g_1", "h_1", "g_a", "g_b", "g_c", "h_a", "h_b", "h_c",
+];
+/// Polynomials opened at the second challenge, the others are opened at the third one
+const FIRST_SUMCHECK: usize = 7;
+/// Queries to the masked polynomials the commitments hide against
+const HIDING_BOUND: usize = 1;
+
+/// Shape of the indexed circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexInfo {
+    /// Public inputs, including the constant one
+    pub num_instance: usize,
+    pub num_witness: usize,
+    pub num_constraints: usize,
+    /// Sizes of the domains X, H and K
+    pub x_size: usize,
+    pub h_size: usize,
+    pub k_size: usize,
+}
+
+impl IndexInfo {
+    /// Degree of the parameters the circuit needs, reached by the mask of the first sumcheck
+    pub fn degree(&self) -> usize {
+        (3 * self.h_size - 1).max(self.k_size - 1)
+    }
+
+    fn domains<F: PrimeField>(&self) -> Res<(Domain<F>, Domain<F>, Domain<F>)> {
+        Ok((
+            domain(self.x_size)?,
+            domain(self.h_size)?,
+            domain(self.k_size)?,
+        ))
+    }
+
+    /// Position in H of the variable with the index given by the constraint system.
+    /// The instance takes the subgroup X, the witness fills the other elements in order
+    fn position(&self, variable: usize) -> usize {
+        if variable < self.num_instance {
+            return variable * (self.h_size / self.x_size);
+        }
+        let ratio = self.h_size / self.x_size;
+        let witness = variable - self.num_instance;
+        witness / (ratio - 1) * ratio + witness % (ratio - 1) + 1
+    }
+
+    /// Degree bound of each committed polynomial of the proof
+    fn degree_bound(&self, label: &str) -> Option<usize> {
+        match label {
+            "g_1" => Some(self.h_size - 2),
+            "g_a" | "g_b" | "g_c" => Some(self.k_size - 2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MarlinVerifyingKey<E: Pairing> {
+    pub info: IndexInfo,
+    /// Commitments to the polynomials encoding the matrices
+    pub index_commitments: Vec<marlin_pc::Commitment<E>>,
+    pub pc_vk: marlin_pc::VerifierKey<E>,
+}
+
+impl<E: Pairing> PartialEq for MarlinVerifyingKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.info == other.info
+            && self.index_commitments == other.index_commitments
+            && self.pc_vk.vk == other.pc_vk.vk
+            && self.pc_vk.degree_bounds_and_shift_powers
+                == other.pc_vk.degree_bounds_and_shift_powers
+            && self.pc_vk.max_degree == other.pc_vk.max_degree
+            && self.pc_vk.supported_degree == other.pc_vk.supported_degree
+    }
+}
+
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MarlinProvingKey<E: Pairing> {
+    pub vk: MarlinVerifyingKey<E>,
+    /// Matrices A, B and C, each row listing its nonzero entries with their positions in H
+    matrices: Vec<Matrix<E::ScalarField>>,
+    /// Row, column, their product and value of the nonzero entries of every matrix, over K
+    index_polynomials: Vec<Poly<E>>,
+    ck: marlin_pc::CommitterKey<E>,
+}
+
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MarlinProof<E: Pairing> {
+    /// Commitments to `PROOF_POLYNOMIALS`
+    pub commitments: Vec<marlin_pc::Commitment<E>>,
+    /// Sums over K of the second sumcheck, one for each matrix
+    pub sums: Vec<E::ScalarField>,
+    /// Evaluations of the committed polynomials, then of the index polynomials
+    pub evaluations: Vec<E::ScalarField>,
+    /// Openings at the second and third challenge
+    pub openings: Vec<kzg10::Proof<E>>,
+}
+
+impl<E: Pairing> Default for MarlinProof<E> {
+    fn default() -> Self {
+        MarlinProof {
+            commitments: vec![],
+            sums: vec![],
+            evaluations: vec![],
+            openings: vec![],
+        }
+    }
+}
+
+/// Fiat-Shamir transcript drawing the challenges of the verifier from everything sent before them
+struct Transcript(Sha256);
+
+impl Transcript {
+    fn new<E: Pairing>(vk: &MarlinVerifyingKey<E>, input: &[E::ScalarField]) -> Res<Self> {
+        let mut transcript = Transcript(Sha256::new_with_prefix(b"battleships-marlin"));
+        transcript.append(&vk.info)?;
+        transcript.append(&vk.index_commitments)?;
+        transcript.append(&input.to_vec())?;
+        Ok(transcript)
+    }
+
+    fn append(&mut self, item: &impl CanonicalSerialize) -> Res<()> {
+        let mut bytes = vec![];
+        item.serialize_compressed(&mut bytes)?;
+        self.0.update((bytes.len() as u64).to_le_bytes());
+        self.0.update(bytes);
+        Ok(())
+    }
+
+    /// 64 bytes derived from the transcript, which they are appended to
+    fn squeeze(&mut self) -> Vec<u8> {
+        let mut bytes = self.0.clone().chain_update([0]).finalize().to_vec();
+        bytes.extend(self.0.clone().chain_update([1]).finalize());
+        self.0.update(&bytes);
+        bytes
+    }
+
+    fn challenge<F: PrimeField>(&mut self) -> F {
+        F::from_le_bytes_mod_order(&self.squeeze())
+    }
+
+    /// Challenge at which the vanishing polynomial of H does not vanish
+    fn challenge_outside<F: PrimeField>(&mut self, h: &Domain<F>) -> Res<F> {
+        let challenge = self.challenge();
+        if h.evaluate_vanishing_polynomial(challenge).is_zero() {
+            return Err(Er {
+                message: "A challenge of the proof fell into the domain of the circuit".to_owned(),
+            });
+        }
+        Ok(challenge)
+    }
+
+    fn rng(&mut self) -> StdRng {
+        let mut seed = [0; 32];
+        seed.copy_from_slice(&self.squeeze()[..32]);
+        StdRng::from_seed(seed)
+    }
+}
+
+fn domain<F: PrimeField>(size: usize) -> Res<Domain<F>> {
+    Domain::new(size).ok_or_else(|| Er {
+        message: format!("The field has no domain of {size} elements"),
+    })
+}
+
+/// Size of the circuit and its matrices, with the columns moved to the positions of the variables in H
+fn index<F: PrimeField, C: ConstraintSynthesizer<F>>(
+    circuit: C,
+) -> Res<(IndexInfo, Vec<Matrix<F>>)> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let matrices = cs.to_matrices().ok_or_else(|| Er {
+        message: "The constraint system has no matrices".to_owned(),
+    })?;
+
+    let x_size = domain::<F>(matrices.num_instance_variables)?.size();
+    let h_size = domain::<F>(
+        (matrices.num_constraints)
+            .max(x_size + matrices.num_witness_variables)
+            .max(2 * x_size),
+    )?
+    .size();
+    let non_zero = (matrices.a_num_non_zero)
+        .max(matrices.b_num_non_zero)
+        .max(matrices.c_num_non_zero);
+    let info = IndexInfo {
+        num_instance: matrices.num_instance_variables,
+        num_witness: matrices.num_witness_variables,
+        num_constraints: matrices.num_constraints,
+        x_size,
+        h_size,
+        k_size: domain::<F>(non_zero.max(2))?.size(),
+    };
+    let positioned = [matrices.a, matrices.b, matrices.c]
+        .into_iter()
+        .map(|matrix| {
+            (matrix.into_iter())
+                .map(|row| {
+                    (row.into_iter())
+                        .map(|(value, variable)| (value, info.position(variable)))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    Ok((info, positioned))
+}
+
+/// Polynomials over K with the row, column, their product and the value of each nonzero entry.
+/// The value is divided by the derivative of the vanishing polynomial of H at the column, so that the sum
+/// over K is the matrix multiplied by the Lagrange polynomials of H
+fn encode_matrix<F: PrimeField>(
+    matrix: &Matrix<F>,
+    h: &Domain<F>,
+    k: &Domain<F>,
+) -> [DensePolynomial<F>; 4] {
+    let h_elements: Vec<F> = h.elements().collect();
+    let h_size_inverse = h.size_inv;
+    let mut row = vec![F::one(); k.size()];
+    let mut col = vec![F::one(); k.size()];
+    let mut val = vec![F::zero(); k.size()];
+    let entries = (matrix.iter().enumerate())
+        .flat_map(|(r, entries)| entries.iter().map(move |&(value, c)| (r, c, value)));
+    for (i, (r, c, value)) in entries.enumerate() {
+        row[i] = h_elements[r];
+        col[i] = h_elements[c];
+        val[i] = value * h_elements[c] * h_size_inverse;
+    }
+    let row_col = row.iter().zip(&col).map(|(r, c)| *r * c).collect();
+    [row, col, row_col, val].map(|evaluations| interpolate(evaluations, k))
+}
+
+fn interpolate<F: PrimeField>(evaluations: Vec<F>, domain: &Domain<F>) -> DensePolynomial<F> {
+    Evaluations::from_vec_and_domain(evaluations, *domain).interpolate()
+}
+
+/// The polynomial plus a random multiple of the vanishing polynomial of H, equal to it on H
+fn masked<F: PrimeField>(
+    mut polynomial: DensePolynomial<F>,
+    h: &Domain<F>,
+    rng: &mut StdRng,
+) -> DensePolynomial<F> {
+    polynomial += &(&DensePolynomial::from(h.vanishing_polynomial()) * F::rand(rng));
+    polynomial
+}
+
+fn labeled<F: PrimeField>(
+    label: &str,
+    polynomial: DensePolynomial<F>,
+    degree_bound: Option<usize>,
+    hiding: bool,
+) -> LabeledPolynomial<F, DensePolynomial<F>> {
+    let hiding_bound = hiding.then_some(HIDING_BOUND);
+    LabeledPolynomial::new(label.to_owned(), polynomial, degree_bound, hiding_bound)
+}
+
+fn index_labels() -> impl Iterator<Item = String> {
+    MATRICES
+        .into_iter()
+        .flat_map(|m| INDEX_POLYNOMIALS.map(|p| format!("{p}_{m}")))
+}
+
+/// Queries of the openings, in the order of the evaluations in the proof:
+/// the first sumcheck at `beta`, the second one and the index at `gamma`
+fn queries<F: PrimeField>(beta: F, gamma: F) -> Vec<(String, (String, F))> {
+    let (first, second) = PROOF_POLYNOMIALS.split_at(FIRST_SUMCHECK);
+    let at = |label: String, point: &str, value: F| (label, (point.to_owned(), value));
+    (first.iter().map(|l| at(l.to_string(), "beta", beta)))
+        .chain(second.iter().map(|l| at(l.to_string(), "gamma", gamma)))
+        .chain(index_labels().map(|l| at(l, "gamma", gamma)))
+        .collect()
+}
+
+/// Powers of the single challenge combining the opened polynomials
+fn opening_challenges<F: PrimeField>(
+    transcript: &mut Transcript,
+) -> ChallengeGenerator<F, PoseidonSponge<F>> {
+    let challenge = transcript.challenge();
+    ChallengeGenerator::Univariate(challenge, challenge)
+}
+
+/// Parameters supporting circuits up to the degree, from the randomness of `rng`
+pub fn parameters<E: Pairing>(degree: usize, rng: &mut StdRng) -> Res<MarlinParameters<E>> {
+    let mut params = Pc::<E>::setup(degree, None, rng)?;
+    // Only the powers blinding the hiding commitments are needed
+    params
+        .powers_of_gamma_g
+        .retain(|&power, _| power <= HIDING_BOUND + 1);
+    Ok(params)
+}
+
+/// Degree of the parameters the setup of the circuit needs
+pub fn degree<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> Res<usize> {
+    Ok(index(circuit)?.0.degree())
+}
+
+/// Keys of the circuit, derived from the parameters without any other randomness
+pub fn setup<E: Pairing, C: ConstraintSynthesizer<E::ScalarField>>(
+    circuit: C,
+    params: &MarlinParameters<E>,
+) -> Res<(MarlinProvingKey<E>, MarlinVerifyingKey<E>)> {
+    let (info, matrices) = index(circuit)?;
+    setup_indexed(info, matrices, params)
+}
+
+/// Keys of the circuit from parameters generated just for it, like a circuit-specific setup
+pub fn circuit_specific_setup<E: Pairing, C: ConstraintSynthesizer<E::ScalarField>>(
+    circuit: C,
+    rng: &mut StdRng,
+) -> Res<(MarlinProvingKey<E>, MarlinVerifyingKey<E>)> {
+    let (info, matrices) = index(circuit)?;
+    let params = parameters(info.degree(), rng)?;
+    setup_indexed(info, matrices, &params)
+}
+
+fn setup_indexed<E: Pairing>(
+    info: IndexInfo,
+    matrices: Vec<Matrix<E::ScalarField>>,
+    params: &MarlinParameters<E>,
+) -> Res<(MarlinProvingKey<E>, MarlinVerifyingKey<E>)> {
+    if info.degree() >= params.powers_of_g.len() {
+        return Err(Er {
+            message: format!(
+                "The universal parameters support degree {}, the circuit needs {}",
+                params.powers_of_g.len() - 1,
+                info.degree()
+            ),
+        });
+    }
+    let (_, h, k) = info.domains()?;
+    let index_polynomials: Vec<_> = matrices
+        .iter()
+        .flat_map(|matrix| encode_matrix(matrix, &h, &k))
+        .collect();
+    let (ck, pc_vk) = Pc::<E>::trim(
+        params,
+        info.degree(),
+        HIDING_BOUND,
+        Some(&[info.h_size - 2, info.k_size - 2]),
+    )?;
+    let labeled: Vec<_> = index_labels()
+        .zip(&index_polynomials)
+        .map(|(label, polynomial)| labeled(&label, polynomial.clone(), None, false))
+        .collect();
+    let (commitments, _) = Pc::<E>::commit(&ck, &labeled, None)?;
+    let vk = MarlinVerifyingKey {
+        info,
+        index_commitments: commitments.into_iter().map(|c| *c.commitment()).collect(),
+        pc_vk,
+    };
+    let pk = MarlinProvingKey {
+        vk: vk.clone(),
+        matrices,
+        index_polynomials,
+        ck,
+    };
+    Ok((pk, vk))
+}
+
+/// Marlin (Chiesa et al., 2019) over the KZG commitments with degree bounds of `ark-poly-commit`.
+/// The circuit is indexed by three sparse matrices encoded as polynomials over the domain K,
+/// the witness is committed over the domain H, which contains the public input on its subgroup X.
+/// The prover convinces the verifier that the encoded matrices times the witness satisfy the constraints
+/// with two univariate sumchecks, one over H and one for each matrix over K
+pub fn prove<E: Pairing, C: ConstraintSynthesizer<E::ScalarField>>(
+    pk: &MarlinProvingKey<E>,
+    circuit: C,
+    rng: &mut StdRng,
+) -> Res<MarlinProof<E>> {
+    let info = pk.vk.info;
+    let (x, h, k) = info.domains::<E::ScalarField>()?;
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Prove {
+        construct_matrices: false,
+    });
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    let (instance, witness) = {
+        let cs = cs.borrow().ok_or_else(|| Er {
+            message: "The constraint system is gone".to_owned(),
+        })?;
+        (
+            cs.instance_assignment.clone(),
+            cs.witness_assignment.clone(),
+        )
+    };
+    if instance.len() != info.num_instance || witness.len() != info.num_witness {
+        return Err(Er {
+            message: "The circuit does not match the proving key".to_owned(),
+        });
+    }
+    let not_satisfied = || Er {
+        message: "The circuit is not satisfied".to_owned(),
+    };
+
+    // The assignment over H and the matrices multiplied by it
+    let mut z = vec![E::ScalarField::zero(); info.h_size];
+    for (variable, value) in instance.iter().chain(&witness).enumerate() {
+        z[info.position(variable)] = *value;
+    }
+    let products: Vec<Vec<E::ScalarField>> = (pk.matrices.iter())
+        .map(|matrix| {
+            let mut product = vec![E::ScalarField::zero(); info.h_size];
+            for (row, entries) in matrix.iter().enumerate() {
+                product[row] = entries.iter().map(|&(value, c)| value * z[c]).sum();
+            }
+            product
+        })
+        .collect();
+    if (0..info.h_size).any(|i| products[0][i] * products[1][i] != products[2][i]) {
+        return Err(not_satisfied());
+    }
+
+    // First round: the witness, z = w * v_X + x, and the products with A and B
+    let mut x_evaluations = instance.clone();
+    x_evaluations.resize(info.x_size, E::ScalarField::zero());
+    let x_polynomial = interpolate(x_evaluations, &x);
+    let x_over_h = h.fft(&x_polynomial.coeffs);
+    let shifted = z.iter().zip(&x_over_h).map(|(z, x)| *z - x).collect();
+    let (w, remainder) = masked(interpolate(shifted, &h), &h, rng)
+        .divide_by_vanishing_poly(x)
+        .ok_or_else(not_satisfied)?;
+    if !remainder.is_zero() {
+        return Err(not_satisfied());
+    }
+    let z_a = masked(interpolate(products[0].clone(), &h), &h, rng);
+    let z_b = masked(interpolate(products[1].clone(), &h), &h, rng);
+    // Random polynomial summing to zero over H
+    let mut mask = DensePolynomial::<E::ScalarField>::rand(3 * info.h_size - 1, rng);
+    mask.coeffs[0] = -(mask.coeffs[info.h_size] + mask.coeffs[2 * info.h_size]);
+
+    let mut transcript = Transcript::new(&pk.vk, &instance[1..])?;
+    let first = vec![
+        labeled("w", w, None, true),
+        labeled("z_a", z_a, None, true),
+        labeled("z_b", z_b, None, true),
+        labeled("mask", mask, None, true),
+    ];
+    let (first_commitments, first_randomness) = Pc::<E>::commit(&pk.ck, &first, Some(rng))?;
+    transcript.append(&commitments(&first_commitments))?;
+    let alpha = transcript.challenge_outside(&h)?;
+    let etas: [E::ScalarField; 3] = [(); 3].map(|_| transcript.challenge());
+
+    // Second round: the first sumcheck, over H, of
+    // mask + r(alpha, X) * (eta_a * z_a + eta_b * z_b + eta_c * z_a * z_b) - t * z = h_1 * v_H + X * g_1
+    let v_h_alpha = h.evaluate_vanishing_polynomial(alpha);
+    let mut r_alpha: Vec<_> = h.elements().map(|e| alpha - e).collect();
+    batch_inversion(&mut r_alpha);
+    let mut t_evaluations = vec![E::ScalarField::zero(); info.h_size];
+    for (matrix, eta) in pk.matrices.iter().zip(etas) {
+        for (row, entries) in matrix.iter().enumerate() {
+            let coefficient = eta * v_h_alpha * r_alpha[row];
+            for &(value, c) in entries {
+                t_evaluations[c] += coefficient * value;
+            }
+        }
+    }
+    let t = interpolate(t_evaluations, &h);
+    let mut alpha_powers = vec![E::ScalarField::one(); info.h_size];
+    for i in 1..info.h_size {
+        alpha_powers[i] = alpha_powers[i - 1] * alpha;
+    }
+    alpha_powers.reverse();
+    let r_alpha_x = DensePolynomial::from_coefficients_vec(alpha_powers);
+    let (w, z_a, z_b, mask) = (
+        first[0].polynomial(),
+        first[1].polynomial(),
+        first[2].polynomial(),
+        first[3].polynomial(),
+    );
+    let z_x = &(w * &DensePolynomial::from(x.vanishing_polynomial())) + &x_polynomial;
+    let mut combined = z_a * etas[0];
+    combined += (etas[1], z_b);
+    combined += (etas[2], &(z_a * z_b));
+    let sumcheck = &(mask + &(&r_alpha_x * &combined)) - &(&t * &z_x);
+    let (h_1, remainder) = sumcheck
+        .divide_by_vanishing_poly(h)
+        .ok_or_else(not_satisfied)?;
+    if remainder.coeffs.first().is_some_and(|c| !c.is_zero()) {
+        return Err(not_satisfied());
+    }
+    let g_1 = DensePolynomial::from_coefficients_slice(remainder.coeffs.get(1..).unwrap_or(&[]));
+    let second = vec![
+        labeled("t", t, None, false),
+        labeled("g_1", g_1, Some(info.h_size - 2), true),
+        labeled("h_1", h_1, None, true),
+    ];
+    let (second_commitments, second_randomness) = Pc::<E>::commit(&pk.ck, &second, Some(rng))?;
+    transcript.append(&commitments(&second_commitments))?;
+    let beta = transcript.challenge_outside(&h)?;
+
+    // Third round: for each matrix, the sum over K of v_H(alpha) v_H(beta) val / ((alpha - row) (beta - col)),
+    // with val - b * (X * g + sum / |K|) = h * v_K, b being the denominator
+    let v_h_beta = h.evaluate_vanishing_polynomial(beta);
+    let mut sums = vec![];
+    let (mut gs, mut hs) = (vec![], vec![]);
+    for (m, name) in MATRICES.iter().enumerate() {
+        let [row, col, row_col, val] = &pk.index_polynomials[4 * m..4 * m + 4] else {
+            unreachable!()
+        };
+        let (row_k, col_k, val_k) = (k.fft(&row.coeffs), k.fft(&col.coeffs), k.fft(&val.coeffs));
+        let mut denominators: Vec<_> = (0..info.k_size)
+            .map(|i| (alpha - row_k[i]) * (beta - col_k[i]))
+            .collect();
+        batch_inversion(&mut denominators);
+        let summands = (0..info.k_size)
+            .map(|i| v_h_alpha * v_h_beta * val_k[i] * denominators[i])
+            .collect();
+        let f = interpolate(summands, &k);
+        let constant = f.coeffs.first().copied().unwrap_or_default();
+        sums.push(constant * E::ScalarField::from(info.k_size as u64));
+        let mut b = &(row_col - &(row * beta)) - &(col * alpha);
+        b += &DensePolynomial::from_coefficients_vec(vec![alpha * beta]);
+        let numerator = &(val * (v_h_alpha * v_h_beta)) - &(&b * &f);
+        let (h_m, remainder) = numerator
+            .divide_by_vanishing_poly(k)
+            .ok_or_else(not_satisfied)?;
+        if !remainder.is_zero() {
+            return Err(Er {
+                message: "The index of the circuit is inconsistent".to_owned(),
+            });
+        }
+        let g_m = DensePolynomial::from_coefficients_slice(f.coeffs.get(1..).unwrap_or(&[]));
+        gs.push(labeled(
+            &format!("g_{name}"),
+            g_m,
+            Some(info.k_size - 2),
+            false,
+        ));
+        hs.push(labeled(&format!("h_{name}"), h_m, None, false));
+    }
+    let third: Vec<_> = gs.into_iter().chain(hs).collect();
+    let (third_commitments, third_randomness) = Pc::<E>::commit(&pk.ck, &third, Some(rng))?;
+    transcript.append(&commitments(&third_commitments))?;
+    transcript.append(&sums)?;
+    let gamma = transcript.challenge();
+
+    let index: Vec<_> = index_labels()
+        .zip(&pk.index_polynomials)
+        .map(|(label, polynomial)| labeled(&label, polynomial.clone(), None, false))
+        .collect();
+    let index_commitments = labeled_index_commitments(&pk.vk);
+    let polynomials: Vec<_> = first.iter().chain(&second).chain(&third).collect();
+    let evaluations: Vec<_> = (polynomials.iter().enumerate())
+        .map(|(i, p)| p.evaluate(&if i < FIRST_SUMCHECK { beta } else { gamma }))
+        .chain(index.iter().map(|p| p.evaluate(&gamma)))
+        .collect();
+    transcript.append(&evaluations)?;
+
+    let empty = marlin_pc::Randomness::empty();
+    let openings = Pc::<E>::batch_open(
+        &pk.ck,
+        polynomials.into_iter().chain(&index),
+        (first_commitments.iter())
+            .chain(&second_commitments)
+            .chain(&third_commitments)
+            .chain(&index_commitments),
+        &queries(beta, gamma).into_iter().collect(),
+        &mut opening_challenges(&mut transcript),
+        (first_randomness.iter())
+            .chain(&second_randomness)
+            .chain(&third_randomness)
+            .chain(index.iter().map(|_| &empty)),
+        Some(rng),
+    )?;
+
+    Ok(MarlinProof {
+        commitments: (first_commitments.iter())
+            .chain(&second_commitments)
+            .chain(&third_commitments)
+            .map(|c| *c.commitment())
+            .collect(),
+        sums,
+        evaluations,
+        openings,
+    })
+}
+
+pub fn verify<E: Pairing>(
+    vk: &MarlinVerifyingKey<E>,
+    input: &[E::ScalarField],
+    proof: &MarlinProof<E>,
+) -> Res<bool> {
+    let info = vk.info;
+    if input.len() + 1 != info.num_instance {
+        return Err(Er {
+            message: format!(
+                "The circuit has {} public inputs, {} were given",
+                info.num_instance - 1,
+                input.len()
+            ),
+        });
+    }
+    let index_count = MATRICES.len() * INDEX_POLYNOMIALS.len();
+    if proof.commitments.len() != PROOF_POLYNOMIALS.len()
+        || proof.sums.len() != MATRICES.len()
+        || proof.evaluations.len() != PROOF_POLYNOMIALS.len() + index_count
+        || proof.openings.len() != 2
+    {
+        return Ok(false);
+    }
+    let commitments: Vec<_> = (PROOF_POLYNOMIALS.iter())
+        .zip(&proof.commitments)
+        .map(|(label, c)| LabeledCommitment::new(label.to_string(), *c, info.degree_bound(label)))
+        .collect();
+    // A shifted commitment proves the degree bound, the commitment scheme panics without it
+    if (commitments.iter())
+        .any(|c| c.degree_bound().is_some() != c.commitment().shifted_comm.is_some())
+    {
+        return Ok(false);
+    }
+    let (x, h, k) = info.domains::<E::ScalarField>()?;
+
+    let mut transcript = Transcript::new(vk, input)?;
+    transcript.append(&proof.commitments[..4].to_vec())?;
+    let alpha = transcript.challenge_outside(&h)?;
+    let etas: [E::ScalarField; 3] = [(); 3].map(|_| transcript.challenge());
+    transcript.append(&proof.commitments[4..7].to_vec())?;
+    let beta = transcript.challenge_outside(&h)?;
+    transcript.append(&proof.commitments[7..].to_vec())?;
+    transcript.append(&proof.sums)?;
+    let gamma = transcript.challenge();
+    if alpha == beta {
+        return Ok(false);
+    }
+
+    let e = &proof.evaluations;
+    let (w, z_a, z_b, mask, t, g_1, h_1) = (e[0], e[1], e[2], e[3], e[4], e[5], e[6]);
+    let v_h_alpha = h.evaluate_vanishing_polynomial(alpha);
+    let v_h_beta = h.evaluate_vanishing_polynomial(beta);
+    let lagrange = x.evaluate_all_lagrange_coefficients(beta);
+    let x_beta: E::ScalarField = (std::iter::once(&E::ScalarField::one()).chain(input))
+        .zip(lagrange)
+        .map(|(x, l)| *x * l)
+        .sum();
+    let z_beta = w * x.evaluate_vanishing_polynomial(beta) + x_beta;
+    let r_alpha_beta = (v_h_alpha - v_h_beta) / (alpha - beta);
+    let combined = etas[0] * z_a + etas[1] * z_b + etas[2] * z_a * z_b;
+    if mask + r_alpha_beta * combined - t * z_beta != h_1 * v_h_beta + beta * g_1 {
+        return Ok(false);
+    }
+    let sums = &proof.sums;
+    if t != etas.iter().zip(sums).map(|(eta, sum)| *eta * sum).sum() {
+        return Ok(false);
+    }
+    let v_k_gamma = k.evaluate_vanishing_polynomial(gamma);
+    let k_size_inverse = k.size_inv;
+    for m in 0..MATRICES.len() {
+        let (g, h) = (e[7 + m], e[10 + m]);
+        let [row, col, row_col, val] = [0, 1, 2, 3].map(|i| e[13 + 4 * m + i]);
+        let b = alpha * beta - alpha * col - beta * row + row_col;
+        let a = v_h_alpha * v_h_beta * val;
+        if a - b * (gamma * g + sums[m] * k_size_inverse) != h * v_k_gamma {
+            return Ok(false);
+        }
+    }
+
+    transcript.append(&proof.evaluations)?;
+    let mut challenges = opening_challenges(&mut transcript);
+    transcript.append(&proof.openings)?;
+    let queries = queries(beta, gamma);
+    let values: BTreeMap<_, _> = (queries.iter())
+        .map(|(label, (_, point))| (label.clone(), *point))
+        .zip(proof.evaluations.iter().copied())
+        .collect();
+    let index_commitments = labeled_index_commitments(vk);
+    Ok(Pc::<E>::batch_check(
+        &vk.pc_vk,
+        commitments.iter().chain(&index_commitments),
+        &queries.into_iter().collect::<QuerySet<_>>(),
+        &values,
+        &proof.openings,
+        &mut challenges,
+        &mut transcript.rng(),
+    )?)
+}
+
+fn commitments<E: Pairing>(
+    labeled: &[LabeledCommitment<marlin_pc::Commitment<E>>],
+) -> Vec<marlin_pc::Commitment<E>> {
+    labeled.iter().map(|c| *c.commitment()).collect()
+}
+
+fn labeled_index_commitments<E: Pairing>(
+    vk: &MarlinVerifyingKey<E>,
+) -> Vec<LabeledCommitment<marlin_pc::Commitment<E>>> {
+    index_labels()
+        .zip(&vk.index_commitments)
+        .map(|(label, c)| LabeledCommitment::new(label, *c, None))
+        .collect()
+}
@@ -1,4 +1,5 @@
 pub mod circuit;
+pub mod config;
 pub mod crypto;
 pub mod logic;
 pub mod model;
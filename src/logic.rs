@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use async_channel::{Receiver, Sender};
 use async_std::task::block_on;
-use game_loop::Player;
+use dispute::DisputeReason;
+pub use game_loop::Player;
+use history::ShotHistoryProof;
 use main::run_logic_async;
 use serde::{Deserialize, Serialize};
 
@@ -16,18 +20,33 @@ use crate::{
 };
 
 mod board_creation;
+pub mod certificate;
+pub mod chat;
+pub mod dispute;
 mod game_loop;
+pub mod history;
 pub mod main;
+pub mod precompute;
+pub mod profile;
+pub mod protocol;
+pub mod spectator;
+pub mod statistics;
+pub mod transcript;
 
 /// Possible message received from another player
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GameMessage {
     BoardDeclaration(CorrectnessProof<BoardDeclarationCircuit>, [u8; 32]),
     AskForField(u8, u8),
     FieldProof(CorrectnessProof<FieldDeclarationCircuit>, FieldState),
+    Dispute(DisputeReason),
+    RematchOffer,
+    RematchAccept,
+    /// Proof of all our answers, sent after the game to let the opponent certify it
+    ShotHistory(ShotHistoryProof),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
     pub board: BoardDeclarationCircuit,
     pub their_hash: [u8; 32],
@@ -35,14 +54,47 @@ pub struct GameState {
     pub our_shots: Vec<(u8, u8, FieldState)>,
     pub their_shots: Vec<(u8, u8, FieldState)>,
     pub turn_of: Player,
+    /// Time spent on generating each of our field proofs on demand, precomputed ones are not counted
+    pub proof_times: Vec<Duration>,
+}
+
+/// Results of all the games played on one connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionScore {
+    pub our_wins: u32,
+    pub their_wins: u32,
+}
+
+impl SessionScore {
+    pub fn games_played(&self) -> u32 {
+        self.our_wins + self.their_wins
+    }
+
+    pub fn record(&mut self, won: bool) {
+        if won {
+            self.our_wins += 1;
+        } else {
+            self.their_wins += 1;
+        }
+    }
+
+    /// Player starting the next game. Players take turns in starting the games
+    pub fn next_starting_player(&self) -> Player {
+        if self.games_played() % 2 == 0 {
+            Player::Host
+        } else {
+            Player::Client
+        }
+    }
 }
 
 pub fn run_logic_with_ui(ui_callback: impl Fn(Receiver<UiMessage>, Sender<UiInput>) -> ()) {
     let (s_input, r_input) = async_channel::unbounded::<UiMessage>();
     let (s_output, r_output) = async_channel::unbounded::<UiInput>();
 
+    // The futures of the whole game are polled on this thread, which needs close to 1 MiB in debug builds
     std::thread::Builder::new()
-        .stack_size(1024 * 1024)
+        .stack_size(8 * 1024 * 1024)
         .spawn(|| {
             let _ = block_on(run_logic_async(AsyncReceiver(r_output), s_input));
         })
@@ -7,13 +7,18 @@ use crate::{
     utils::{
         log::{Log, Logger},
         result::{Er, Res},
-        threads::{merge, spawn_thread_async},
+        threads::merge,
+        worker_pool::{worker_pool, Priority},
     },
 };
 
 use super::{
-    game_loop::GameContext,
-    main::{NetReceiver, NetSender},
+    dispute::{handle_dispute, report_cheating, CheatingEvidence, DisputeReason, EvidenceInput},
+    game_loop::{GameContext, Player},
+    main::NetSender,
+    protocol::{violation, ProtocolReceiver, ProtocolState},
+    spectator::{SpectatorEvent, Spectators},
+    transcript::BoardProofs,
     GameMessage,
 };
 
@@ -55,7 +60,12 @@ async fn build_and_prove_board(
     ui_sender: UiSender,
     net_sender: NetSender,
     keys: ArkKeys,
-) -> Res<BoardDeclarationCircuit> {
+    spectators: Spectators,
+    player: Player,
+) -> Res<(
+    BoardDeclarationCircuit,
+    CorrectnessProof<BoardDeclarationCircuit>,
+)> {
     let circ: BoardDeclarationCircuit = build_board(ui_receiver, ui_sender.clone()).await?.into();
     // let circ: BoardDeclarationCircuit = SAMPLE_BOARD.into();
     // let board = SAMPLE_BOARD;
@@ -63,79 +73,119 @@ async fn build_and_prove_board(
     ui_sender.log_message("Generating board correctness proof. This can take a while...")?;
 
     let logger: Logger = ui_sender.clone().into();
-    let proof = spawn_thread_async(move || CorrectnessProof::create(circ, logger, keys)).await??;
+    let proof = worker_pool()
+        .run(Priority::Normal, move || {
+            CorrectnessProof::create(circ, logger, keys)
+        })
+        .await??;
 
+    // The salt stays secret, it would reveal the board to anyone reading the log
     ui_sender.log_message(&format!(
-        "Successfully generated board correctness proof. Salt: {:?} Hash: {:?}",
-        circ.salt, circ.hash
+        "Successfully generated board correctness proof. Hash: {:?}",
+        circ.hash
     ))?;
 
     net_sender
         .send(Message::Value(GameMessage::BoardDeclaration(
-            proof, circ.hash,
+            proof.clone(),
+            circ.hash,
         )))
         .await?;
+    spectators.publish(SpectatorEvent::BoardCommitment {
+        player,
+        proof: proof.clone(),
+        hash: circ.hash,
+    });
 
     ui_sender.log_message("Proof has been sent to the other player.")?;
 
-    Ok(circ)
+    Ok((circ, proof))
 }
 
 /// Receive and verify other player's proof
 async fn receive_and_verify_board_proof(
-    net_receiver: &mut NetReceiver,
+    net_receiver: &mut ProtocolReceiver,
+    net_sender: NetSender,
     ui_sender: &mut UiSender,
     keys: ArkKeys,
-) -> Res<[u8; 32]> {
-    loop {
-        if let Message::Value(GameMessage::BoardDeclaration(mut proof, hash)) =
-            net_receiver.get().await?
-        {
+    spectators: Spectators,
+    player: Player,
+) -> Res<([u8; 32], CorrectnessProof<BoardDeclarationCircuit>)> {
+    let state = ProtocolState::AwaitingBoard;
+    match net_receiver.next(state).await? {
+        GameMessage::BoardDeclaration(proof, hash) => {
             ui_sender.log_message(&format!(
                 "Received board correctness proof from the other player. Hash {:?}.\nVerifying received proof...",
                 hash
             ))?;
 
+            let mut proof_clone = proof.clone();
             let hash_clone = hash.to_vec();
-            if spawn_thread_async(move || proof.is_correct(hash_clone.to_vec().into(), keys))
+            if worker_pool()
+                .run(Priority::High, move || {
+                    proof_clone.is_correct(hash_clone.into(), keys)
+                })
                 .await??
             {
                 ui_sender.log_message("Received proof is correct!")?;
-                return Ok(hash);
+                spectators.publish(SpectatorEvent::BoardCommitment {
+                    player,
+                    proof: proof.clone(),
+                    hash,
+                });
+                Ok((hash, proof))
             } else {
                 ui_sender.log_message("Invalid proof")?;
-                return Err(Er {
-                    message: "Invalid proof".to_owned(),
-                });
+                let evidence = CheatingEvidence {
+                    reason: DisputeReason::InvalidBoardProof,
+                    message: GameMessage::BoardDeclaration(proof, hash),
+                    input: EvidenceInput { hash, field: None },
+                };
+                Err(report_cheating(evidence, &net_sender, ui_sender).await)
             }
         }
+        GameMessage::Dispute(reason) => Err(handle_dispute(reason, ui_sender).await),
+        other => Err(violation(&other, state)),
     }
 }
 
 /// Handle boards creation and verification.
-/// Returns constructed board and the hash of the board of the other player
+/// Returns constructed board, the hash of the board of the other player and the proofs of both boards
 pub async fn initialize_boards(
     game_context: &mut GameContext,
-) -> Res<(BoardDeclarationCircuit, [u8; 32])> {
-    if let Ok((board, hash)) = merge(
+) -> Res<(BoardDeclarationCircuit, [u8; 32], BoardProofs)> {
+    match merge(
         build_and_prove_board(
             &mut game_context.ui_receiver,
             game_context.ui_sender.clone(),
             game_context.net_sender.clone(),
             game_context.keys.board_declaration_keys.clone(),
+            game_context.spectators.clone(),
+            game_context.player,
         ),
         receive_and_verify_board_proof(
             &mut game_context.net_receiver,
+            game_context.net_sender.clone(),
             &mut game_context.ui_sender,
             game_context.keys.board_declaration_keys.clone(),
+            game_context.spectators.clone(),
+            game_context.player.other(),
         ),
     )
     .await
     {
-        Ok((board, hash))
-    } else {
-        Err(Er {
-            message: "Error while initializing boards".to_owned(),
-        })
+        Ok(((board, ours), (their_hash, theirs))) => Ok((
+            board,
+            their_hash,
+            BoardProofs {
+                our_hash: board.hash,
+                ours,
+                their_hash,
+                theirs,
+            },
+        )),
+        Err(e) => Err(Er {
+            message: format!("Error while initializing boards: {}", e.message),
+        }),
     }
 }
@@ -0,0 +1,187 @@
+use std::fs::{self, File};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    circuit::game_certificate_circuit::{
+        players_digest, CertificateBackend, CertificateField, CertificateInput, CertifiedBoard,
+        GameCertificateCircuit, InnerBackend, InnerProof,
+    },
+    crypto::{
+        backend::Proof,
+        key_store::{
+            board_keys_dir, certificate_keys_dir, certificates_supported, history_keys_dir,
+        },
+        keys::ArkKeys,
+        proofs::{CorrectnessProof, PublicInput},
+    },
+    utils::{
+        log::Logger,
+        result::{Er, Res},
+    },
+};
+
+use super::{game_loop::Player, history::ShotHistoryProof, transcript::GameTranscript};
+
+pub static CERTIFICATE_DIR: &str = "certificates";
+
+/// One proof that a game was played legally and who won it, checked without the transcript
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameCertificate {
+    pub host_hash: [u8; 32],
+    pub client_hash: [u8; 32],
+    /// Identifier of the game, see `game_id`
+    pub game_id: String,
+    pub host_name: String,
+    pub client_name: String,
+    pub winner: Player,
+    /// Seconds since the UNIX epoch, as in the transcript. Not covered by the proof
+    pub finished_at: u64,
+    pub proof: CorrectnessProof<GameCertificateCircuit, CertificateBackend>,
+}
+
+impl GameCertificate {
+    /// Compose the proofs stored in the transcript. Needs the proofs of the answers of both players
+    pub fn create(transcript: &GameTranscript, logger: Logger) -> Res<Self> {
+        if !certificates_supported() {
+            return Err(Er {
+                message: "Certificates need the game built with the `bls12-377` feature and proving with Groth16"
+                    .to_owned(),
+            });
+        }
+        let missing = |what: &str| Er {
+            message: format!("The transcript has no {what}"),
+        };
+        let boards = transcript
+            .board_proofs
+            .as_ref()
+            .ok_or_else(|| missing("board proofs"))?;
+        let our_history = transcript.history_proof.as_ref().ok_or_else(|| {
+            missing("proof of our answers, make it with --history-proof or prove-history")
+        })?;
+        let their_history = transcript
+            .their_history_proof
+            .as_ref()
+            .ok_or_else(|| missing("proof of the opponent's answers"))?;
+        if transcript.name.is_empty() {
+            return Err(Er {
+                message: "The transcript has no player name, it was saved by an older version"
+                    .to_owned(),
+            });
+        }
+
+        let ours = certified_board(boards.our_hash, &boards.ours.0, our_history)?;
+        let theirs = certified_board(boards.their_hash, &boards.theirs.0, their_history)?;
+        let (host, client) = match transcript.our_role {
+            Player::Host => (ours, theirs),
+            Player::Client => (theirs, ours),
+        };
+        let (host_name, client_name) = match transcript.our_role {
+            Player::Host => (&transcript.name, &transcript.opponent),
+            Player::Client => (&transcript.opponent, &transcript.name),
+        };
+        let game_id = game_id(&host.hash, &client.hash);
+        let winner = if transcript.won {
+            transcript.our_role
+        } else {
+            transcript.our_role.other()
+        };
+
+        let board_vk = ArkKeys::<InnerBackend>::load_for_backend(logger.clone(), &board_keys_dir())
+            .verifying_key()?;
+        let history_vk =
+            ArkKeys::<InnerBackend>::load_for_backend(logger.clone(), &history_keys_dir())
+                .verifying_key()?;
+        let circuit = GameCertificateCircuit {
+            board_vk: (*board_vk).clone(),
+            history_vk: (*history_vk).clone(),
+            host: host.clone(),
+            client: client.clone(),
+            host_won: winner == Player::Host,
+            players: players_digest(&game_id, host_name, client_name),
+        };
+        let keys = certificate_keys(logger.clone());
+
+        Ok(GameCertificate {
+            host_hash: host.hash,
+            client_hash: client.hash,
+            game_id,
+            host_name: host_name.clone(),
+            client_name: client_name.clone(),
+            winner,
+            finished_at: transcript.finished_at,
+            proof: CorrectnessProof::create(circuit, logger, keys)?,
+        })
+    }
+
+    pub fn verify(&self, keys: ArkKeys<CertificateBackend>) -> Res<bool> {
+        let input: PublicInput<CertificateField> = CertificateInput {
+            host_hash: self.host_hash,
+            client_hash: self.client_hash,
+            host_won: self.winner == Player::Host,
+            players: players_digest(&self.game_id, &self.host_name, &self.client_name),
+        }
+        .into();
+        self.proof.clone().is_correct(input, keys)
+    }
+
+    /// Store the certificate in the `CERTIFICATE_DIR` directory. Returns path to the created file
+    pub fn save(&self) -> Res<String> {
+        fs::create_dir_all(CERTIFICATE_DIR)?;
+        let path = format!(
+            "{CERTIFICATE_DIR}/game-{}-{}.json",
+            self.finished_at, self.game_id
+        );
+        serde_json::to_writer_pretty(File::create(&path)?, self)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Res<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+}
+
+/// Identifier of the game both players agree on without exchanging it: the board commitments are salted,
+/// so no two games share them
+pub fn game_id(host_hash: &[u8; 32], client_hash: &[u8; 32]) -> String {
+    let digest = Sha256::new()
+        .chain_update(host_hash)
+        .chain_update(client_hash)
+        .finalize();
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Keys of the certificates from the configured key directory
+pub fn certificate_keys(logger: Logger) -> ArkKeys<CertificateBackend> {
+    ArkKeys::load_for_backend(logger, &certificate_keys_dir())
+}
+
+fn certified_board(
+    hash: [u8; 32],
+    board_proof: &Proof,
+    history: &ShotHistoryProof,
+) -> Res<CertifiedBoard> {
+    if history.hash != hash {
+        return Err(Er {
+            message: "The shot history proof is for another board".to_owned(),
+        });
+    }
+    Ok(CertifiedBoard {
+        hash,
+        board_proof: inner_proof(board_proof)?,
+        shots: history.shots.clone(),
+        history_proof: inner_proof(&history.proof.0)?,
+    })
+}
+
+/// The proof as a proof over BLS12-377, which it is when certificates are supported
+fn inner_proof(proof: &Proof) -> Res<InnerProof> {
+    let mut bytes = vec![];
+    proof.serialize_compressed(&mut bytes)?;
+    Ok(InnerProof::deserialize_compressed(&bytes[..])?)
+}
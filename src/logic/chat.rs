@@ -0,0 +1,64 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Single chat message exchanged with the opponent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub text: String,
+    /// Seconds since the UNIX epoch
+    pub sent_at: u64,
+    /// True if the message has been written by us
+    pub ours: bool,
+}
+
+impl ChatMessage {
+    pub fn new(sender: &str, text: &str, ours: bool) -> Self {
+        ChatMessage {
+            sender: sender.to_owned(),
+            text: text.to_owned(),
+            sent_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ours,
+        }
+    }
+
+    /// Time of the message as `HH:MM` (UTC)
+    pub fn time(&self) -> String {
+        let minutes = self.sent_at / 60;
+        format!("{:02}:{:02}", (minutes / 60) % 24, minutes % 60)
+    }
+}
+
+impl Display for ChatMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}> {}", self.time(), self.sender, self.text)
+    }
+}
+
+/// Messages exchanged since the last stored transcript. Shared by the input filters and the game loop
+#[derive(Debug, Clone, Default)]
+pub struct ChatLog(Arc<Mutex<Vec<ChatMessage>>>);
+
+impl ChatLog {
+    pub fn push(&self, message: ChatMessage) {
+        if let Ok(mut messages) = self.0.lock() {
+            messages.push(message);
+        }
+    }
+
+    /// Remove and return all the stored messages
+    pub fn take(&self) -> Vec<ChatMessage> {
+        self.0
+            .lock()
+            .map(|mut messages| std::mem::take(&mut *messages))
+            .unwrap_or_default()
+    }
+}
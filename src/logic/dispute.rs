@@ -0,0 +1,186 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::board_declaration_circuit::CircuitField,
+    crypto::proofs::PublicInput,
+    model::FieldState,
+    net::message::Message,
+    ui::{UiMessage, UiSender},
+    utils::{
+        log::Log,
+        result::{Er, Res},
+        worker_pool::{worker_pool, Priority},
+    },
+};
+
+use super::{
+    main::{GameKeys, NetSender},
+    GameMessage,
+};
+
+pub static EVIDENCE_DIR: &str = "evidence";
+
+/// Why one of the players considers the other one a cheater
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DisputeReason {
+    InvalidBoardProof,
+    InvalidFieldProof(u8, u8),
+}
+
+impl Display for DisputeReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisputeReason::InvalidBoardProof => write!(f, "invalid board correctness proof"),
+            DisputeReason::InvalidFieldProof(x, y) => {
+                write!(f, "invalid proof of the field ({x}, {y})")
+            }
+        }
+    }
+}
+
+/// Outcome of a dispute, presented to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Verdict {
+    /// We have caught the opponent. Evidence has been stored at `evidence_path`
+    OpponentCheated {
+        reason: DisputeReason,
+        evidence_path: String,
+    },
+    /// The opponent claims that we have cheated
+    AccusedByOpponent(DisputeReason),
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::OpponentCheated {
+                reason,
+                evidence_path,
+            } => write!(
+                f,
+                "Cheating detected: the opponent sent an {reason}. Evidence saved to {evidence_path}"
+            ),
+            Verdict::AccusedByOpponent(reason) => {
+                write!(f, "The opponent reported a dispute: {reason}")
+            }
+        }
+    }
+}
+
+/// Public inputs under which the offending proof has been checked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceInput {
+    pub hash: [u8; 32],
+    pub field: Option<(u8, u8, FieldState)>,
+}
+
+impl From<EvidenceInput> for PublicInput {
+    fn from(value: EvidenceInput) -> Self {
+        let input: PublicInput = value.hash.to_vec().into();
+        match value.field {
+            Some((x, y, state)) => {
+                input
+                    + CircuitField::from(x)
+                    + CircuitField::from(y)
+                    + CircuitField::from(state as u8)
+            }
+            None => input,
+        }
+    }
+}
+
+/// Offending message together with everything needed to check it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheatingEvidence {
+    pub reason: DisputeReason,
+    pub message: GameMessage,
+    pub input: EvidenceInput,
+}
+
+impl CheatingEvidence {
+    /// Store the evidence in the `EVIDENCE_DIR` directory. Returns path to the created file
+    pub fn save(&self) -> Res<String> {
+        fs::create_dir_all(EVIDENCE_DIR)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = format!("{EVIDENCE_DIR}/evidence-{timestamp}.json");
+        serde_json::to_writer_pretty(File::create(&path)?, self)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Res<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Check the stored proof again. Returns true if the proof is indeed invalid
+    pub fn confirms_cheating(self, keys: GameKeys) -> Res<bool> {
+        let input: PublicInput = self.input.into();
+        let is_correct = match self.message {
+            GameMessage::BoardDeclaration(mut proof, _) => {
+                proof.is_correct(input, keys.board_declaration_keys)?
+            }
+            GameMessage::FieldProof(mut proof, _) => {
+                proof.is_correct(input, keys.field_declaration_keys)?
+            }
+            _ => {
+                return Err(Er {
+                    message: "Evidence does not contain any proof".to_owned(),
+                })
+            }
+        };
+        Ok(!is_correct)
+    }
+}
+
+/// Store the evidence, notify the opponent and show the verdict.
+/// Returns the error that should end the game
+pub async fn report_cheating(
+    evidence: CheatingEvidence,
+    net_sender: &NetSender,
+    ui_sender: &UiSender,
+) -> Er {
+    let reason = evidence.reason;
+    let evidence_path = match worker_pool()
+        .run(Priority::Normal, move || evidence.save())
+        .await
+    {
+        Ok(Ok(path)) => path,
+        Ok(Err(e)) | Err(e) => {
+            let _ = ui_sender.warn(&format!("Could not store the evidence: {}", e.message));
+            "<not stored>".to_owned()
+        }
+    };
+    let _ = net_sender
+        .send(Message::Value(GameMessage::Dispute(reason)))
+        .await;
+    let _ = ui_sender
+        .send(UiMessage::Verdict(Verdict::OpponentCheated {
+            reason,
+            evidence_path,
+        }))
+        .await;
+
+    Er {
+        message: format!("Cheating detected: {reason}"),
+    }
+}
+
+/// Show the dispute raised by the opponent.
+/// Returns the error that should end the game
+pub async fn handle_dispute(reason: DisputeReason, ui_sender: &UiSender) -> Er {
+    let _ = ui_sender
+        .send(UiMessage::Verdict(Verdict::AccusedByOpponent(reason)))
+        .await;
+
+    Er {
+        message: format!("Opponent reported a dispute: {reason}"),
+    }
+}
@@ -1,28 +1,42 @@
+use std::time::Instant;
+
 use ark_std::iterable::Iterable;
+use futures::future::{pending, Either};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    circuit::{
-        board_declaration_circuit::CircuitField, field_declaration_circuit::FieldDeclarationCircuit,
-    },
-    crypto::proofs::{CorrectnessProof, PublicInput},
+    circuit::field_declaration_circuit::FieldDeclarationCircuit,
+    config::config,
+    crypto::proofs::CorrectnessProof,
     logic::GameMessage,
     model::FieldState,
     net::message::Message,
-    ui::{UiInput, UiReceiver, UiSender},
+    ui::{UiInput, UiMessage, UiReceiver, UiSender},
     utils::{
+        coordinates::{field_name, is_on_board},
         log::{Log, Logger},
-        result::{Er, Res},
-        threads::spawn_thread_async,
+        result::Res,
+        threads::select_first,
+        worker_pool::{worker_pool, JobHandle, Priority},
     },
 };
 
 use super::{
     board_creation::initialize_boards,
-    main::{GameKeys, NetReceiver, NetSender},
-    GameState,
+    certificate::game_id,
+    chat::ChatLog,
+    dispute::{handle_dispute, report_cheating, CheatingEvidence, DisputeReason, EvidenceInput},
+    history::ShotHistoryProof,
+    main::{GameKeys, NetSender},
+    precompute::ProofCache,
+    protocol::{violation, ProtocolReceiver, ProtocolState},
+    spectator::{SpectatorEvent, Spectators},
+    statistics::{GameRecord, Statistics},
+    transcript::GameTranscript,
+    GameState, SessionScore,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Player {
     Client,
     Host,
@@ -41,58 +55,305 @@ pub struct GameContext {
     pub player: Player,
     pub ui_receiver: UiReceiver,
     pub ui_sender: UiSender,
-    pub net_receiver: NetReceiver,
+    pub net_receiver: ProtocolReceiver,
     pub net_sender: NetSender,
     pub keys: GameKeys,
+    /// Our name, as presented to the opponent
+    pub name: String,
+    /// Name of the opponent
+    pub opponent: String,
+    pub chat: ChatLog,
+    pub spectators: Spectators,
+    /// Logs of the game, with the `game` field identifying the current game
+    pub logger: Logger,
+    /// Field proofs of our board generated ahead of the opponent's shots
+    pub proof_cache: ProofCache,
 }
 
 impl GameContext {
     pub async fn game_loop(&mut self) -> Res<()> {
-        self.ui_sender.send(crate::ui::UiMessage::Lobby).await?;
-        let (board, their_hash) = initialize_boards(self).await?;
-        self.ui_sender
-            .log_message("Boards have been successfully initialized!")?;
-        GameState {
-            board,
-            their_hash,
-            our_role: self.player,
-            our_shots: vec![],
-            their_shots: vec![],
-            turn_of: Player::Host,
+        let mut score = SessionScore::default();
+        loop {
+            self.ui_sender.send(UiMessage::Lobby).await?;
+            self.spectators.new_game();
+            // The game is identified by the board commitments, which are known only once both are declared
+            self.logger = self.logger.clone().without_field("game");
+            self.net_receiver.new_game();
+            let (board, their_hash, board_proofs) = initialize_boards(self).await?;
+            let (host_hash, client_hash) = match self.player {
+                Player::Host => (&board.hash, &their_hash),
+                Player::Client => (&their_hash, &board.hash),
+            };
+            let game = game_id(host_hash, client_hash);
+            self.logger = self.logger.clone().with_field("game", &game);
+            self.logger
+                .info("Boards have been successfully initialized!")?;
+            let mut state = GameState {
+                board,
+                their_hash,
+                our_role: self.player,
+                our_shots: vec![],
+                their_shots: vec![],
+                turn_of: score.next_starting_player(),
+                proof_times: vec![],
+            };
+            self.proof_cache
+                .start(board, self.keys.field_declaration_keys.clone());
+            let result = state.process(self).await;
+            self.proof_cache.clear();
+            let won = result?;
+            self.spectators.publish(SpectatorEvent::GameOver {
+                winner: if won {
+                    self.player
+                } else {
+                    self.player.other()
+                },
+            });
+
+            score.record(won);
+            if let Err(e) = Statistics::record(GameRecord::from_game(&state, &self.opponent, won)) {
+                self.logger
+                    .warn(&format!("Could not save statistics: {}", e.message))?;
+            }
+            let mut transcript = GameTranscript::from_game(
+                &state,
+                &self.name,
+                &self.opponent,
+                won,
+                self.chat.take(),
+            );
+            transcript.board_proofs = Some(board_proofs);
+            if let Err(e) = transcript.save() {
+                self.logger
+                    .warn(&format!("Could not save the transcript: {}", e.message))?;
+            }
+            self.ui_sender
+                .send(UiMessage::GameOver { won, score })
+                .await?;
+            let history = if config().history_proof {
+                self.start_history_proof(&state)?
+            } else {
+                None
+            };
+            self.negotiate_rematch(&state, &mut transcript, history)
+                .await?;
+        }
+    }
+
+    /// Start proving all our answers at once in the background, while the players decide about a rematch
+    fn start_history_proof(
+        &self,
+        state: &GameState,
+    ) -> Res<Option<JobHandle<Res<ShotHistoryProof>>>> {
+        let Some(keys) = self.keys.shot_history_keys.clone() else {
+            self.logger
+                .warn("The shot history keys are not generated, skipping the history proof")?;
+            return Ok(None);
+        };
+        self.logger.info(&format!(
+            "Proving the answers to all {} shots...",
+            state.their_shots.len()
+        ))?;
+        let (board, shots, logger) = (state.board, state.their_shots.clone(), self.logger.clone());
+        Ok(Some(worker_pool().spawn(Priority::Normal, move || {
+            ShotHistoryProof::create(board, shots, logger, keys)
+        })))
+    }
+
+    /// Send the proof of our answers to the opponent and the spectators, and keep it in the transcript
+    async fn publish_history(
+        &self,
+        proof: Res<ShotHistoryProof>,
+        transcript: &mut GameTranscript,
+    ) -> Res<()> {
+        let proof = match proof {
+            Ok(proof) => proof,
+            Err(e) => {
+                return self
+                    .logger
+                    .warn(&format!("Could not prove the shot history: {}", e.message))
+            }
+        };
+        self.net_sender
+            .send(Message::Value(GameMessage::ShotHistory(proof.clone())))
+            .await?;
+        self.spectators.publish(SpectatorEvent::ShotHistory {
+            owner: self.player,
+            proof: proof.clone(),
+        });
+        self.logger
+            .info("All our answers have been proven at once and sent to the opponent")?;
+        transcript.history_proof = Some(proof);
+        if let Err(e) = transcript.save() {
+            self.logger
+                .warn(&format!("Could not save the transcript: {}", e.message))?;
+        }
+        Ok(())
+    }
+
+    /// Check the opponent's proof of their answers and keep it in the transcript
+    async fn receive_history(
+        &self,
+        proof: ShotHistoryProof,
+        state: &GameState,
+        transcript: &mut GameTranscript,
+    ) -> Res<()> {
+        if proof.hash != state.their_hash || proof.shots != state.our_shots {
+            return self
+                .logger
+                .warn("The opponent's shot history does not match the game, ignoring it");
+        }
+        let (keys, proof_clone) = (self.keys.clone(), proof.clone());
+        let valid = worker_pool()
+            .run(Priority::High, move || proof_clone.verify(&keys))
+            .await?;
+        match valid {
+            Ok(true) => {
+                self.logger
+                    .info("The opponent has proven all their answers at once")?;
+                transcript.their_history_proof = Some(proof);
+                if let Err(e) = transcript.save() {
+                    self.logger
+                        .warn(&format!("Could not save the transcript: {}", e.message))?;
+                }
+                Ok(())
+            }
+            Ok(false) => self
+                .logger
+                .warn("The opponent's shot history proof is invalid, ignoring it"),
+            Err(e) => self.logger.warn(&format!(
+                "Could not verify the opponent's shot history: {}",
+                e.message
+            )),
+        }
+    }
+
+    /// Wait until both players agree to play another game, proving our answers meanwhile if asked to
+    async fn negotiate_rematch(
+        &mut self,
+        game: &GameState,
+        transcript: &mut GameTranscript,
+        mut history: Option<JobHandle<Res<ShotHistoryProof>>>,
+    ) -> Res<()> {
+        let mut offered_by_us = false;
+        let mut offered_by_them = false;
+
+        let state = ProtocolState::GameOver;
+        loop {
+            let (ui_receiver, net_receiver) = (&self.ui_receiver, &mut self.net_receiver);
+            let proven = async {
+                match &history {
+                    // A failed job is reported like a failed proof, without ending the game
+                    Some(job) => Ok(job.result().await.and_then(|proof| proof)),
+                    None => pending().await,
+                }
+            };
+            let input = select_first(
+                select_first(
+                    async { Ok(ui_receiver.get().await?) },
+                    net_receiver.next(state),
+                ),
+                proven,
+            )
+            .await?;
+            let input = match input {
+                Either::Left(input) => input,
+                Either::Right(proof) => {
+                    history = None;
+                    self.publish_history(proof, transcript).await?;
+                    continue;
+                }
+            };
+
+            match input {
+                Either::Left(UiInput::Rematch) => {
+                    if offered_by_them {
+                        self.net_sender
+                            .send(Message::Value(GameMessage::RematchAccept))
+                            .await?;
+                        break;
+                    }
+                    if !offered_by_us {
+                        offered_by_us = true;
+                        self.net_sender
+                            .send(Message::Value(GameMessage::RematchOffer))
+                            .await?;
+                        self.logger
+                            .info("Rematch offered, waiting for the opponent...")?;
+                    }
+                }
+                Either::Right(GameMessage::RematchOffer) => {
+                    // Both players offered a rematch at the same time
+                    if offered_by_us {
+                        break;
+                    }
+                    offered_by_them = true;
+                    self.logger
+                        .info("The opponent offers a rematch. Accept to play again")?;
+                }
+                Either::Right(GameMessage::RematchAccept) => {
+                    if offered_by_us {
+                        break;
+                    }
+                }
+                Either::Left(UiInput::ProveHistory) => {
+                    if history.is_some() {
+                        self.logger.info("Our answers are already being proven")?;
+                    } else if transcript.history_proof.is_some() {
+                        self.logger.info("Our answers have already been proven")?;
+                    } else {
+                        history = self.start_history_proof(game)?;
+                    }
+                }
+                Either::Right(GameMessage::ShotHistory(proof)) => {
+                    self.receive_history(proof, game, transcript).await?;
+                }
+                Either::Right(GameMessage::Dispute(reason)) => {
+                    return Err(handle_dispute(reason, &self.ui_sender).await);
+                }
+                Either::Right(other) => return Err(violation(&other, state)),
+                Either::Left(_) => {}
+            }
         }
-        .process(self)
-        .await?;
+
+        if history.is_some() {
+            self.logger.warn(
+                "Starting a new game before our answers have been proven, dropping the proof",
+            )?;
+        }
+        self.logger.info("Starting a new game")?;
         Ok(())
     }
 }
 
 impl GameState {
-    async fn process(&mut self, game_context: &mut GameContext) -> Res<()> {
+    /// Play the game until one of the players has lost. Returns true if we have won
+    async fn process(&mut self, game_context: &mut GameContext) -> Res<bool> {
         loop {
             game_context
                 .ui_sender
-                .send(crate::ui::UiMessage::PrintGameState(self.clone()))
+                .send(UiMessage::PrintGameState(self.clone()))
                 .await?;
 
             if are_all_discovered(&self.their_shots) {
-                game_context.ui_sender.log_message("We have lost...")?;
-                return Ok(());
+                game_context.logger.info("We have lost...")?;
+                log_pool_metrics(game_context)?;
+                return Ok(false);
             }
             if are_all_discovered(&self.our_shots) {
-                game_context.ui_sender.log_message("We have won!")?;
-                return Ok(());
+                game_context.logger.info("We have won!")?;
+                log_pool_metrics(game_context)?;
+                return Ok(true);
             }
 
             let should_switch: bool;
             if self.our_role == self.turn_of {
-                game_context
-                    .ui_sender
-                    .log_message("Processing our turn...")?;
+                game_context.logger.debug("Processing our turn...")?;
                 should_switch = self.process_our_turn(game_context).await?;
             } else {
                 game_context
-                    .ui_sender
-                    .log_message("Processing turn of the other player...")?;
+                    .logger
+                    .debug("Processing turn of the other player...")?;
                 should_switch = self.process_their_turn(game_context).await?;
             }
 
@@ -104,81 +365,150 @@ impl GameState {
     async fn process_our_turn(&mut self, game_context: &mut GameContext) -> Res<bool> {
         loop {
             if let UiInput::Shoot(x, y) = game_context.ui_receiver.get().await? {
+                // The opponent ends the game on such shots, so they are not sent
+                if !is_on_board(x, y) {
+                    game_context
+                        .logger
+                        .warn(&format!("({x}, {y}) is not a field of the board"))?;
+                    continue;
+                }
+                if self.our_shots.iter().any(|&(sx, sy, _)| (sx, sy) == (x, y)) {
+                    game_context.logger.warn(&format!(
+                        "We have already shot at {}, choose another field",
+                        field_name(x, y)
+                    ))?;
+                    continue;
+                }
                 game_context
-                    .ui_sender
-                    .log_message(&format!("Shooting at ({x}, {y})"))?;
+                    .logger
+                    .info(&format!("Shooting at ({x}, {y})"))?;
                 game_context
                     .net_sender
                     .send(Message::Value(GameMessage::AskForField(x, y)))
                     .await?;
-                loop {
-                    if let Message::Value(GameMessage::FieldProof(mut proof, state)) =
-                        game_context.net_receiver.get().await?
-                    {
+                game_context.spectators.publish(SpectatorEvent::Shot {
+                    shooter: self.our_role,
+                    x,
+                    y,
+                });
+                let protocol_state = ProtocolState::AwaitingFieldProof;
+                match game_context.net_receiver.next(protocol_state).await? {
+                    GameMessage::FieldProof(proof, state) => {
                         game_context
-                            .ui_sender
-                            .log_message("Received response, verifying...")?;
+                            .logger
+                            .info("Received response, verifying...")?;
                         let keys_clone = game_context.keys.field_declaration_keys.clone();
-                        let hash_input: PublicInput = self.their_hash.clone().to_vec().into();
-                        if !spawn_thread_async(move || {
-                            proof.is_correct(
-                                hash_input
-                                    + CircuitField::from(x)
-                                    + CircuitField::from(y)
-                                    + CircuitField::from(state as u8),
-                                keys_clone,
-                            )
-                        })
-                        .await??
+                        let input = EvidenceInput {
+                            hash: self.their_hash,
+                            field: Some((x, y, state)),
+                        };
+                        let input_clone = input.clone();
+                        let mut proof_clone = proof.clone();
+                        if !worker_pool()
+                            .run(Priority::High, move || {
+                                proof_clone.is_correct(input_clone.into(), keys_clone)
+                            })
+                            .await??
                         {
-                            return Err(Er {
-                                message: "Invalid proof of the field!".to_owned(),
-                            });
+                            let evidence = CheatingEvidence {
+                                reason: DisputeReason::InvalidFieldProof(x, y),
+                                message: GameMessage::FieldProof(proof, state),
+                                input,
+                            };
+                            return Err(report_cheating(
+                                evidence,
+                                &game_context.net_sender,
+                                &game_context.ui_sender,
+                            )
+                            .await);
                         }
-                        game_context.ui_sender.log_message(&format!(
+                        game_context.logger.info(&format!(
                             "Received proof is correct. The field ({x}, {y}) is {}",
                             match state {
                                 FieldState::Empty => "empty :(",
                                 FieldState::Occupied => "occupied!",
                             }
                         ))?;
+                        game_context.spectators.publish(SpectatorEvent::FieldProof {
+                            owner: self.our_role.other(),
+                            x,
+                            y,
+                            proof,
+                            state,
+                        });
                         self.our_shots.push((x, y, state));
                         return Ok(state == FieldState::Empty);
                     }
+                    GameMessage::Dispute(reason) => {
+                        return Err(handle_dispute(reason, &game_context.ui_sender).await);
+                    }
+                    other => return Err(violation(&other, protocol_state)),
                 }
             }
         }
     }
 
     async fn process_their_turn(&mut self, game_context: &mut GameContext) -> Res<bool> {
-        game_context
-            .ui_sender
-            .log_message("Waiting for opponent's query")?;
-        loop {
-            if let Message::Value(GameMessage::AskForField(x, y)) =
-                game_context.net_receiver.get().await?
-            {
-                game_context.ui_sender.log_message(&format!(
+        game_context.logger.info("Waiting for opponent's query")?;
+        let protocol_state = ProtocolState::TheirTurn;
+        match game_context.net_receiver.next(protocol_state).await? {
+            GameMessage::AskForField(x, y) => {
+                game_context.logger.info(&format!(
                     "Opponent asked for ({x}, {y}), generating proof..."
                 ))?;
+                game_context.spectators.publish(SpectatorEvent::Shot {
+                    shooter: self.our_role.other(),
+                    x,
+                    y,
+                });
                 let state = self.board.board.get_field_state(x, y);
-                let circ: FieldDeclarationCircuit = (self.board, x, y).into();
-                let logger: Logger = game_context.ui_sender.clone().into();
-                let keys = game_context.keys.field_declaration_keys.clone();
-                let proof =
-                    spawn_thread_async(move || CorrectnessProof::create(circ, logger, keys))
-                        .await??;
+                let proof = match game_context.proof_cache.take(x, y).await {
+                    Some(proof) => {
+                        game_context.logger.debug(&format!(
+                            "Using the precomputed proof, {} more ready",
+                            game_context.proof_cache.ready()
+                        ))?;
+                        proof
+                    }
+                    None => {
+                        let circ: FieldDeclarationCircuit = (self.board, x, y).into();
+                        let logger = game_context.logger.clone();
+                        let keys = game_context.keys.field_declaration_keys.clone();
+                        let now = Instant::now();
+                        let proof = worker_pool()
+                            .run(Priority::Normal, move || {
+                                CorrectnessProof::create(circ, logger, keys)
+                            })
+                            .await??;
+                        self.proof_times.push(now.elapsed());
+                        proof
+                    }
+                };
 
                 game_context
-                    .ui_sender
-                    .log_message("Field proof generated, sending...")?;
+                    .logger
+                    .info("Field proof generated, sending...")?;
                 game_context
                     .net_sender
-                    .send(Message::Value(GameMessage::FieldProof(proof, state)))
+                    .send(Message::Value(GameMessage::FieldProof(
+                        proof.clone(),
+                        state,
+                    )))
                     .await?;
+                game_context.spectators.publish(SpectatorEvent::FieldProof {
+                    owner: self.our_role,
+                    x,
+                    y,
+                    proof,
+                    state,
+                });
                 self.their_shots.push((x, y, state));
-                return Ok(state == FieldState::Empty);
+                Ok(state == FieldState::Empty)
             }
+            GameMessage::Dispute(reason) => {
+                Err(handle_dispute(reason, &game_context.ui_sender).await)
+            }
+            other => Err(violation(&other, protocol_state)),
         }
     }
 }
@@ -186,3 +516,9 @@ impl GameState {
 fn are_all_discovered(shots: &Vec<(u8, u8, FieldState)>) -> bool {
     35 == shots.iter().map(|v| v.2).fold(0, |a, b| a + b as u8)
 }
+
+fn log_pool_metrics(game_context: &GameContext) -> Res<()> {
+    game_context
+        .logger
+        .debug(&format!("Worker pool: {}", worker_pool().metrics()))
+}
@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::{
+        board_declaration_circuit::BoardDeclarationCircuit,
+        shot_history_circuit::{ShotHistoryCircuit, ShotHistoryInput, MAX_SHOTS},
+    },
+    crypto::{keys::ArkKeys, proofs::CorrectnessProof},
+    model::FieldState,
+    utils::{
+        coordinates::is_on_board,
+        log::Logger,
+        result::{Er, Res},
+    },
+};
+
+use super::main::GameKeys;
+
+/// One proof that all the answers given to the opponent's shots agree with the committed board.
+/// Lets a spectator or an auditor check a whole game instead of every field proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShotHistoryProof {
+    /// Commitment to the board the shots have been fired at
+    pub hash: [u8; 32],
+    pub shots: Vec<(u8, u8, FieldState)>,
+    pub proof: CorrectnessProof<ShotHistoryCircuit>,
+}
+
+impl ShotHistoryProof {
+    pub fn create(
+        board: BoardDeclarationCircuit,
+        shots: Vec<(u8, u8, FieldState)>,
+        logger: Logger,
+        keys: ArkKeys,
+    ) -> Res<Self> {
+        let circuit = ShotHistoryCircuit::from((board, shots.clone()));
+        Ok(ShotHistoryProof {
+            hash: board.hash,
+            shots,
+            proof: CorrectnessProof::create(circuit, logger, keys)?,
+        })
+    }
+
+    /// False if the shots are not distinct fields of the board or there are more than `MAX_SHOTS`
+    pub fn verify(&self, keys: &GameKeys) -> Res<bool> {
+        if !self.has_valid_shots() {
+            return Ok(false);
+        }
+        let Some(keys) = keys.shot_history_keys.clone() else {
+            return Err(Er {
+                message: "The shot history keys are not generated".to_owned(),
+            });
+        };
+        let input = ShotHistoryInput {
+            hash: self.hash,
+            shots: self.shots.clone(),
+        }
+        .into_public_input()?;
+        self.proof.clone().is_correct(input, keys)
+    }
+
+    /// The circuit rejects such shots too, checking them first saves verifying the proof
+    fn has_valid_shots(&self) -> bool {
+        let mut fields = HashSet::new();
+        self.shots.len() <= MAX_SHOTS
+            && (self.shots.iter()).all(|&(x, y, _)| is_on_board(x, y) && fields.insert((x, y)))
+    }
+}
@@ -1,25 +1,50 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_channel::Sender;
-use futures::{future::Either, pin_mut, select, FutureExt};
+use async_std::{
+    net::TcpListener,
+    task::{self, JoinHandle},
+};
+use futures::{future::Either, pin_mut, select, Future, FutureExt};
 
 use crate::{
-    crypto::keys::ArkKeys,
-    net::{connection::Endpoint, message::Message},
+    config::config,
+    crypto::{
+        key_store::{
+            board_keys_dir, field_keys_dir, generate_all_keys, history_keys_dir, KeyStatus,
+            KeysInfo, HISTORY_KEYS_SUBDIR,
+        },
+        keys::ArkKeys,
+    },
+    net::{
+        connection::Endpoint,
+        discovery::{announce_room, discover_games, RoomAnnouncement, DISCOVERY_PORT},
+        message::Message,
+    },
     ui::{UiInput, UiMessage, UiReceiver, UiSender},
     utils::{
-        async_receiver::AsyncReceiver,
-        log::Log,
+        async_receiver::{AsyncReceiver, Reclaim},
+        log::{Log, Logger},
         result::{Er, Res},
         threads::select_first,
+        worker_pool::{worker_pool, Priority},
     },
 };
 
 use super::{
+    chat::{ChatLog, ChatMessage},
     game_loop::{GameContext, Player},
+    precompute::ProofCache,
+    profile::PlayerProfile,
+    protocol::ProtocolReceiver,
+    spectator::{spectate_game, SpectatorEvent, Spectators},
+    statistics::Statistics,
     GameMessage,
 };
 
+/// How long to listen for the rooms announced in LAN
+const LAN_DISCOVERY_TIME: Duration = Duration::from_secs(2);
+
 pub type NetSender = Sender<Message<GameMessage>>;
 pub type NetReceiver = AsyncReceiver<Message<GameMessage>>;
 
@@ -56,65 +81,191 @@ pub async fn run_logic_async(ui_receiver: UiReceiver, ui_sender: UiSender) -> Re
 
 /// Enter the game's logic
 async fn logic_main_loop(mut ui_receiver: UiReceiver, ui_sender: UiSender) -> Res<()> {
-    let keys = GameKeys {
-        board_declaration_keys: ArkKeys::load(ui_sender.clone().into(), "keys/board_declaration"),
-        field_declaration_keys: ArkKeys::load(ui_sender.clone().into(), "keys/field_declaration"),
-    };
+    let mut keys = GameKeys::load(ui_sender.clone().into());
+    let mut key_fingerprint = KeysInfo::collect()
+        .fingerprint
+        .unwrap_or_else(|| "unknown".to_owned());
 
-    let interrupt_filter = |msg| async move {
-        match msg {
-            UiInput::Esc => Err(Er {
-                message: "Interrupted".to_owned(),
-            }),
-            _ => Ok(()),
-        }
-    };
+    let mut profile = PlayerProfile::load();
 
+    // Inputs not leaving the main screen do not need to redraw it
+    let mut show_main_screen = true;
+    let keys_info = KeysInfo::collect();
+    if !keys_info.is_complete() {
+        ui_sender.warn(&format!(
+            "The zk-SNARK keys are missing in {}. Generate them (it takes several minutes) or download them",
+            keys_info.dir
+        ))?;
+        ui_sender.send(UiMessage::Keys(keys_info)).await?;
+        show_main_screen = false;
+    }
     loop {
-        ui_sender.send(UiMessage::MainScreen).await?;
+        if show_main_screen {
+            ui_sender.send(UiMessage::MainScreen).await?;
+        }
+        show_main_screen = true;
 
         match ui_receiver.get().await? {
             crate::ui::UiInput::HostGame { addr, passwd } => {
-                if let Ok(Either::Right(endpoint)) = select_first(
-                    ui_receiver.consume_in_loop(interrupt_filter),
-                    Endpoint::<GameMessage>::accept_incoming_connection(
+                let spectators = Spectators::default();
+                let hosting = host_game(
+                    &addr,
+                    &passwd,
+                    &profile.name,
+                    &key_fingerprint,
+                    &ui_sender,
+                    &spectators,
+                );
+                ui_receiver = Box::pin(connect_and_play(
+                    ui_receiver,
+                    &ui_sender,
+                    hosting,
+                    Player::Host,
+                    &keys,
+                    &profile,
+                    spectators.clone(),
+                ))
+                .await?;
+            }
+            crate::ui::UiInput::JoinGame { addr, passwd } => {
+                let joining = Endpoint::<GameMessage>::create_connection_to(
+                    &addr,
+                    &passwd,
+                    &profile.name,
+                    config().net.limits(),
+                    ui_sender.clone().into(),
+                );
+                ui_receiver = Box::pin(connect_and_play(
+                    ui_receiver,
+                    &ui_sender,
+                    joining,
+                    Player::Client,
+                    &keys,
+                    &profile,
+                    Spectators::default(),
+                ))
+                .await?;
+            }
+            UiInput::HostViaRelay {
+                relay,
+                room,
+                passwd,
+            } => {
+                let hosting = Endpoint::<GameMessage>::host_via_relay(
+                    &relay,
+                    &room,
+                    &passwd,
+                    &profile.name,
+                    config().net.limits(),
+                    ui_sender.clone().into(),
+                );
+                ui_receiver = Box::pin(connect_and_play(
+                    ui_receiver,
+                    &ui_sender,
+                    hosting,
+                    Player::Host,
+                    &keys,
+                    &profile,
+                    Spectators::default(),
+                ))
+                .await?;
+            }
+            UiInput::JoinViaRelay {
+                relay,
+                room,
+                passwd,
+            } => {
+                let joining = Endpoint::<GameMessage>::join_via_relay(
+                    &relay,
+                    &room,
+                    &passwd,
+                    &profile.name,
+                    config().net.limits(),
+                    ui_sender.clone().into(),
+                );
+                ui_receiver = Box::pin(connect_and_play(
+                    ui_receiver,
+                    &ui_sender,
+                    joining,
+                    Player::Client,
+                    &keys,
+                    &profile,
+                    Spectators::default(),
+                ))
+                .await?;
+            }
+            UiInput::SetPlayerName(name) => {
+                profile.name = name;
+                match profile.save() {
+                    Ok(()) => ui_sender.log_message(&format!("Playing as {}", profile.name))?,
+                    Err(e) => {
+                        ui_sender.warn(&format!("Could not save the profile: {}", e.message))?
+                    }
+                }
+                show_main_screen = false;
+            }
+            UiInput::Spectate { addr, passwd } => {
+                let watching = async {
+                    let endpoint = Endpoint::<SpectatorEvent>::spectate(
                         &addr,
                         &passwd,
+                        &profile.name,
+                        config().net.limits(),
                         ui_sender.clone().into(),
-                    ),
-                )
-                .await
-                {
-                    ui_receiver = enter_lobby(
-                        ui_receiver,
-                        ui_sender.clone(),
-                        endpoint,
-                        Player::Host,
-                        keys.clone(),
                     )
                     .await?;
+                    spectate_game(endpoint, keys.clone(), &ui_sender).await
+                };
+                if let Err(e) =
+                    select_first(ui_receiver.consume_in_loop(interrupt_filter), watching).await
+                {
+                    ui_sender.log_message(&format!("Stopped watching: {}", e.message))?;
                 }
             }
-            crate::ui::UiInput::JoinGame { addr, passwd } => {
-                if let Ok(Either::Right(endpoint)) = select_first(
-                    ui_receiver.consume_in_loop(interrupt_filter),
-                    Endpoint::<GameMessage>::create_connection_to(
-                        &addr,
-                        &passwd,
-                        ui_sender.clone().into(),
-                    ),
-                )
-                .await
-                {
-                    ui_receiver = enter_lobby(
-                        ui_receiver,
-                        ui_sender.clone(),
-                        endpoint,
-                        Player::Client,
-                        keys.clone(),
-                    )
+            UiInput::ListLanGames => {
+                match discover_games(DISCOVERY_PORT, LAN_DISCOVERY_TIME, &key_fingerprint).await {
+                    Ok(games) => ui_sender.send(UiMessage::LanGames(games)).await?,
+                    Err(e) => {
+                        ui_sender.error(&e.message)?;
+                        ui_sender
+                            .send(UiMessage::LanDiscoveryFailed(e.message))
+                            .await?;
+                    }
+                }
+                show_main_screen = false;
+            }
+            UiInput::ShowStatistics => {
+                ui_sender
+                    .send(UiMessage::Statistics(Statistics::load()))
                     .await?;
+                show_main_screen = false;
+            }
+            UiInput::ShowKeys => {
+                ui_sender.send(UiMessage::Keys(KeysInfo::collect())).await?;
+                show_main_screen = false;
+            }
+            UiInput::GenerateKeys => {
+                ui_sender.log_message("Generating the keys, this takes several minutes...")?;
+                let logger = Logger::from(ui_sender.clone());
+                match worker_pool()
+                    .run(Priority::Normal, move || generate_all_keys(&logger))
+                    .await?
+                {
+                    Ok(()) => {
+                        keys = GameKeys::load(ui_sender.clone().into());
+                        ui_sender.log_message("The keys have been generated")?;
+                    }
+                    Err(e) => {
+                        ui_sender.error(&format!("Could not generate the keys: {}", e.message))?
+                    }
                 }
+                let keys_info = KeysInfo::collect();
+                key_fingerprint = keys_info
+                    .fingerprint
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_owned());
+                ui_sender.send(UiMessage::Keys(keys_info)).await?;
+                show_main_screen = false;
             }
             UiInput::Esc => {
                 return Ok(());
@@ -124,23 +275,194 @@ async fn logic_main_loop(mut ui_receiver: UiReceiver, ui_sender: UiSender) -> Re
     }
 }
 
+/// Used while connecting, to let the user give up
+async fn interrupt_filter(msg: UiInput) -> Res<()> {
+    match msg {
+        UiInput::Esc => Err(Er {
+            message: "Interrupted".to_owned(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Wait until the connection is established, unless interrupted, and play on it
+async fn connect_and_play(
+    mut ui_receiver: UiReceiver,
+    ui_sender: &UiSender,
+    connecting: impl Future<Output = Res<Endpoint<GameMessage>>>,
+    player: Player,
+    keys: &GameKeys,
+    profile: &PlayerProfile,
+    spectators: Spectators,
+) -> Res<UiReceiver> {
+    // Proofs are generated right after connecting, so the keys are loaded while waiting for the opponent.
+    // A corrupted proving key is reported before connecting
+    keys.preload_proving_keys();
+    if let Err(e) = keys.check_proving_keys().await {
+        ui_sender.error(&e.message)?;
+        return Ok(ui_receiver);
+    }
+    let result = match select_first(ui_receiver.consume_in_loop(interrupt_filter), connecting).await
+    {
+        Ok(Either::Right(endpoint)) => {
+            enter_lobby(
+                ui_receiver,
+                ui_sender.clone(),
+                endpoint,
+                player,
+                keys.clone(),
+                profile.clone(),
+                spectators.clone(),
+            )
+            .await
+        }
+        Ok(Either::Left(())) => Ok(ui_receiver),
+        Err(e) => {
+            ui_sender.error(&format!("Could not connect: {}", e.message))?;
+            Ok(ui_receiver)
+        }
+    };
+    spectators.close().await;
+    result
+}
+
+/// Wait for the opponent while announcing the room in LAN.
+/// Spectators are accepted on the same address until the session ends
+async fn host_game(
+    addr: &str,
+    passwd: &str,
+    name: &str,
+    key_fingerprint: &str,
+    ui_sender: &UiSender,
+    spectators: &Spectators,
+) -> Res<Endpoint<GameMessage>> {
+    let logger: Logger = ui_sender.clone().into();
+    logger.log_message(&format!("Listening on {}...", addr))?;
+    let listener = TcpListener::bind(addr).await?;
+    let on_spectator = {
+        let spectators = spectators.clone();
+        move |endpoint: Endpoint<GameMessage>| spectators.add(endpoint.into_other())
+    };
+
+    let accepting = Endpoint::<GameMessage>::accept_player(
+        &listener,
+        passwd,
+        name,
+        config().net.limits(),
+        logger.clone(),
+        on_spectator.clone(),
+    );
+    let endpoint = match addr.rsplit(':').next().and_then(|p| p.parse().ok()) {
+        None => accepting.await?,
+        Some(port) => {
+            let announcement = RoomAnnouncement {
+                room: name.to_owned(),
+                port,
+                password_required: !passwd.is_empty(),
+                key_fingerprint: key_fingerprint.to_owned(),
+            };
+            match select_first(
+                accepting,
+                announce_room(announcement, DISCOVERY_PORT, logger.clone()),
+            )
+            .await?
+            {
+                Either::Left(endpoint) => endpoint,
+                Either::Right(()) => {
+                    return Err(Er {
+                        message: "The room is no longer announced".to_owned(),
+                    })
+                }
+            }
+        }
+    };
+
+    let (passwd, name) = (passwd.to_owned(), name.to_owned());
+    spectators.set_acceptor(task::spawn(async move {
+        Endpoint::<GameMessage>::accept_spectators(
+            listener,
+            &passwd,
+            &name,
+            config().net.limits(),
+            logger,
+            on_spectator,
+        )
+        .await
+    }));
+    Ok(endpoint)
+}
+
+/// Send chat messages from the opponent straight to the UI and the chat log, and all the others
+/// to the returned receiver. The messages are attributed to the name the opponent gave in the handshake,
+/// not to the sender they claim
+pub fn route_chat(
+    net_receiver: NetReceiver,
+    ui_sender: UiSender,
+    chat: ChatLog,
+    opponent: String,
+) -> (NetReceiver, JoinHandle<NetReceiver>, Reclaim) {
+    net_receiver.into_bufferred(move |message, sender: Sender<Message<GameMessage>>| {
+        let ui_sender = ui_sender.clone();
+        let chat = chat.clone();
+        let opponent = opponent.clone();
+        async move {
+            match message {
+                Message::Info { info, .. } => {
+                    let message = ChatMessage::new(&opponent, &info, false);
+                    chat.push(message.clone());
+                    ui_sender.send(UiMessage::Chat(message)).await?;
+                }
+                x => sender.send(x).await?,
+            }
+            Ok(())
+        }
+    })
+}
+
 async fn enter_lobby(
     ui_receiver: UiReceiver,
     ui_sender: UiSender,
     endpoint: Endpoint<GameMessage>,
     player: Player,
     keys: GameKeys,
+    profile: PlayerProfile,
+    spectators: Spectators,
 ) -> Res<UiReceiver> {
+    let (name, opponent) = (profile.name.clone(), endpoint.second_name.clone());
+    match player {
+        Player::Host => spectators.set_players(&profile.name, &opponent),
+        Player::Client => spectators.set_players(&opponent, &profile.name),
+    }
+    let chat = ChatLog::default();
     let (net_sender, net_receiver, net_loop_task) = endpoint.as_channel_pair();
+
+    // Both have to live as long as the lobby, dropping the reclaim stops the routing
+    let (net_receiver, chat_task, chat_reclaim) = route_chat(
+        net_receiver,
+        ui_sender.clone(),
+        chat.clone(),
+        opponent.clone(),
+    );
+
     let net_sender_clone1 = net_sender.clone();
     let filter = {
         let counter = Arc::new(net_sender_clone1);
+        let ui_sender = ui_sender.clone();
+        let chat = chat.clone();
         move |input, sender: Sender<UiInput>| {
             let net_sender = Arc::clone(&counter);
+            let name = profile.name.clone();
+            let ui_sender = ui_sender.clone();
+            let chat = chat.clone();
             async move {
                 match input {
-                    UiInput::SendMessage(sender, info) => {
-                        net_sender.send(Message::Info { sender, info }).await?;
+                    UiInput::SendMessage(info) => {
+                        let message = ChatMessage::new(&name, &info, true);
+                        chat.push(message.clone());
+                        ui_sender.send(UiMessage::Chat(message)).await?;
+                        net_sender
+                            .send(Message::Info { sender: name, info })
+                            .await?;
                         Ok(())
                     }
                     UiInput::Esc => Err(Er {
@@ -157,13 +479,20 @@ async fn enter_lobby(
 
     let (filtret_ui_input, buffer_loop_task, reclaim) = ui_receiver.into_bufferred(filter);
     let ui_sender_clone = ui_sender.clone();
+    let logger = Logger::from(ui_sender.clone()).with_field("component", "game");
     let mut game_context = GameContext {
         player,
         ui_receiver: filtret_ui_input,
         ui_sender,
-        net_receiver,
+        net_receiver: ProtocolReceiver::new(net_receiver),
         net_sender,
         keys,
+        name,
+        opponent,
+        chat,
+        spectators,
+        logger,
+        proof_cache: ProofCache::new(config().precompute_proofs),
     };
 
     let buffer_loop_task_fuse = buffer_loop_task.fuse();
@@ -174,12 +503,14 @@ async fn enter_lobby(
     loop {
         select! {
             receiver = buffer_loop_task_fuse => {
+                chat_reclaim.ask().await;
+                chat_task.await;
                 return Ok(receiver);
             }
 
             r = game_loop_fuse => {
                 if let Err(e) = r {
-                    ui_sender_clone.log_message(&format!("Error in the main loop: {}", e.message))?;
+                    ui_sender_clone.error(&format!("Error in the main loop: {}", e.message))?;
                     reclaim.ask().await;
                 }
                 else {
@@ -189,7 +520,7 @@ async fn enter_lobby(
 
             r = net_loop_task_fuse => {
                 if let Err(e) = r {
-                    ui_sender_clone.log_message(&format!("Received network error: {}", e.message))?;
+                    ui_sender_clone.error(&format!("Received network error: {}", e.message))?;
                 }
             }
         }
@@ -200,4 +531,37 @@ async fn enter_lobby(
 pub struct GameKeys {
     pub board_declaration_keys: ArkKeys,
     pub field_declaration_keys: ArkKeys,
+    /// Keys of the shot history proofs, if they were generated
+    pub shot_history_keys: Option<ArkKeys>,
+}
+
+impl GameKeys {
+    /// Start loading the keys from the configured key directory
+    pub fn load(logger: Logger) -> Self {
+        let history_dir = history_keys_dir();
+        let shot_history_keys = KeyStatus::of(HISTORY_KEYS_SUBDIR, &history_dir)
+            .missing
+            .is_empty()
+            .then(|| ArkKeys::load(logger.clone(), &history_dir));
+        GameKeys {
+            board_declaration_keys: ArkKeys::load(logger.clone(), &board_keys_dir()),
+            field_declaration_keys: ArkKeys::load(logger, &field_keys_dir()),
+            shot_history_keys,
+        }
+    }
+
+    pub fn preload_proving_keys(&self) {
+        self.board_declaration_keys.preload_proving_key();
+        self.field_declaration_keys.preload_proving_key();
+    }
+
+    /// Wait until the digests of the preloaded proving keys are checked
+    pub async fn check_proving_keys(&self) -> Res<()> {
+        let keys = self.clone();
+        task::spawn_blocking(move || {
+            keys.board_declaration_keys.proving_key_checked()?;
+            keys.field_declaration_keys.proving_key_checked()
+        })
+        .await
+    }
 }
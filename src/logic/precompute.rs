@@ -0,0 +1,144 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_channel::Receiver;
+
+use crate::{
+    circuit::{
+        board_declaration_circuit::BoardDeclarationCircuit,
+        field_declaration_circuit::FieldDeclarationCircuit,
+    },
+    crypto::{keys::ArkKeys, proofs::CorrectnessProof},
+    utils::{
+        log::{background_logger, Log, Logger},
+        worker_pool::{worker_pool, Priority},
+    },
+};
+
+type FieldProof = CorrectnessProof<FieldDeclarationCircuit>;
+
+#[derive(Default)]
+struct Speculation {
+    /// Fields not proven yet, in the order of proving
+    pending: VecDeque<(u8, u8)>,
+    /// Fields being proven. The receiver is closed when the proof is stored
+    running: HashMap<(u8, u8), Receiver<()>>,
+    proofs: HashMap<(u8, u8), FieldProof>,
+    stopped: bool,
+}
+
+type Shared = Arc<Mutex<Speculation>>;
+
+fn lock(shared: &Shared) -> MutexGuard<'_, Speculation> {
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Field proofs generated in the background before the opponent asks for them.
+/// At most one proof less than the number of workers is generated at once, so a proof asked for never waits for a worker
+#[derive(Default)]
+pub struct ProofCache {
+    /// Proofs are not precomputed unless enabled
+    pub enabled: bool,
+    shared: Shared,
+}
+
+impl ProofCache {
+    pub fn new(enabled: bool) -> Self {
+        ProofCache {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    /// Start proving every field of the board, unless precomputation is disabled
+    pub fn start(&mut self, board: BoardDeclarationCircuit, keys: ArkKeys) {
+        self.clear();
+        if !self.enabled {
+            return;
+        }
+        // Speculative proofs are not shown in the UI
+        let logger = background_logger().with_field("component", "precompute");
+        // Fields are numbered from 1 to 10, as in the field declaration circuit
+        lock(&self.shared).pending = (1..=10)
+            .flat_map(|x| (1..=10).map(move |y| (x, y)))
+            .collect();
+        let lanes = worker_pool().metrics().workers.saturating_sub(1);
+        if lanes == 0 {
+            let _ = logger.debug("A single proof worker, the proofs are generated on demand");
+        }
+        for _ in 0..lanes {
+            prove_next(self.shared.clone(), board, keys.clone(), logger.clone());
+        }
+    }
+
+    /// Precomputed proof of the field. Waits for the proof if it is being generated,
+    /// since generating it again would take longer. The field is not proven later
+    pub async fn take(&mut self, x: u8, y: u8) -> Option<FieldProof> {
+        let running = {
+            let mut speculation = lock(&self.shared);
+            speculation.pending.retain(|&field| field != (x, y));
+            speculation.running.get(&(x, y)).cloned()
+        };
+        if let Some(done) = running {
+            let _ = done.recv().await;
+        }
+        lock(&self.shared).proofs.remove(&(x, y))
+    }
+
+    /// Number of proofs ready to be sent
+    pub fn ready(&self) -> usize {
+        lock(&self.shared).proofs.len()
+    }
+
+    /// Stop proving and forget the proofs of the finished game.
+    /// Proofs already being generated are stored in the old, discarded state
+    pub fn clear(&mut self) {
+        lock(&self.shared).stopped = true;
+        self.shared = Shared::default();
+    }
+}
+
+/// Prove the next pending field in the pool, then queue the one after it
+fn prove_next(shared: Shared, board: BoardDeclarationCircuit, keys: ArkKeys, logger: Logger) {
+    worker_pool().execute(Priority::Speculative, move || {
+        let (sender, receiver) = async_channel::bounded::<()>(1);
+        let field = {
+            let mut speculation = lock(&shared);
+            let field = if speculation.stopped {
+                None
+            } else {
+                speculation.pending.pop_front()
+            };
+            if let Some(field) = field {
+                speculation.running.insert(field, receiver);
+            }
+            field
+        };
+        let Some((x, y)) = field else {
+            return;
+        };
+
+        let circ: FieldDeclarationCircuit = (board, x, y).into();
+        let proof = CorrectnessProof::create(circ, logger.clone(), keys.clone());
+        {
+            let mut speculation = lock(&shared);
+            speculation.running.remove(&(x, y));
+            match proof {
+                Ok(proof) => {
+                    speculation.proofs.insert((x, y), proof);
+                }
+                Err(e) => {
+                    let _ = logger.warn(&format!(
+                        "Could not precompute the proof of ({x}, {y}): {}",
+                        e.message
+                    ));
+                }
+            }
+        }
+        // Wakes up `take` waiting for the proof
+        drop(sender);
+        prove_next(shared, board, keys, logger);
+    });
+}
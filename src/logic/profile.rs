@@ -0,0 +1,94 @@
+use std::{
+    fs::{self, File},
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    config::{config, Config},
+    utils::{
+        log::{background_logger, Log},
+        result::Res,
+    },
+};
+
+pub static PROFILE_DIR: &str = "profile";
+
+/// Read a JSON file of the profile directory, `None` if it does not exist yet or cannot be read.
+/// A file which cannot be parsed is moved aside to `<file>.corrupt-<timestamp>`, so saving the defaults
+/// over it loses nothing
+pub fn load_profile_file<T: DeserializeOwned>(path: &str) -> Option<T> {
+    let logger = background_logger();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            let _ = logger.error(&format!("Could not open {path}: {e}"));
+            return None;
+        }
+    };
+    let error = match serde_json::from_reader(io::BufReader::new(file)) {
+        Ok(value) => return Some(value),
+        Err(e) => e,
+    };
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let backup = format!("{path}.corrupt-{secs}");
+    let _ = match fs::rename(path, &backup) {
+        Ok(()) => logger.error(&format!("Invalid {path}, moved to {backup}: {error}")),
+        Err(e) => logger.error(&format!(
+            "Invalid {path}: {error}. Could not move it aside: {e}"
+        )),
+    };
+    None
+}
+
+/// Local player profile
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlayerProfile {
+    /// Name presented to the opponent
+    pub name: String,
+    /// Directory of the zk-SNARK keys saved by older versions, used when the config sets none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keys_dir: Option<String>,
+}
+
+impl Default for PlayerProfile {
+    fn default() -> Self {
+        PlayerProfile {
+            name: "Player".to_owned(),
+            keys_dir: None,
+        }
+    }
+}
+
+impl PlayerProfile {
+    fn path() -> String {
+        format!("{PROFILE_DIR}/profile.json")
+    }
+
+    /// Load the stored profile, or the default one if there is none yet, with the config applied
+    pub fn load() -> Self {
+        load_profile_file::<Self>(&Self::path())
+            .unwrap_or_default()
+            .configured(config())
+    }
+
+    /// The player name of the config, or of the command line, takes precedence over the stored one
+    pub fn configured(mut self, config: &Config) -> Self {
+        if let Some(name) = &config.player_name {
+            self.name = name.clone();
+        }
+        self
+    }
+
+    pub fn save(&self) -> Res<()> {
+        fs::create_dir_all(PROFILE_DIR)?;
+        serde_json::to_writer_pretty(File::create(Self::path())?, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::Display;
+
+use crate::{
+    net::message::Message,
+    utils::result::{Er, Res},
+};
+
+use super::{main::NetReceiver, GameMessage};
+
+/// Phase of the game in which we wait for a message of the opponent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolState {
+    /// Waiting for the board declaration of the opponent
+    AwaitingBoard,
+    /// We have asked for a field and wait for its proof
+    AwaitingFieldProof,
+    /// Waiting for the opponent to ask for a field
+    TheirTurn,
+    /// The game has ended, players agree on a rematch
+    GameOver,
+}
+
+impl ProtocolState {
+    pub const ALL: [ProtocolState; 4] = [
+        ProtocolState::AwaitingBoard,
+        ProtocolState::AwaitingFieldProof,
+        ProtocolState::TheirTurn,
+        ProtocolState::GameOver,
+    ];
+
+    pub fn classify(&self, kind: MessageKind) -> Classification {
+        use Classification::*;
+        use MessageKind::*;
+        match (self, kind) {
+            // Cheating can be reported at any moment
+            (_, Dispute) => Expected,
+            (ProtocolState::AwaitingBoard, BoardDeclaration) => Expected,
+            // The opponent may start its turn before we finish building our board
+            (ProtocolState::AwaitingBoard, AskForField) => Premature,
+            (ProtocolState::AwaitingFieldProof, FieldProof) => Expected,
+            (ProtocolState::TheirTurn, AskForField) => Expected,
+            (ProtocolState::GameOver, RematchOffer | RematchAccept | ShotHistory) => Expected,
+            // The proof of the previous game may be sent before the opponent sees the rematch agreed
+            (ProtocolState::AwaitingBoard, ShotHistory) => Ignored,
+            // The opponent may start the next game right after accepting the rematch
+            (ProtocolState::GameOver, BoardDeclaration) => Premature,
+            _ => Illegal,
+        }
+    }
+}
+
+impl Display for ProtocolState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolState::AwaitingBoard => write!(f, "waiting for the board"),
+            ProtocolState::AwaitingFieldProof => write!(f, "waiting for the field proof"),
+            ProtocolState::TheirTurn => write!(f, "waiting for the opponent's shot"),
+            ProtocolState::GameOver => write!(f, "after the game"),
+        }
+    }
+}
+
+/// `GameMessage` without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    BoardDeclaration,
+    AskForField,
+    FieldProof,
+    Dispute,
+    RematchOffer,
+    RematchAccept,
+    ShotHistory,
+}
+
+impl MessageKind {
+    pub const ALL: [MessageKind; 7] = [
+        MessageKind::BoardDeclaration,
+        MessageKind::AskForField,
+        MessageKind::FieldProof,
+        MessageKind::Dispute,
+        MessageKind::RematchOffer,
+        MessageKind::RematchAccept,
+        MessageKind::ShotHistory,
+    ];
+}
+
+impl From<&GameMessage> for MessageKind {
+    fn from(value: &GameMessage) -> Self {
+        match value {
+            GameMessage::BoardDeclaration(..) => MessageKind::BoardDeclaration,
+            GameMessage::AskForField(..) => MessageKind::AskForField,
+            GameMessage::FieldProof(..) => MessageKind::FieldProof,
+            GameMessage::Dispute(_) => MessageKind::Dispute,
+            GameMessage::RematchOffer => MessageKind::RematchOffer,
+            GameMessage::RematchAccept => MessageKind::RematchAccept,
+            GameMessage::ShotHistory(_) => MessageKind::ShotHistory,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    Expected,
+    /// Legal only in a later state, kept until then
+    Premature,
+    /// Sent for a game which has already ended, dropped
+    Ignored,
+    /// Protocol violation, ends the game
+    Illegal,
+}
+
+/// Messages of the opponent, checked against the state of the game
+pub struct ProtocolReceiver {
+    receiver: NetReceiver,
+    /// Premature messages, at most one of each kind
+    pending: Vec<GameMessage>,
+    /// Fields the opponent has asked for in the current game
+    shots: Vec<(u8, u8)>,
+}
+
+impl ProtocolReceiver {
+    pub fn new(receiver: NetReceiver) -> Self {
+        ProtocolReceiver {
+            receiver,
+            pending: vec![],
+            shots: vec![],
+        }
+    }
+
+    /// Forget the fields asked for in the previous game
+    pub fn new_game(&mut self) {
+        self.shots.clear();
+    }
+
+    /// Like `ProtocolState::classify`, but a shot outside of the board or at an already asked field is illegal
+    pub fn classify(&self, message: &GameMessage, state: ProtocolState) -> Classification {
+        match message {
+            GameMessage::AskForField(x, y) if self.is_illegal_shot(*x, *y) => {
+                Classification::Illegal
+            }
+            _ => state.classify(message.into()),
+        }
+    }
+
+    fn is_illegal_shot(&self, x: u8, y: u8) -> bool {
+        !(1..=10).contains(&x) || !(1..=10).contains(&y) || self.shots.contains(&(x, y))
+    }
+
+    fn violation(&self, message: &GameMessage, state: ProtocolState) -> Er {
+        match message {
+            GameMessage::AskForField(x, y) if self.is_illegal_shot(*x, *y) => Er {
+                message: format!(
+                    "Protocol violation: the opponent asked for ({x}, {y}), outside of the board or for the second time"
+                ),
+            },
+            _ => violation(message, state),
+        }
+    }
+
+    fn deliver(&mut self, message: GameMessage) -> GameMessage {
+        if let GameMessage::AskForField(x, y) = message {
+            self.shots.push((x, y));
+        }
+        message
+    }
+
+    /// Next message expected in the state. Premature messages are kept for later states
+    pub async fn next(&mut self, state: ProtocolState) -> Res<GameMessage> {
+        for i in 0..self.pending.len() {
+            match self.classify(&self.pending[i], state) {
+                Classification::Expected => {
+                    let message = self.pending.remove(i);
+                    return Ok(self.deliver(message));
+                }
+                Classification::Premature | Classification::Ignored => {}
+                Classification::Illegal => {
+                    return Err(self.violation(&self.pending[i], state));
+                }
+            }
+        }
+
+        loop {
+            let message = match self.receiver.get().await? {
+                Message::Value(message) => message,
+                _ => {
+                    return Err(Er {
+                        message: "Protocol violation: unexpected message during the game"
+                            .to_owned(),
+                    })
+                }
+            };
+            match self.classify(&message, state) {
+                Classification::Expected => return Ok(self.deliver(message)),
+                Classification::Ignored => {}
+                Classification::Premature
+                    if self
+                        .pending
+                        .iter()
+                        .all(|m| MessageKind::from(m) != MessageKind::from(&message)) =>
+                {
+                    self.pending.push(message)
+                }
+                Classification::Premature | Classification::Illegal => {
+                    return Err(self.violation(&message, state))
+                }
+            }
+        }
+    }
+}
+
+/// Error ending the game after the opponent has broken the protocol
+pub fn violation(message: &GameMessage, state: ProtocolState) -> Er {
+    Er {
+        message: format!(
+            "Protocol violation: the opponent sent {:?} {state}",
+            MessageKind::from(message)
+        ),
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::{Arc, Mutex};
+
+use async_channel::Sender;
+use async_std::task::JoinHandle;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::{
+        board_declaration_circuit::BoardDeclarationCircuit,
+        field_declaration_circuit::FieldDeclarationCircuit,
+    },
+    crypto::proofs::{CorrectnessProof, PublicInput},
+    model::{FieldState, SHIP_SIZES},
+    net::{connection::Endpoint, message::Message},
+    ui::{UiMessage, UiSender},
+    utils::{
+        coordinates::{field_name, is_on_board},
+        log::Log,
+        result::{Er, Res},
+        worker_pool::{worker_pool, Priority},
+    },
+};
+
+use super::{dispute::EvidenceInput, game_loop::Player, history::ShotHistoryProof, main::GameKeys};
+
+/// Public part of the protocol forwarded to the spectators.
+/// Never contains a board or a salt, so spectators learn only what the proofs reveal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpectatorEvent {
+    NewGame {
+        host: String,
+        client: String,
+    },
+    BoardCommitment {
+        player: Player,
+        proof: CorrectnessProof<BoardDeclarationCircuit>,
+        hash: [u8; 32],
+    },
+    Shot {
+        shooter: Player,
+        x: u8,
+        y: u8,
+    },
+    FieldProof {
+        /// Owner of the board the shot has been fired at
+        owner: Player,
+        x: u8,
+        y: u8,
+        proof: CorrectnessProof<FieldDeclarationCircuit>,
+        state: FieldState,
+    },
+    /// All the answers of the owner of the board proven at once, sent at the end of the game if enabled
+    ShotHistory {
+        owner: Player,
+        proof: ShotHistoryProof,
+    },
+    GameOver {
+        winner: Player,
+    },
+}
+
+#[derive(Default)]
+struct SpectatorsInner {
+    /// Names of the host and the client
+    players: (String, String),
+    senders: Vec<Sender<Message<SpectatorEvent>>>,
+    /// Events of the current game, replayed to the spectators joining late
+    history: Vec<SpectatorEvent>,
+    acceptor: Option<JoinHandle<Res<()>>>,
+}
+
+/// Spectators watching the games hosted by us
+#[derive(Clone, Default)]
+pub struct Spectators(Arc<Mutex<SpectatorsInner>>);
+
+impl Spectators {
+    fn with_inner<R>(&self, f: impl FnOnce(&mut SpectatorsInner) -> R) -> Option<R> {
+        self.0.lock().ok().map(|mut inner| f(&mut inner))
+    }
+
+    pub fn add(&self, endpoint: Endpoint<SpectatorEvent>) {
+        // Spectators are read-only, anything they send closes their connection
+        let (sender, _, _) = endpoint.as_channel_pair();
+        self.with_inner(|inner| {
+            for event in &inner.history {
+                let _ = sender.try_send(Message::Value(event.clone()));
+            }
+            inner.senders.push(sender);
+        });
+    }
+
+    pub fn publish(&self, event: SpectatorEvent) {
+        self.with_inner(|inner| {
+            inner
+                .senders
+                .retain(|sender| sender.try_send(Message::Value(event.clone())).is_ok());
+            inner.history.push(event);
+        });
+    }
+
+    pub fn set_players(&self, host: &str, client: &str) {
+        self.with_inner(|inner| inner.players = (host.to_owned(), client.to_owned()));
+    }
+
+    pub fn new_game(&self) {
+        let players = self.with_inner(|inner| {
+            inner.history.clear();
+            inner.players.clone()
+        });
+        if let Some((host, client)) = players {
+            self.publish(SpectatorEvent::NewGame { host, client });
+        }
+    }
+
+    /// Task accepting the spectators, stopped by `close`
+    pub fn set_acceptor(&self, acceptor: JoinHandle<Res<()>>) {
+        self.with_inner(|inner| inner.acceptor = Some(acceptor));
+    }
+
+    /// Stop accepting spectators and disconnect the present ones
+    pub async fn close(&self) {
+        let acceptor = self.with_inner(|inner| {
+            inner.senders.clear();
+            inner.acceptor.take()
+        });
+        if let Some(Some(acceptor)) = acceptor {
+            acceptor.cancel().await;
+        }
+    }
+}
+
+/// Game as seen by a spectator. Contains only the verified public information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpectatorState {
+    pub host: String,
+    pub client: String,
+    /// Shots fired at the board of the host
+    pub host_board: Vec<(u8, u8, FieldState)>,
+    /// Shots fired at the board of the client
+    pub client_board: Vec<(u8, u8, FieldState)>,
+    pub host_hash: Option<[u8; 32]>,
+    pub client_hash: Option<[u8; 32]>,
+    /// Decided by the verified answers, not by the host
+    pub winner: Option<Player>,
+    /// Number of proofs which did not verify or did not answer a shot, and of false claims of the host
+    pub invalid_proofs: u32,
+}
+
+impl SpectatorState {
+    pub fn name(&self, player: Player) -> &str {
+        match player {
+            Player::Host => &self.host,
+            Player::Client => &self.client,
+        }
+    }
+
+    fn hash_mut(&mut self, player: Player) -> &mut Option<[u8; 32]> {
+        match player {
+            Player::Host => &mut self.host_hash,
+            Player::Client => &mut self.client_hash,
+        }
+    }
+
+    fn board(&self, owner: Player) -> &[(u8, u8, FieldState)] {
+        match owner {
+            Player::Host => &self.host_board,
+            Player::Client => &self.client_board,
+        }
+    }
+
+    fn board_mut(&mut self, owner: Player) -> &mut Vec<(u8, u8, FieldState)> {
+        match owner {
+            Player::Host => &mut self.host_board,
+            Player::Client => &mut self.client_board,
+        }
+    }
+
+    /// True if the verified answers show every ship of the owner sunk
+    fn is_fleet_sunk(&self, owner: Player) -> bool {
+        let hits = self
+            .board(owner)
+            .iter()
+            .filter(|(_, _, state)| *state == FieldState::Occupied)
+            .count();
+        hits == SHIP_SIZES.iter().map(|&size| size as usize).sum::<usize>()
+    }
+}
+
+/// Watch the game, verifying every proof independently. Returns when the host disconnects
+pub async fn spectate_game(
+    endpoint: Endpoint<SpectatorEvent>,
+    keys: GameKeys,
+    ui_sender: &UiSender,
+) -> Res<()> {
+    let (_net_sender, net_receiver, _) = endpoint.as_channel_pair();
+    let mut state = SpectatorState::default();
+    // Shot waiting for its answer: the owner of the board and the field
+    let mut unanswered: Option<(Player, u8, u8)> = None;
+
+    loop {
+        let Ok(message) = net_receiver.get().await else {
+            return Err(Er {
+                message: "The host has closed the connection".to_owned(),
+            });
+        };
+        let Message::Value(event) = message else {
+            continue;
+        };
+
+        match event {
+            SpectatorEvent::NewGame { host, client } => {
+                ui_sender.log_message(&format!("New game: {host} (host) vs {client}"))?;
+                state = SpectatorState {
+                    host,
+                    client,
+                    ..Default::default()
+                };
+                unanswered = None;
+            }
+            SpectatorEvent::BoardCommitment {
+                player,
+                mut proof,
+                hash,
+            } => {
+                let keys = keys.board_declaration_keys.clone();
+                let input: PublicInput = hash.to_vec().into();
+                let valid = worker_pool()
+                    .run(Priority::High, move || proof.is_correct(input, keys))
+                    .await??;
+                let name = state.name(player).to_owned();
+                if valid {
+                    ui_sender.log_message(&format!("Board of {name} committed, proof verified"))?;
+                    *state.hash_mut(player) = Some(hash);
+                } else {
+                    ui_sender.log_message(&format!("!!! Board proof of {name} is INVALID !!!"))?;
+                    state.invalid_proofs += 1;
+                }
+            }
+            SpectatorEvent::Shot { shooter, x, y } => {
+                let name = state.name(shooter).to_owned();
+                // A repeated shot would count the same verified hit twice
+                let repeated = (state.board(shooter.other()).iter()).any(|f| (f.0, f.1) == (x, y));
+                if !is_on_board(x, y) || repeated {
+                    ui_sender.log_message(&format!(
+                        "!!! {name} shoots at ({x}, {y}), off the board or for the second time !!!"
+                    ))?;
+                    state.invalid_proofs += 1;
+                    continue;
+                }
+                ui_sender.log_message(&format!("{name} shoots at {}", field_name(x, y)))?;
+                unanswered = Some((shooter.other(), x, y));
+            }
+            SpectatorEvent::FieldProof {
+                owner,
+                x,
+                y,
+                mut proof,
+                state: field_state,
+            } => {
+                let name = state.name(owner).to_owned();
+                // Only the answer to the last shot counts, a proof of any other field reveals nothing
+                if unanswered.take() != Some((owner, x, y)) {
+                    ui_sender.log_message(&format!(
+                        "!!! {name} sent a proof for ({x}, {y}), which has not been shot at !!!"
+                    ))?;
+                    state.invalid_proofs += 1;
+                    continue;
+                }
+                let valid = match *state.hash_mut(owner) {
+                    Some(hash) => {
+                        let keys = keys.field_declaration_keys.clone();
+                        let input: PublicInput = EvidenceInput {
+                            hash,
+                            field: Some((x, y, field_state)),
+                        }
+                        .into();
+                        worker_pool()
+                            .run(Priority::High, move || proof.is_correct(input, keys))
+                            .await??
+                    }
+                    None => false,
+                };
+                if valid {
+                    ui_sender.log_message(&format!(
+                        "{name}: {} is {}, proof verified",
+                        field_name(x, y),
+                        match field_state {
+                            FieldState::Empty => "empty",
+                            FieldState::Occupied => "occupied",
+                        }
+                    ))?;
+                    state.board_mut(owner).push((x, y, field_state));
+                    if state.winner.is_none() && state.is_fleet_sunk(owner) {
+                        let winner = owner.other();
+                        ui_sender.log_message(&format!("{} has won!", state.name(winner)))?;
+                        state.winner = Some(winner);
+                    }
+                } else {
+                    ui_sender.log_message(&format!(
+                        "!!! Proof of {name} for {} is INVALID !!!",
+                        field_name(x, y)
+                    ))?;
+                    state.invalid_proofs += 1;
+                }
+            }
+            SpectatorEvent::ShotHistory { owner, proof } => {
+                let name = state.name(owner).to_owned();
+                // The proof has to cover exactly the answers verified one by one
+                let matches = *state.hash_mut(owner) == Some(proof.hash)
+                    && state.board(owner) == proof.shots.as_slice();
+                let count = proof.shots.len();
+                let keys = keys.clone();
+                let valid = worker_pool()
+                    .run(Priority::Normal, move || proof.verify(&keys))
+                    .await?;
+                match valid {
+                    Ok(true) if matches => ui_sender.log_message(&format!(
+                        "All {count} answers of {name} proven at once, proof verified"
+                    ))?,
+                    Ok(true) => {
+                        ui_sender.log_message(&format!(
+                            "!!! Shot history of {name} does not match the game !!!"
+                        ))?;
+                        state.invalid_proofs += 1;
+                    }
+                    Ok(false) => {
+                        ui_sender.log_message(&format!(
+                            "!!! Shot history proof of {name} is INVALID !!!"
+                        ))?;
+                        state.invalid_proofs += 1;
+                    }
+                    Err(e) => ui_sender.log_message(&format!(
+                        "Could not verify the shot history of {name}: {}",
+                        e.message
+                    ))?,
+                }
+            }
+            SpectatorEvent::GameOver { winner } => {
+                // The winner is already known from the verified answers, the host only confirms it
+                if state.winner != Some(winner) {
+                    ui_sender.log_message(&format!(
+                        "!!! The host claims that {} has won, which the verified answers do not show !!!",
+                        state.name(winner)
+                    ))?;
+                    state.invalid_proofs += 1;
+                }
+            }
+        }
+        ui_sender.send(UiMessage::Spectating(state.clone())).await?;
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs::{self, File},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::FieldState, utils::result::Res};
+
+use super::{
+    profile::{load_profile_file, PROFILE_DIR},
+    GameState,
+};
+
+/// Summary of a single finished game
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GameRecord {
+    pub opponent: String,
+    pub won: bool,
+    pub shots: u32,
+    pub hits: u32,
+    /// Average time of generating our field proofs
+    pub average_proof_time_ms: Option<u64>,
+    /// Seconds since the UNIX epoch
+    pub finished_at: u64,
+}
+
+impl GameRecord {
+    pub fn from_game(state: &GameState, opponent: &str, won: bool) -> Self {
+        let hits = state
+            .our_shots
+            .iter()
+            .filter(|(_, _, s)| *s == FieldState::Occupied)
+            .count();
+        let average_proof_time_ms = if state.proof_times.is_empty() {
+            None
+        } else {
+            let total: u128 = state.proof_times.iter().map(|t| t.as_millis()).sum();
+            Some((total / state.proof_times.len() as u128) as u64)
+        };
+
+        GameRecord {
+            opponent: opponent.to_owned(),
+            won,
+            shots: state.our_shots.len() as u32,
+            hits: hits as u32,
+            average_proof_time_ms,
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// All the games played with the local profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Statistics {
+    pub games: Vec<GameRecord>,
+}
+
+/// Values derived from the stored games
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatisticsSummary {
+    pub wins: u32,
+    pub losses: u32,
+    pub average_shots_to_win: Option<f64>,
+    pub hit_ratio: Option<f64>,
+    pub average_proof_time_ms: Option<u64>,
+    /// Number of games played against every opponent
+    pub opponents: BTreeMap<String, u32>,
+}
+
+impl Statistics {
+    fn path() -> String {
+        format!("{PROFILE_DIR}/statistics.json")
+    }
+
+    /// Load the stored statistics, or empty ones if there are none yet or they cannot be read
+    pub fn load() -> Self {
+        load_profile_file(&Self::path()).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Res<()> {
+        fs::create_dir_all(PROFILE_DIR)?;
+        serde_json::to_writer_pretty(File::create(Self::path())?, self)?;
+        Ok(())
+    }
+
+    /// Add the record to the stored statistics
+    pub fn record(record: GameRecord) -> Res<()> {
+        let mut stats = Self::load();
+        stats.games.push(record);
+        stats.save()
+    }
+
+    pub fn summary(&self) -> StatisticsSummary {
+        let wins = self.games.iter().filter(|g| g.won).count() as u32;
+        let won_games_shots: u32 = self.games.iter().filter(|g| g.won).map(|g| g.shots).sum();
+        let shots: u32 = self.games.iter().map(|g| g.shots).sum();
+        let hits: u32 = self.games.iter().map(|g| g.hits).sum();
+        let proof_times: Vec<u64> = self
+            .games
+            .iter()
+            .filter_map(|g| g.average_proof_time_ms)
+            .collect();
+
+        let mut opponents = BTreeMap::new();
+        for game in &self.games {
+            *opponents.entry(game.opponent.clone()).or_insert(0) += 1;
+        }
+
+        StatisticsSummary {
+            wins,
+            losses: self.games.len() as u32 - wins,
+            average_shots_to_win: (wins > 0).then(|| won_games_shots as f64 / wins as f64),
+            hit_ratio: (shots > 0).then(|| hits as f64 / shots as f64),
+            average_proof_time_ms: (!proof_times.is_empty())
+                .then(|| proof_times.iter().sum::<u64>() / proof_times.len() as u64),
+            opponents,
+        }
+    }
+}
+
+impl Display for StatisticsSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Wins: {}", self.wins)?;
+        writeln!(f, "Losses: {}", self.losses)?;
+        match self.average_shots_to_win {
+            Some(shots) => writeln!(f, "Average shots to win: {shots:.1}")?,
+            None => writeln!(f, "Average shots to win: -")?,
+        }
+        match self.hit_ratio {
+            Some(ratio) => writeln!(f, "Hit ratio: {:.1}%", ratio * 100.0)?,
+            None => writeln!(f, "Hit ratio: -")?,
+        }
+        match self.average_proof_time_ms {
+            Some(time) => writeln!(f, "Average proof time: {time} ms")?,
+            None => writeln!(f, "Average proof time: -")?,
+        }
+        writeln!(f, "Opponents played:")?;
+        for (opponent, games) in &self.opponents {
+            writeln!(f, "    {opponent}: {games}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs::{self, File},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    circuit::board_declaration_circuit::BoardDeclarationCircuit, crypto::proofs::CorrectnessProof,
+    model::FieldState, utils::result::Res,
+};
+
+use super::{
+    certificate::game_id, chat::ChatMessage, game_loop::Player, history::ShotHistoryProof,
+    GameState,
+};
+
+pub static TRANSCRIPT_DIR: &str = "transcripts";
+
+/// Board proofs exchanged at the start of the game, kept to certify it later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardProofs {
+    pub our_hash: [u8; 32],
+    pub ours: CorrectnessProof<BoardDeclarationCircuit>,
+    pub their_hash: [u8; 32],
+    pub theirs: CorrectnessProof<BoardDeclarationCircuit>,
+}
+
+/// Course of a finished game together with the chat held during it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameTranscript {
+    /// Our name as presented to the opponent, empty in the transcripts of older versions
+    #[serde(default)]
+    pub name: String,
+    pub opponent: String,
+    pub our_role: Player,
+    pub won: bool,
+    pub our_shots: Vec<(u8, u8, FieldState)>,
+    pub their_shots: Vec<(u8, u8, FieldState)>,
+    pub chat: Vec<ChatMessage>,
+    /// Seconds since the UNIX epoch
+    pub finished_at: u64,
+    /// Proof of our answers to `their_shots`, if enabled
+    #[serde(default)]
+    pub history_proof: Option<ShotHistoryProof>,
+    /// Proof of the opponent's answers to `our_shots`, if they sent one
+    #[serde(default)]
+    pub their_history_proof: Option<ShotHistoryProof>,
+    #[serde(default)]
+    pub board_proofs: Option<BoardProofs>,
+}
+
+impl GameTranscript {
+    pub fn from_game(
+        state: &GameState,
+        name: &str,
+        opponent: &str,
+        won: bool,
+        chat: Vec<ChatMessage>,
+    ) -> Self {
+        GameTranscript {
+            name: name.to_owned(),
+            opponent: opponent.to_owned(),
+            our_role: state.our_role,
+            won,
+            our_shots: state.our_shots.clone(),
+            their_shots: state.their_shots.clone(),
+            chat,
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            history_proof: None,
+            their_history_proof: None,
+            board_proofs: None,
+        }
+    }
+
+    /// Name of the transcript file, unique per game and player so that games finished within
+    /// the same second, or both sides of one game, do not overwrite each other
+    pub fn file_name(&self) -> String {
+        let role = match self.our_role {
+            Player::Host => "host",
+            Player::Client => "client",
+        };
+        match &self.board_proofs {
+            Some(boards) => {
+                let (host_hash, client_hash) = match self.our_role {
+                    Player::Host => (&boards.our_hash, &boards.their_hash),
+                    Player::Client => (&boards.their_hash, &boards.our_hash),
+                };
+                let id = game_id(host_hash, client_hash);
+                format!("game-{}-{id}-{role}.json", self.finished_at)
+            }
+            None => format!("game-{}-{role}.json", self.finished_at),
+        }
+    }
+
+    /// Store the transcript in the `TRANSCRIPT_DIR` directory. Returns path to the created file
+    pub fn save(&self) -> Res<String> {
+        fs::create_dir_all(TRANSCRIPT_DIR)?;
+        let path = format!("{TRANSCRIPT_DIR}/{}", self.file_name());
+        serde_json::to_writer_pretty(File::create(&path)?, self)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Res<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+}
@@ -1,13 +1,57 @@
-use battleships::{logic::run_logic_with_ui, ui::cli::run_cli, ui::gui::run_gui};
+use std::path::PathBuf;
+
+use battleships::{
+    circuit::report::{report_all, CircuitReport},
+    config::{config, set_config, Config, Theme, UiKind},
+    crypto::backend::{Backend, BackendKind, ProvingBackend},
+    crypto::key_store::{
+        generate_all_keys, keys_dir, resolve_keys_dir, set_keys_dir, verify_all_keys, KeysInfo,
+    },
+    logic::{
+        certificate::{certificate_keys, GameCertificate},
+        dispute::CheatingEvidence,
+        main::GameKeys,
+        profile::PlayerProfile,
+        run_logic_with_ui,
+        statistics::Statistics,
+        transcript::GameTranscript,
+    },
+    net::codec::WireFormat,
+    ui::cli::run_cli,
+    ui::gui::run_gui,
+    ui::headless::run_headless,
+    ui::tui::run_tui,
+    utils::log::{
+        file_sink::FileLog,
+        get_print_logger, set_background_logger,
+        tracing_sink::{init_tracing, TracingLog},
+        Level, Logger,
+    },
+};
 use clap::{Parser, Subcommand};
 
 fn main() {
     let cli = Cli::parse();
+    set_config(load_config(&cli));
+    WireFormat::force_json(cli.json_messages);
+    // Profiles of older versions stored the key directory
+    let profile_keys_dir = PlayerProfile::load().keys_dir;
+    set_keys_dir(&resolve_keys_dir(
+        cli.keys_dir.as_deref(),
+        config().keys_dir.as_deref().or(profile_keys_dir.as_deref()),
+    ));
+    init_logging();
 
     match &cli.command {
         Some(Command::GenerateKeys) => {
-            battleships::circuit::board_declaration_circuit::generate_keys();
-            battleships::circuit::field_declaration_circuit::generate_keys();
+            keys_command(&KeysCommand::Generate);
+        }
+        Some(Command::Keys { command }) => {
+            keys_command(command);
+        }
+        Some(Command::Config { command }) => {
+            config_command(&cli, command);
+            return;
         }
         Some(Command::Gui) => {
             run_logic_with_ui(run_gui);
@@ -15,9 +59,41 @@ fn main() {
         Some(Command::Cli) => {
             run_logic_with_ui(run_cli);
         }
-        None => {
-            run_logic_with_ui(run_gui);
+        Some(Command::Tui) => {
+            run_logic_with_ui(run_tui);
+        }
+        Some(Command::Headless) => {
+            // Standard output is reserved for JSON events
+            run_logic_with_ui(run_headless);
+            return;
+        }
+        Some(Command::VerifyEvidence { path }) => {
+            verify_evidence(path);
+        }
+        Some(Command::VerifyHistory { path }) => {
+            verify_history(path);
+        }
+        Some(Command::Certify { path }) => {
+            certify(path);
+        }
+        Some(Command::VerifyCertificate { path }) => {
+            verify_certificate(path);
+        }
+        Some(Command::Stats) => {
+            print!("{}", Statistics::load().summary());
         }
+        Some(Command::Bench { constraints_only }) => {
+            bench_command(*constraints_only);
+        }
+        None => match config().ui {
+            UiKind::Gui => run_logic_with_ui(run_gui),
+            UiKind::Cli => run_logic_with_ui(run_cli),
+            UiKind::Tui => run_logic_with_ui(run_tui),
+            UiKind::Headless => {
+                run_logic_with_ui(run_headless);
+                return;
+            }
+        },
     }
     println!("Następna stacja: Łódź Fabryczna")
 }
@@ -27,11 +103,322 @@ fn main() {
 struct Cli {
     #[clap(subcommand)]
     command: Option<Command>,
+    /// Send JSON instead of binary messages, to inspect the traffic
+    #[clap(long, global = true)]
+    json_messages: bool,
+    /// Lowest level of the messages written to the log file
+    #[clap(long, global = true)]
+    log_level: Option<Level>,
+    /// Print `tracing` events of at least `log-level` to the standard error. `--trace=false` turns it off
+    #[clap(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    trace: Option<bool>,
+    /// Directory of the zk-SNARK keys, overriding `BATTLESHIPS_KEYS_DIR` and the config file
+    #[clap(long, global = true)]
+    keys_dir: Option<String>,
+    /// Generate the proofs of our fields in the background, before the opponent asks for them.
+    /// `--precompute-proofs=false` turns it off
+    #[clap(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    precompute_proofs: Option<bool>,
+    /// Proof system of the keys and proofs: groth16 or marlin
+    #[clap(long, global = true)]
+    proving_backend: Option<BackendKind>,
+    /// Config file to use instead of `config.toml` in the platform config directory
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+    /// Name used until another one is set in the game
+    #[clap(long, global = true)]
+    player_name: Option<String>,
+    /// Threads generating and verifying proofs
+    #[clap(long, global = true)]
+    workers: Option<usize>,
+    /// Look of the window: ocean or dark
+    #[clap(long, global = true)]
+    theme: Option<Theme>,
+    /// At the end of every game, prove all the answers to the opponent's shots with one proof.
+    /// `--history-proof=false` turns it off
+    #[clap(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    history_proof: Option<bool>,
+}
+
+fn config_path(cli: &Cli) -> Option<PathBuf> {
+    cli.config.clone().or_else(Config::default_path)
+}
+
+/// The config file with the command line flags applied
+fn load_config(cli: &Cli) -> Config {
+    let mut config = match config_path(cli).map(|path| Config::load(&path)) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("{}. Using the default config", e.message);
+            Config::default()
+        }
+        None => Config::default(),
+    };
+    if let Some(level) = cli.log_level {
+        config.log.level = level;
+    }
+    if let Some(trace) = cli.trace {
+        config.log.trace = trace;
+    }
+    if let Some(name) = &cli.player_name {
+        config.player_name = Some(name.clone());
+    }
+    if let Some(workers) = cli.workers {
+        config.proof_workers = Some(workers);
+    }
+    if let Some(theme) = cli.theme {
+        config.gui.theme = theme;
+    }
+    if let Some(history_proof) = cli.history_proof {
+        config.history_proof = history_proof;
+    }
+    if let Some(precompute_proofs) = cli.precompute_proofs {
+        config.precompute_proofs = precompute_proofs;
+    }
+    if let Some(backend) = cli.proving_backend {
+        config.proving_backend = backend;
+    }
+    config
+}
+
+/// Every message shown in the UI is also written to a rotated log file
+fn init_logging() {
+    let log = &config().log;
+    let mut logger = Logger::default();
+    match FileLog::new(
+        &log.dir,
+        "battleships.log",
+        log.max_file_size,
+        log.max_files,
+    ) {
+        Ok(file) => logger = logger.with_sink(file, log.level),
+        Err(e) => eprintln!("Could not open the log file: {}", e.message),
+    }
+    if log.trace {
+        init_tracing(log.level);
+        logger = logger.with_sink(TracingLog, log.level);
+    }
+    set_background_logger(logger);
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
+    /// Same as `keys generate`
     GenerateKeys,
+    /// Manage the zk-SNARK keys
+    Keys {
+        #[clap(subcommand)]
+        command: KeysCommand,
+    },
     Gui,
     Cli,
+    /// Full-screen terminal interface
+    Tui,
+    /// Read JSON inputs from stdin and write JSON events to stdout
+    Headless,
+    /// Check the evidence file stored after detecting a cheating opponent
+    VerifyEvidence {
+        path: String,
+    },
+    /// Check the shot history proof stored in a game transcript
+    VerifyHistory {
+        path: String,
+    },
+    /// Compose the proofs stored in a game transcript into a game certificate
+    Certify {
+        path: String,
+    },
+    /// Check a game certificate, without the transcript of the game
+    VerifyCertificate {
+        path: String,
+    },
+    /// Show statistics of the games played with the local profile
+    Stats,
+    /// Report the size of every circuit and the time of its setup, proof and verification
+    Bench {
+        /// Only count the constraints, skipping the setup which takes minutes
+        #[clap(long)]
+        constraints_only: bool,
+    },
+    /// Inspect the config file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the config in effect, with the command line flags applied
+    Show,
+    /// Print the location of the config file
+    Path,
+}
+
+fn config_command(cli: &Cli, command: &ConfigCommand) {
+    let path = config_path(cli)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "-".to_owned());
+    match command {
+        ConfigCommand::Show => print!("# {path}\n{}", config()),
+        ConfigCommand::Path => println!("{path}"),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysCommand {
+    /// Generate new keys in the key directory. Players need the same keys to play together
+    Generate,
+    /// Show which keys are present and their fingerprint
+    Info,
+    /// Load all the keys, checking them against their checksums
+    Verify,
+    /// Print the key directory
+    Path,
+}
+
+fn keys_command(command: &KeysCommand) {
+    match command {
+        KeysCommand::Generate => match generate_all_keys(&get_print_logger()) {
+            Ok(()) => println!("{}", KeysInfo::collect()),
+            Err(e) => {
+                eprintln!("Could not generate the keys: {}", e.message);
+                std::process::exit(1);
+            }
+        },
+        KeysCommand::Info => println!("{}", KeysInfo::collect()),
+        KeysCommand::Verify => {
+            if let Err(e) = verify_all_keys(&get_print_logger()) {
+                eprintln!("{}", e.message);
+                std::process::exit(1);
+            }
+        }
+        KeysCommand::Path => println!("{}", keys_dir()),
+    }
+}
+
+fn bench_command(constraints_only: bool) {
+    println!(
+        "Sample circuits over {}, proven with {}",
+        Backend::CURVE.name(),
+        Backend::kind().name()
+    );
+    match report_all::<Backend>(!constraints_only) {
+        Ok(reports) => {
+            println!("{}", CircuitReport::header());
+            reports.iter().for_each(|report| println!("{report}"));
+        }
+        Err(e) => {
+            println!("Could not measure the circuits: {}", e.message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn verify_evidence(path: &str) {
+    let evidence = match CheatingEvidence::load(path) {
+        Ok(evidence) => evidence,
+        Err(e) => {
+            println!("Could not read the evidence: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+    println!("Checking evidence: {}", evidence.reason);
+
+    // Exits with 0 only if the proof is valid, so scripts can tell confirmed cheating apart
+    let keys = GameKeys::load(get_print_logger());
+    match evidence.confirms_cheating(keys) {
+        Ok(true) => {
+            println!("Verdict: the proof is INVALID, cheating confirmed");
+            std::process::exit(1);
+        }
+        Ok(false) => println!("Verdict: the proof is valid, the dispute is unfounded"),
+        Err(e) => {
+            println!("Could not verify the evidence: {}", e.message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn verify_history(path: &str) {
+    let proof = match GameTranscript::load(path) {
+        Ok(GameTranscript {
+            history_proof: Some(proof),
+            ..
+        }) => proof,
+        Ok(_) => {
+            println!("The transcript has no shot history proof, enable it with --history-proof");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("Could not read the transcript: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+    println!("Checking the answers to {} shots", proof.shots.len());
+
+    // Exits with 0 only if the proof is valid, like `verify-evidence`
+    let keys = GameKeys::load(get_print_logger());
+    match proof.verify(&keys) {
+        Ok(true) => println!("Verdict: the proof is valid, all the answers agree with the board"),
+        Ok(false) => {
+            println!("Verdict: the proof is INVALID");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("Could not verify the proof: {}", e.message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn certify(path: &str) {
+    let transcript = match GameTranscript::load(path) {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            println!("Could not read the transcript: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+    println!("Composing the proofs of the game. This can take a few minutes...");
+    let certificate = match GameCertificate::create(&transcript, get_print_logger()) {
+        Ok(certificate) => certificate,
+        Err(e) => {
+            println!("Could not certify the game: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+    match certificate.save() {
+        Ok(path) => println!("Certificate saved to {path}"),
+        Err(e) => {
+            println!("Could not save the certificate: {}", e.message);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn verify_certificate(path: &str) {
+    let certificate = match GameCertificate::load(path) {
+        Ok(certificate) => certificate,
+        Err(e) => {
+            println!("Could not read the certificate: {}", e.message);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Checking the certificate of the game {} between {} (host) and {} (client), won by the {:?}",
+        certificate.game_id, certificate.host_name, certificate.client_name, certificate.winner
+    );
+
+    match certificate.verify(certificate_keys(get_print_logger())) {
+        Ok(true) => println!("Verdict: the certificate is valid"),
+        Ok(false) => {
+            println!("Verdict: the certificate is INVALID");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            println!("Could not verify the certificate: {}", e.message);
+            std::process::exit(1);
+        }
+    }
 }
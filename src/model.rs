@@ -5,7 +5,7 @@ use crate::utils::ship_helpers::*;
 
 pub const SHIP_SIZES: [u8; 15] = [1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 4, 4, 5];
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Direction {
     // downwards
     Vertical = 0,
@@ -30,7 +30,7 @@ impl Direction {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Ship {
     pub x: u8,
     pub y: u8,
@@ -38,7 +38,7 @@ pub struct Ship {
     pub direction: Direction,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Board {
     pub ships: [Ship; 15],
 }
@@ -57,7 +57,7 @@ impl Board {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct IncompleteBoard(pub Vec<Ship>);
 
 impl IncompleteBoard {
@@ -1,2 +1,6 @@
+pub mod codec;
 pub mod connection;
+pub mod discovery;
 pub mod message;
+pub mod relay;
+pub mod transport;
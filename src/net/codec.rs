@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    crypto::backend::{Backend, BackendKind, ProvingBackend},
+    utils::result::{Er, Res},
+};
+
+/// Version of the protocol spoken between the players.
+/// Version 1 was sent without a handshake, as JSON with uncompressed proofs
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Prefix of the feature naming the curve of the proofs
+const CURVE_FEATURE_PREFIX: &str = "curve:";
+/// Prefix of the feature naming the proof system. Players not announcing it prove with Groth16
+const PROOF_FEATURE_PREFIX: &str = "proof:";
+
+/// Default limit of a single message, far above the size of any legitimate one
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// Encoding of the messages sent after the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// Human readable, for debugging
+    Json,
+    Bincode,
+}
+
+static FORCE_JSON: AtomicBool = AtomicBool::new(false);
+
+impl WireFormat {
+    /// Name announced in the feature list of the handshake
+    pub fn feature(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::Bincode => "bincode",
+        }
+    }
+
+    pub fn encode<M: Serialize>(&self, message: &M) -> Res<Vec<u8>> {
+        match self {
+            WireFormat::Json => Ok(serde_json::to_vec(message)?),
+            WireFormat::Bincode => Ok(bincode::serialize(message)?),
+        }
+    }
+
+    pub fn decode<M: DeserializeOwned>(&self, bytes: &[u8]) -> Res<M> {
+        match self {
+            WireFormat::Json => Ok(serde_json::from_slice(bytes)?),
+            WireFormat::Bincode => Ok(bincode::deserialize(bytes)?),
+        }
+    }
+
+    /// Offer only JSON in the following handshakes, so the traffic can be inspected
+    pub fn force_json(force: bool) {
+        FORCE_JSON.store(force, Ordering::Relaxed);
+    }
+}
+
+/// Length of the frame announced by its 4-byte prefix. Too long frames are refused before allocating them
+pub fn frame_length(prefix: [u8; 4], max_frame_size: usize) -> Res<usize> {
+    let length = u32::from_be_bytes(prefix) as usize;
+    if length > max_frame_size {
+        return Err(Er {
+            message: format!("Frame of {length} bytes exceeds the limit of {max_frame_size} bytes"),
+        });
+    }
+    Ok(length)
+}
+
+/// Message encoded and prefixed with its length
+pub fn encode_frame<M: Serialize>(
+    message: &M,
+    format: WireFormat,
+    max_frame_size: usize,
+) -> Res<Vec<u8>> {
+    let body = format.encode(message)?;
+    if body.len() > max_frame_size {
+        return Err(Er {
+            message: format!("Message of {} bytes is too long to be sent", body.len()),
+        });
+    }
+    let mut frame = (body.len() as u32).to_be_bytes().to_vec();
+    frame.extend(body);
+    Ok(frame)
+}
+
+/// Message of the complete frame, without its length prefix
+pub fn frame_body(frame: &[u8], max_frame_size: usize) -> Res<&[u8]> {
+    let Some((prefix, body)) = frame.split_first_chunk::<4>() else {
+        return Err(Er {
+            message: "Frame is too short".to_owned(),
+        });
+    };
+    if frame_length(*prefix, max_frame_size)? != body.len() {
+        return Err(Er {
+            message: "Frame length does not match its prefix".to_owned(),
+        });
+    }
+    Ok(body)
+}
+
+/// Inverse of `encode_frame`, the frame has to be complete
+pub fn decode_frame<M: DeserializeOwned>(
+    frame: &[u8],
+    format: WireFormat,
+    max_frame_size: usize,
+) -> Res<M> {
+    format.decode(frame_body(frame, max_frame_size)?)
+}
+
+/// Version and features announced by both sides at the beginning of the handshake
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+    pub features: Vec<String>,
+}
+
+impl Hello {
+    pub fn ours() -> Self {
+        let formats = if FORCE_JSON.load(Ordering::Relaxed) {
+            vec![WireFormat::Json]
+        } else {
+            vec![WireFormat::Bincode, WireFormat::Json]
+        };
+        let mut features: Vec<String> = formats.iter().map(|f| f.feature().to_owned()).collect();
+        features.push(Backend::CURVE.feature());
+        features.push(Backend::kind().feature());
+        Hello {
+            version: PROTOCOL_VERSION,
+            features,
+        }
+    }
+
+    /// Value of the announced feature starting with the prefix, e.g. the curve of `curve:bls12-381`
+    fn feature_value(&self, prefix: &str) -> Option<&str> {
+        self.features.iter().find_map(|f| f.strip_prefix(prefix))
+    }
+
+    fn supports(&self, format: WireFormat) -> bool {
+        self.features.iter().any(|f| f == format.feature())
+    }
+
+    /// Wire format used by both sides, bincode if both of them support it
+    pub fn negotiate(&self, other: &Hello) -> Res<WireFormat> {
+        if self.version != other.version {
+            return Err(Er {
+                message: format!(
+                    "Incompatible protocol versions: {} and {}",
+                    self.version, other.version
+                ),
+            });
+        }
+        let (ours, theirs) = (
+            self.feature_value(CURVE_FEATURE_PREFIX),
+            other.feature_value(CURVE_FEATURE_PREFIX),
+        );
+        if ours != theirs {
+            return Err(Er {
+                message: format!(
+                    "The opponent proves over {} and we over {}",
+                    theirs.unwrap_or("an unknown curve"),
+                    ours.unwrap_or("an unknown curve")
+                ),
+            });
+        }
+        let (ours, theirs) = (
+            self.feature_value(PROOF_FEATURE_PREFIX),
+            other.feature_value(PROOF_FEATURE_PREFIX),
+        );
+        let groth16 = BackendKind::Groth16.name();
+        if ours.unwrap_or(groth16) != theirs.unwrap_or(groth16) {
+            return Err(Er {
+                message: format!(
+                    "The opponent proves with {} and we with {}",
+                    theirs.unwrap_or(groth16),
+                    ours.unwrap_or(groth16)
+                ),
+            });
+        }
+        [WireFormat::Bincode, WireFormat::Json]
+            .into_iter()
+            .find(|&f| self.supports(f) && other.supports(f))
+            .ok_or(Er {
+                message: "No common wire format".to_owned(),
+            })
+    }
+}
@@ -1,58 +1,98 @@
-use std::marker::PhantomData;
+use std::{marker::PhantomData, time::Duration};
 
 use async_channel::Sender;
 use async_std::{
-    io::{ReadExt, WriteExt},
+    future::timeout,
     net::{TcpListener, TcpStream},
     task::{self, JoinHandle},
 };
-use futures::{
-    future::{select, Either},
-    pin_mut,
-};
+use futures::future::Either;
 use serde::{Deserialize, Serialize};
 
 use crate::utils::{
     async_receiver::AsyncReceiver,
     log::{Log, Logger},
     result::{Er, Res},
+    threads::select_first,
+};
+
+use super::{
+    codec::{encode_frame, Hello, WireFormat, DEFAULT_MAX_FRAME_SIZE},
+    message::Message,
+    relay::{enter_room, RelayRequest},
+    transport::Transport,
 };
 
-use super::message::Message;
+/// Kind of the connection accepted by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handshake {
+    Player,
+    Spectator,
+    Refused,
+}
+
+/// Limits protecting us against misbehaving and dead peers
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    /// Longer messages are refused without reading them
+    pub max_frame_size: usize,
+    /// Time allowed for receiving the rest of an already started message
+    pub read_timeout: Duration,
+    /// The peer is considered dead if nothing arrives for this long
+    pub idle_timeout: Duration,
+    /// A ping is sent if nothing else was sent for this long
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        ConnectionLimits {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(15),
+        }
+    }
+}
 
 pub struct Endpoint<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync> {
-    stream: TcpStream,
+    transport: Transport,
     pub second_addr: String,
+    /// Name of the player on the other side, exchanged during the handshake
+    pub second_name: String,
     pd: PhantomData<T>,
     logger: Logger,
+    /// Agreed in the handshake, JSON until then
+    pub format: WireFormat,
+    pub limits: ConnectionLimits,
 }
 
 impl<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync> Endpoint<T> {
     pub async fn send(&mut self, message: &Message<T>) -> Res<()> {
-        let to_send = serde_json::to_vec(message).unwrap();
-        let length = (to_send.len() as u32).to_be_bytes();
-
-        self.stream.write_all(&length).await?;
-        self.stream.write_all(&to_send).await?;
-
+        let frame = encode_frame(message, self.format, self.limits.max_frame_size)?;
+        self.transport.write_frame(frame).await?;
         Ok(())
     }
 
+    /// Next message from the peer. Pings are answered, pongs skipped
     pub async fn receive(&mut self) -> Res<Message<T>> {
-        let mut length_buf = [0u8; 4];
-        self.stream.read_exact(&mut length_buf).await?;
-        let response_length = u32::from_be_bytes(length_buf) as usize;
+        loop {
+            match self.receive_frame().await? {
+                Message::Ping => self.send(&Message::Pong).await?,
+                Message::Pong => {}
+                a => return Ok(a),
+            }
+        }
+    }
 
-        let mut buffer = vec![0; response_length];
-        self.stream.read_exact(&mut buffer).await?;
-        let res: Message<T> = serde_json::from_slice(&buffer)?;
+    async fn receive_frame(&mut self) -> Res<Message<T>> {
+        let buffer = self
+            .transport
+            .read_frame(&self.limits, &self.second_addr)
+            .await?;
+        let res: Message<T> = self.format.decode(&buffer)?;
 
         match res {
-            Message::Info { sender, info } => {
-                self.logger
-                    .log_message(&format!("{}|  {}> {}", self.second_addr, sender, info))?;
-                Ok(Message::Info { sender, info })
-            }
             Message::Error { sender, info } => {
                 self.logger
                     .log_message(&format!("{}|  {}!!!> {}", self.second_addr, sender, info))?;
@@ -62,63 +102,262 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync> Endpoint<T>
         }
     }
 
-    pub async fn accept_incoming_connection(addr: &str, passwd: &str, logger: Logger) -> Res<Self> {
+    fn from_transport(
+        transport: Transport,
+        second_addr: String,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Self {
+        Endpoint {
+            transport,
+            logger: logger
+                .with_field("component", "net")
+                .with_field("peer", &second_addr),
+            second_addr,
+            second_name: "".to_owned(),
+            pd: PhantomData,
+            format: WireFormat::Json,
+            limits,
+        }
+    }
+
+    /// Second handle of the same connection
+    fn try_clone(&self) -> Self {
+        Endpoint {
+            transport: self.transport.clone(),
+            second_addr: self.second_addr.clone(),
+            second_name: self.second_name.clone(),
+            pd: PhantomData,
+            logger: self.logger.clone(),
+            format: self.format,
+            limits: self.limits,
+        }
+    }
+
+    /// Client side of the version negotiation
+    async fn send_hello(&mut self) -> Res<()> {
+        let ours = Hello::ours();
+        self.send(&Message::Hello(ours.clone())).await?;
+        match self.receive().await? {
+            Message::Hello(theirs) => self.use_format(ours.negotiate(&theirs)?),
+            _ => Err(Er {
+                message: "Invalid response".to_owned(),
+            }),
+        }
+    }
+
+    /// Host side of the version negotiation. The peer is told why it is refused
+    async fn receive_hello(&mut self, name: &str) -> Res<()> {
+        let ours = Hello::ours();
+        let format = match self.receive().await? {
+            Message::Hello(theirs) => ours.negotiate(&theirs),
+            _ => Err(Er {
+                message: "Unsupported protocol version, please update the game".to_owned(),
+            }),
+        };
+        match format {
+            Ok(format) => {
+                self.send(&Message::Hello(ours)).await?;
+                self.use_format(format)
+            }
+            Err(e) => {
+                self.send(&Message::Error {
+                    sender: name.to_owned(),
+                    info: e.message.clone(),
+                })
+                .await?;
+                Err(e)
+            }
+        }
+    }
+
+    fn use_format(&mut self, format: WireFormat) -> Res<()> {
+        self.logger
+            .debug(&format!("Using {} messages", format.feature()))?;
+        self.format = format;
+        Ok(())
+    }
+
+    /// Host side of the handshake
+    async fn accept_handshake(&mut self, passwd: &str, name: &str) -> Res<Handshake> {
+        self.receive_hello(name).await?;
+        self.logger.log_message("Waiting for password...")?;
+        let (sender, received_passwd, handshake) = match self.receive().await? {
+            Message::Info { sender, info } => (sender, info, Handshake::Player),
+            Message::Spectate { sender, passwd } => (sender, passwd, Handshake::Spectator),
+            _ => {
+                self.logger
+                    .log_message("No password received, refusing connection.")?;
+                self.send(&Message::Error {
+                    sender: name.to_owned(),
+                    info: "Expected a password".to_owned(),
+                })
+                .await?;
+                return Ok(Handshake::Refused);
+            }
+        };
+
+        if received_passwd != passwd {
+            self.logger
+                .log_message("Incorrect password, refusing connection.")?;
+            self.send(&Message::Error {
+                sender: name.to_owned(),
+                info: "Incorrect password".to_owned(),
+            })
+            .await?;
+            return Ok(Handshake::Refused);
+        }
+        match handshake {
+            Handshake::Spectator => self
+                .logger
+                .log_message(&format!("{sender} is watching the game"))?,
+            _ => self
+                .logger
+                .log_message(&format!("Correct password. Playing with {sender}"))?,
+        }
+        self.second_name = sender;
+        self.send(&Message::Info {
+            sender: name.to_owned(),
+            info: "Password is correct".to_owned(),
+        })
+        .await?;
+        Ok(handshake)
+    }
+
+    /// Client side of the handshake
+    async fn connect_handshake(mut self, passwd: &str, name: &str) -> Res<Self> {
+        self.send_hello().await?;
+        self.send(&Message::Info {
+            sender: name.to_owned(),
+            info: passwd.to_owned(),
+        })
+        .await?;
+
+        if let Message::Info { sender, info: _ } = self.receive().await? {
+            self.logger.log_message(&format!("Playing with {sender}"))?;
+            self.second_name = sender;
+            Ok(self)
+        } else {
+            Err(Er {
+                message: "Invalid response".to_owned(),
+            })
+        }
+    }
+
+    pub async fn accept_incoming_connection(
+        addr: &str,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Res<Self> {
         logger.log_message(&format!("Listening on {}...", addr))?;
         let listener = TcpListener::bind(addr).await?;
+        Self::accept_player(&listener, passwd, name, limits, logger, |_| {}).await
+    }
 
+    /// Wait for the opponent. Spectators connecting in the meantime are passed to `on_spectator`.
+    /// The accepted endpoints use the limits, which also bound their handshakes
+    pub async fn accept_player(
+        listener: &TcpListener,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+        on_spectator: impl Fn(Self),
+    ) -> Res<Self> {
         loop {
             let (stream, second_addr) = listener.accept().await?;
             logger.log_message(&format!("Received connection from {}", second_addr))?;
-            let mut endpoint = Endpoint::<T> {
-                stream,
-                second_addr: second_addr.to_string(),
-                pd: PhantomData,
-                logger: logger.clone(),
-            };
 
-            logger.log_message("Waiting for password...")?;
-            if let Message::Info { sender: _, info } = endpoint.receive().await? {
-                if info == passwd {
-                    logger.log_message("Correct password")?;
-                    endpoint
-                        .send(&Message::Info {
-                            sender: "HOST".to_owned(),
-                            info: "Password is correct".to_owned(),
-                        })
-                        .await?;
-                } else {
-                    logger.log_message("Incorrect password, refusing connection.")?;
-                    endpoint
+            let second_addr = second_addr.to_string();
+            match Self::accept_connection(stream, second_addr, passwd, name, limits, &logger).await
+            {
+                Ok((endpoint, Handshake::Player)) => return Ok(endpoint),
+                Ok((endpoint, Handshake::Spectator)) => on_spectator(endpoint),
+                Ok((_, Handshake::Refused)) => {}
+                Err(e) => logger.warn(&format!("Handshake failed: {}", e.message))?,
+            }
+        }
+    }
+
+    /// Accept spectators of an already started game. Never returns successfully
+    pub async fn accept_spectators(
+        listener: TcpListener,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+        on_spectator: impl Fn(Self),
+    ) -> Res<()> {
+        loop {
+            let (stream, second_addr) = listener.accept().await?;
+
+            let second_addr = second_addr.to_string();
+            match Self::accept_connection(stream, second_addr, passwd, name, limits, &logger).await
+            {
+                Ok((endpoint, Handshake::Spectator)) => on_spectator(endpoint),
+                Ok((mut endpoint, Handshake::Player)) => {
+                    let _ = endpoint
                         .send(&Message::Error {
-                            sender: "HOST".to_owned(),
-                            info: "Incorrect password".to_owned(),
+                            sender: name.to_owned(),
+                            info: "The game has already started".to_owned(),
                         })
-                        .await?;
-                    continue;
+                        .await;
                 }
+                Ok((_, Handshake::Refused)) | Err(_) => {}
             }
-
-            return Ok(endpoint);
         }
     }
 
-    pub async fn create_connection_to(addr: &str, passwd: &str, logger: Logger) -> Res<Self> {
-        logger.log_message(&format!("Connecting to {}...", addr))?;
-        let stream = TcpStream::connect(addr).await?;
-        let mut endpoint = Endpoint {
-            second_addr: stream.local_addr()?.to_string().to_owned(),
-            stream,
-            pd: PhantomData,
-            logger: logger.clone(),
+    /// Host side of a new TCP or WebSocket connection.
+    /// Connections are accepted one by one, so the handshake has to finish within the read timeout
+    async fn accept_connection(
+        stream: TcpStream,
+        second_addr: String,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: &Logger,
+    ) -> Res<(Self, Handshake)> {
+        let handshake = async {
+            let transport = Transport::accept(stream, &limits).await?;
+            let mut endpoint =
+                Endpoint::from_transport(transport, second_addr.clone(), limits, logger.clone());
+            let handshake = endpoint.accept_handshake(passwd, name).await?;
+            Ok((endpoint, handshake))
         };
+        timeout(limits.read_timeout, handshake)
+            .await
+            .map_err(|_| Er {
+                message: format!("{second_addr} did not finish the handshake in time"),
+            })?
+    }
+
+    /// Connect to the host as a spectator of its game
+    pub async fn spectate(
+        addr: &str,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Res<Self> {
+        logger.log_message(&format!("Connecting to {}...", addr))?;
+        let transport = Transport::connect(addr, &limits).await?;
+        let mut endpoint = Endpoint::from_transport(transport, addr.to_owned(), limits, logger);
+        endpoint.send_hello().await?;
         endpoint
-            .send(&Message::Info {
-                sender: "CLIENT".to_owned(),
-                info: passwd.to_owned(),
+            .send(&Message::Spectate {
+                sender: name.to_owned(),
+                passwd: passwd.to_owned(),
             })
             .await?;
 
-        if let Message::Info { sender: _, info: _ } = endpoint.receive().await? {
+        if let Message::Info { sender, info: _ } = endpoint.receive().await? {
+            endpoint
+                .logger
+                .log_message(&format!("Watching the game of {sender}"))?;
+            endpoint.second_name = sender;
             Ok(endpoint)
         } else {
             Err(Er {
@@ -127,6 +366,86 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync> Endpoint<T>
         }
     }
 
+    /// The same connection carrying values of another type
+    pub fn into_other<U: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync>(
+        self,
+    ) -> Endpoint<U> {
+        Endpoint {
+            transport: self.transport,
+            second_addr: self.second_addr,
+            second_name: self.second_name,
+            pd: PhantomData,
+            logger: self.logger,
+            format: self.format,
+            limits: self.limits,
+        }
+    }
+
+    pub async fn create_connection_to(
+        addr: &str,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Res<Self> {
+        logger.log_message(&format!("Connecting to {}...", addr))?;
+        let transport = Transport::connect(addr, &limits).await?;
+        Endpoint::from_transport(transport, addr.to_owned(), limits, logger)
+            .connect_handshake(passwd, name)
+            .await
+    }
+
+    /// Create a room on the relay and wait for the opponent.
+    /// The room is created again if the opponent sends an incorrect password or stalls the handshake
+    pub async fn host_via_relay(
+        relay_addr: &str,
+        room: &str,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Res<Self> {
+        loop {
+            let request = RelayRequest::Host {
+                room: room.to_owned(),
+            };
+            let stream = enter_room(relay_addr, request, &limits, &logger).await?;
+            let mut endpoint = Endpoint::<T>::from_transport(
+                Transport::Tcp(stream),
+                relay_addr.to_owned(),
+                limits,
+                logger.clone(),
+            );
+
+            // Spectators are not supported by the relay
+            match timeout(limits.read_timeout, endpoint.accept_handshake(passwd, name)).await {
+                Ok(Ok(Handshake::Player)) => return Ok(endpoint),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(e),
+                Err(_) => logger.warn("The opponent did not finish the handshake in time")?,
+            }
+        }
+    }
+
+    /// Join the room created on the relay
+    pub async fn join_via_relay(
+        relay_addr: &str,
+        room: &str,
+        passwd: &str,
+        name: &str,
+        limits: ConnectionLimits,
+        logger: Logger,
+    ) -> Res<Self> {
+        let request = RelayRequest::Join {
+            room: room.to_owned(),
+        };
+        let stream = enter_room(relay_addr, request, &limits, &logger).await?;
+        let transport = Transport::Tcp(stream);
+        Endpoint::from_transport(transport, relay_addr.to_owned(), limits, logger)
+            .connect_handshake(passwd, name)
+            .await
+    }
+
     pub fn as_channel_pair(
         self,
     ) -> (
@@ -136,36 +455,48 @@ impl<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync> Endpoint<T>
     ) {
         async fn receive_loop<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync>(
             mut endpoint: Endpoint<T>,
-            to_send_receiver: AsyncReceiver<Message<T>>,
             received_sender: Sender<Message<T>>,
+            pong_sender: Sender<()>,
         ) -> Res<()> {
             loop {
-                let result = async {
-                    let received_fut = endpoint.receive();
-                    let to_send_fut = to_send_receiver.get();
-
-                    pin_mut!(received_fut, to_send_fut);
-
-                    match select(received_fut, to_send_fut).await {
-                        Either::Left(x) => Either::Left(x.0),
-                        Either::Right(x) => Either::Right(x.0),
-                    }
+                match endpoint.receive_frame().await? {
+                    Message::Ping => pong_sender.send(()).await?,
+                    Message::Pong => {}
+                    message => received_sender.send(message).await?,
                 }
-                .await;
-                match result {
-                    futures::future::Either::Left(x) => {
-                        received_sender.send(x?).await?;
-                    }
-                    futures::future::Either::Right(x) => {
-                        endpoint.send(&x?).await?;
-                    }
+            }
+        }
+
+        async fn send_loop<T: Serialize + for<'a> Deserialize<'a> + Send + 'static + Sync>(
+            mut endpoint: Endpoint<T>,
+            to_send_receiver: AsyncReceiver<Message<T>>,
+            pong_receiver: AsyncReceiver<()>,
+        ) -> Res<()> {
+            loop {
+                let next = select_first(async { Ok(to_send_receiver.get().await?) }, async {
+                    Ok(pong_receiver.get().await?)
+                });
+                let message = match timeout(endpoint.limits.heartbeat_interval, next).await {
+                    Ok(next) => match next? {
+                        Either::Left(message) => message,
+                        Either::Right(()) => Message::Pong,
+                    },
+                    Err(_) => Message::Ping,
                 };
+                endpoint.send(&message).await?;
             }
         }
 
         let (s_output, r_output) = async_channel::unbounded::<Message<T>>();
         let (s_input, r_input) = async_channel::unbounded();
-        let task = task::spawn(receive_loop(self, AsyncReceiver(r_input), s_output));
+        let (s_pong, r_pong) = async_channel::unbounded();
+        // Separate tasks for both directions, so a message is never abandoned in the middle
+        let receiving = receive_loop(self.try_clone(), s_output, s_pong);
+        let sending = send_loop(self, AsyncReceiver(r_input), AsyncReceiver(r_pong));
+        let task = task::spawn(async move {
+            select_first(receiving, sending).await?;
+            Ok(())
+        });
         (s_input, AsyncReceiver(r_output), task)
     }
 }
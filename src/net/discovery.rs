@@ -0,0 +1,139 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use async_std::{future::timeout, net::UdpSocket, task};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::utils::{
+    log::{Log, Logger},
+    result::{Er, Res},
+};
+
+/// UDP port on which the hosts announce their rooms
+pub const DISCOVERY_PORT: u16 = 34255;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// Distinguishes our announcements from other datagrams sent to the port
+const MAGIC: &str = "battleships-room";
+
+/// Room announced by a host waiting for the opponent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomAnnouncement {
+    /// Name of the hosting player
+    pub room: String,
+    /// Port on which the host accepts connections
+    pub port: u16,
+    pub password_required: bool,
+    /// Fingerprint of the verifying keys used by the host
+    pub key_fingerprint: String,
+}
+
+/// Room found in the local network
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LanGame {
+    /// Address to join the game at
+    pub addr: String,
+    pub announcement: RoomAnnouncement,
+    /// True if the host uses the same keys as we do
+    pub compatible: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Packet {
+    magic: String,
+    announcement: RoomAnnouncement,
+}
+
+/// Broadcast the announcement until the future is dropped.
+/// Never finishes; if the network does not allow broadcasting, the room is simply not announced
+pub async fn announce_room(
+    announcement: RoomAnnouncement,
+    discovery_port: u16,
+    logger: Logger,
+) -> Res<()> {
+    let packet = serde_json::to_vec(&Packet {
+        magic: MAGIC.to_owned(),
+        announcement,
+    })?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await;
+    let socket = match socket.and_then(|s| s.set_broadcast(true).map(|_| s)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            logger.warn(&format!("The room will not be announced in LAN: {e}"))?;
+            return futures::future::pending().await;
+        }
+    };
+
+    loop {
+        // The loopback copy makes rooms hosted on this machine visible as well
+        for target in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+            let _ = socket.send_to(&packet, (target, discovery_port)).await;
+        }
+        task::sleep(ANNOUNCE_INTERVAL).await;
+    }
+}
+
+/// Listen for the announcements for the given time
+pub async fn discover_games(
+    discovery_port: u16,
+    duration: Duration,
+    our_fingerprint: &str,
+) -> Res<Vec<LanGame>> {
+    let socket = listening_socket(discovery_port).map_err(|e| Er {
+        message: format!("Could not listen for LAN games on port {discovery_port}: {e}"),
+    })?;
+
+    let deadline = Instant::now() + duration;
+    let mut games: Vec<LanGame> = vec![];
+    let mut buffer = [0u8; 1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Ok(received) = timeout(remaining, socket.recv_from(&mut buffer)).await else {
+            return Ok(games);
+        };
+        let (length, source): (usize, SocketAddr) = received?;
+        let Ok(packet) = serde_json::from_slice::<Packet>(&buffer[..length]) else {
+            continue;
+        };
+        if packet.magic != MAGIC {
+            continue;
+        }
+
+        let addr = format!("{}:{}", source.ip(), packet.announcement.port);
+        // A room on this machine arrives both as a broadcast and as a loopback copy
+        let is_loopback = |addr: &str| {
+            addr.parse::<SocketAddr>()
+                .map(|a| a.ip().is_loopback())
+                .unwrap_or(false)
+        };
+        if let Some(game) = games.iter_mut().find(|g| {
+            g.addr == addr
+                || (g.announcement == packet.announcement
+                    && (is_loopback(&g.addr) || is_loopback(&addr)))
+        }) {
+            if is_loopback(&game.addr) {
+                game.addr = addr;
+            }
+            continue;
+        }
+        games.push(LanGame {
+            addr,
+            compatible: packet.announcement.key_fingerprint == our_fingerprint,
+            announcement: packet.announcement,
+        });
+    }
+}
+
+/// Socket receiving the announcements. The port is shared, so several games on one machine can browse at once
+fn listening_socket(discovery_port: u16) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, discovery_port)).into())?;
+    socket.set_nonblocking(true)?;
+    Ok(UdpSocket::from(std::net::UdpSocket::from(socket)))
+}
@@ -1,8 +1,26 @@
 use serde::{Deserialize, Serialize};
 
+use super::codec::Hello;
+
 #[derive(Serialize, Deserialize)]
 pub enum Message<T> {
-    Info { sender: String, info: String },
-    Error { sender: String, info: String },
+    /// Protocol version and features, always the first message of a connection
+    Hello(Hello),
+    Info {
+        sender: String,
+        info: String,
+    },
+    Error {
+        sender: String,
+        info: String,
+    },
+    /// First message of a read-only connection watching the game
+    Spectate {
+        sender: String,
+        passwd: String,
+    },
+    /// Heartbeat, sent when there was nothing else to send for a while
+    Ping,
+    Pong,
     Value(T),
 }
@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    net::Shutdown,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+use async_channel::Sender;
+use async_std::{
+    io::{self, ReadExt, WriteExt},
+    net::{TcpListener, TcpStream},
+    task,
+};
+use futures::future::Either;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    net::connection::ConnectionLimits,
+    utils::{
+        log::{Log, Logger},
+        result::{Er, Res},
+        threads::select_first,
+    },
+};
+
+/// Control frames are tiny, anything bigger is not a relay request
+const MAX_REQUEST_LENGTH: usize = 1024;
+/// A room without an opponent for this long is given up
+const MAX_WAIT_FOR_OPPONENT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits protecting the relay against players flooding it with connections
+#[derive(Debug, Clone, Copy)]
+pub struct RelayLimits {
+    /// Rooms waiting for the second player at the same time
+    pub max_rooms: usize,
+    /// Connections which have not sent their request yet. Further ones are closed right away
+    pub max_pending: usize,
+}
+
+impl Default for RelayLimits {
+    fn default() -> Self {
+        RelayLimits {
+            max_rooms: 1000,
+            max_pending: 100,
+        }
+    }
+}
+
+/// First frame sent by a player connecting to the relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayRequest {
+    Host { room: String },
+    Join { room: String },
+}
+
+/// Answer of the relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayResponse {
+    /// The room has been created, waiting for the second player
+    Waiting,
+    /// Both players are present. From now on every byte is forwarded to the other player
+    Paired,
+    Refused(String),
+}
+
+/// Waiting hosts. The stream of the joining player is handed over through the channel
+type Rooms = Arc<Mutex<HashMap<String, Sender<TcpStream>>>>;
+
+async fn write_frame<T: Serialize>(stream: &mut TcpStream, value: &T) -> Res<()> {
+    let bytes = serde_json::to_vec(value)?;
+    stream
+        .write_all(&(bytes.len() as u32).to_be_bytes())
+        .await?;
+    stream.write_all(&bytes).await?;
+    Ok(())
+}
+
+/// Frame of a control message. Its start has to arrive within `wait`, the rest within the read timeout
+async fn read_frame<T: for<'a> Deserialize<'a>>(
+    stream: &mut TcpStream,
+    wait: Duration,
+    limits: &ConnectionLimits,
+) -> Res<T> {
+    let mut length_buf = [0u8; 4];
+    io::timeout(wait, stream.read_exact(&mut length_buf)).await?;
+    let length = u32::from_be_bytes(length_buf) as usize;
+    if length > MAX_REQUEST_LENGTH {
+        return Err(Er {
+            message: format!("Relay frame too long: {length} bytes"),
+        });
+    }
+
+    let mut buffer = vec![0; length];
+    io::timeout(limits.read_timeout, stream.read_exact(&mut buffer)).await?;
+    Ok(serde_json::from_slice(&buffer)?)
+}
+
+/// Connect to the relay and wait until the second player joins the room.
+/// Returns the stream forwarded to the other player
+pub async fn enter_room(
+    relay_addr: &str,
+    request: RelayRequest,
+    limits: &ConnectionLimits,
+    logger: &Logger,
+) -> Res<TcpStream> {
+    logger.log_message(&format!("Connecting to the relay {}...", relay_addr))?;
+    let mut stream = TcpStream::connect(relay_addr).await?;
+    write_frame(&mut stream, &request).await?;
+
+    let mut wait = limits.read_timeout;
+    loop {
+        match read_frame(&mut stream, wait, limits).await? {
+            RelayResponse::Waiting => {
+                logger.log_message("Room created, waiting for the opponent...")?;
+                wait = MAX_WAIT_FOR_OPPONENT;
+            }
+            RelayResponse::Paired => return Ok(stream),
+            RelayResponse::Refused(reason) => return Err(Er { message: reason }),
+        }
+    }
+}
+
+/// Run the relay server. Hosts register named rooms, clients join them,
+/// and the relay forwards the framed stream between them without interpreting it.
+/// The connection limits bound the time given to the players for their requests
+pub async fn run_relay(
+    addr: &str,
+    limits: ConnectionLimits,
+    relay_limits: RelayLimits,
+    logger: Logger,
+) -> Res<()> {
+    let listener = TcpListener::bind(addr).await?;
+    logger.log_message(&format!("Relay listening on {}", listener.local_addr()?))?;
+    let rooms: Rooms = Arc::new(Mutex::new(HashMap::new()));
+    let pending = Arc::new(AtomicUsize::new(0));
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        if pending.fetch_add(1, Ordering::SeqCst) >= relay_limits.max_pending {
+            pending.fetch_sub(1, Ordering::SeqCst);
+            logger.warn(&format!("{peer}: too many pending connections, closed"))?;
+            continue;
+        }
+        let rooms = Arc::clone(&rooms);
+        let pending = Arc::clone(&pending);
+        let logger = logger.clone();
+        task::spawn(async move {
+            let handled = handle_player(stream, rooms, pending, &limits, &relay_limits, &logger);
+            if let Err(e) = handled.await {
+                let _ = logger.warn(&format!("{peer}: {}", e.message));
+            }
+        });
+    }
+}
+
+fn lock_rooms(rooms: &Rooms) -> Res<MutexGuard<'_, HashMap<String, Sender<TcpStream>>>> {
+    rooms.lock().map_err(|_| Er {
+        message: "Rooms are unavailable".to_owned(),
+    })
+}
+
+async fn handle_player(
+    mut stream: TcpStream,
+    rooms: Rooms,
+    pending: Arc<AtomicUsize>,
+    limits: &ConnectionLimits,
+    relay_limits: &RelayLimits,
+    logger: &Logger,
+) -> Res<()> {
+    let request = read_frame(&mut stream, limits.read_timeout, limits).await;
+    pending.fetch_sub(1, Ordering::SeqCst);
+    match request? {
+        RelayRequest::Host { room } => {
+            let (sender, receiver) = async_channel::bounded(1);
+            let refused = {
+                let mut rooms = lock_rooms(&rooms)?;
+                if rooms.contains_key(&room) {
+                    Some(format!("Room {room} already exists"))
+                } else if rooms.len() >= relay_limits.max_rooms {
+                    Some("The relay has no free room".to_owned())
+                } else {
+                    rooms.insert(room.clone(), sender.clone());
+                    None
+                }
+            };
+            if let Some(reason) = refused {
+                return write_frame(&mut stream, &RelayResponse::Refused(reason)).await;
+            }
+            logger.log_message(&format!("Room {room} created"))?;
+            write_frame(&mut stream, &RelayResponse::Waiting).await?;
+
+            // The host sends nothing before being paired, so any read means it has left
+            let mut host_reader = stream.clone();
+            let mut probe = [0u8; 1];
+            let joined = select_first(async { Ok(receiver.recv().await?) }, async {
+                host_reader.read(&mut probe).await?;
+                Ok(())
+            })
+            .await;
+
+            {
+                let mut rooms = lock_rooms(&rooms)?;
+                if rooms.get(&room).is_some_and(|s| s.same_channel(&sender)) {
+                    rooms.remove(&room);
+                }
+            }
+            match joined {
+                Ok(Either::Left(client)) => forward(room, stream, client, logger).await,
+                _ => logger.log_message(&format!("Room {room}: the host has left")),
+            }
+        }
+        RelayRequest::Join { room } => {
+            let host = lock_rooms(&rooms)?.remove(&room);
+            let reason = format!("There is no room {room}");
+            match host {
+                Some(host) => {
+                    if let Err(e) = host.send(stream).await {
+                        let mut stream = e.into_inner();
+                        write_frame(&mut stream, &RelayResponse::Refused(reason)).await?;
+                    }
+                    Ok(())
+                }
+                None => write_frame(&mut stream, &RelayResponse::Refused(reason)).await,
+            }
+        }
+    }
+}
+
+/// Pair the players and copy the bytes between them until one of them disconnects
+async fn forward(
+    room: String,
+    mut host: TcpStream,
+    mut client: TcpStream,
+    logger: &Logger,
+) -> Res<()> {
+    write_frame(&mut host, &RelayResponse::Paired).await?;
+    write_frame(&mut client, &RelayResponse::Paired).await?;
+    logger.log_message(&format!("Room {room}: players paired"))?;
+
+    let _ = select_first(
+        async { Ok(io::copy(&mut host.clone(), &mut client.clone()).await?) },
+        async { Ok(io::copy(&mut client.clone(), &mut host.clone()).await?) },
+    )
+    .await;
+    let _ = host.shutdown(Shutdown::Both);
+    let _ = client.shutdown(Shutdown::Both);
+    logger.log_message(&format!("Room {room}: closed"))
+}
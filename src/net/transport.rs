@@ -0,0 +1,169 @@
+use std::{sync::Arc, time::Duration};
+
+use async_std::{
+    future::timeout,
+    io::{ReadExt, WriteExt},
+    net::TcpStream,
+    sync::Mutex,
+    task,
+};
+use async_tungstenite::{
+    accept_async_with_config, client_async_with_config,
+    tungstenite::{protocol::WebSocketConfig, Message as WsMessage},
+    WebSocketStream,
+};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+
+use crate::utils::result::{Er, Res};
+
+use super::{
+    codec::{frame_body, frame_length},
+    connection::ConnectionLimits,
+};
+
+type WsStream = WebSocketStream<TcpStream>;
+
+/// Connection carrying the frames of an `Endpoint`.
+/// Clones share the connection, so one task can read while another one writes
+#[derive(Clone)]
+pub enum Transport {
+    Tcp(TcpStream),
+    /// Every frame is sent as a single binary WebSocket message
+    WebSocket {
+        sink: Arc<Mutex<SplitSink<WsStream, WsMessage>>>,
+        stream: Arc<Mutex<SplitStream<WsStream>>>,
+    },
+}
+
+impl Transport {
+    /// Accept a raw TCP or a WebSocket client, recognised by the HTTP request starting the connection
+    pub async fn accept(stream: TcpStream, limits: &ConnectionLimits) -> Res<Self> {
+        let is_http = timeout(limits.read_timeout, starts_with_http(&stream))
+            .await
+            .map_err(|_| Er {
+                message: "The client did not start the handshake".to_owned(),
+            })??;
+        if !is_http {
+            return Ok(Transport::Tcp(stream));
+        }
+        Ok(
+            accept_async_with_config(stream, Some(websocket_config(limits)))
+                .await?
+                .into(),
+        )
+    }
+
+    /// Connect with WebSocket to `ws://` URLs, and with raw TCP to other addresses
+    pub async fn connect(addr: &str, limits: &ConnectionLimits) -> Res<Self> {
+        let Some(rest) = addr.strip_prefix("ws://") else {
+            return Ok(Transport::Tcp(TcpStream::connect(addr).await?));
+        };
+        let host = rest.split('/').next().unwrap_or(rest);
+        let stream = TcpStream::connect(host).await?;
+        let (websocket, _) =
+            client_async_with_config(addr, stream, Some(websocket_config(limits))).await?;
+        Ok(websocket.into())
+    }
+
+    pub async fn write_frame(&mut self, frame: Vec<u8>) -> Res<()> {
+        match self {
+            Transport::Tcp(stream) => stream.write_all(&frame).await?,
+            Transport::WebSocket { sink, .. } => {
+                sink.lock().await.send(WsMessage::Binary(frame)).await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Body of the next frame. `peer` is used in the error messages
+    pub async fn read_frame(&mut self, limits: &ConnectionLimits, peer: &str) -> Res<Vec<u8>> {
+        let stopped_responding = |_| Er {
+            message: format!("{peer} stopped responding"),
+        };
+        match self {
+            Transport::Tcp(stream) => {
+                let mut length_buf = [0u8; 4];
+                timeout(limits.idle_timeout, stream.read_exact(&mut length_buf))
+                    .await
+                    .map_err(stopped_responding)??;
+                let response_length = frame_length(length_buf, limits.max_frame_size)?;
+
+                let mut buffer = vec![0; response_length];
+                timeout(limits.read_timeout, stream.read_exact(&mut buffer))
+                    .await
+                    .map_err(|_| Er {
+                        message: format!("Timed out while receiving a message from {peer}"),
+                    })??;
+                Ok(buffer)
+            }
+            Transport::WebSocket { stream, .. } => {
+                let mut stream = stream.lock().await;
+                loop {
+                    let message = timeout(limits.idle_timeout, stream.next())
+                        .await
+                        .map_err(stopped_responding)?;
+                    match message {
+                        Some(Ok(WsMessage::Binary(frame))) => {
+                            return Ok(frame_body(&frame, limits.max_frame_size)?.to_vec());
+                        }
+                        // Control frames are answered by the library
+                        Some(Ok(WsMessage::Ping(_) | WsMessage::Pong(_))) => {}
+                        Some(Ok(_)) => {
+                            return Err(Er {
+                                message: format!("Unexpected WebSocket message from {peer}"),
+                            })
+                        }
+                        Some(Err(e)) => return Err(e.into()),
+                        None => {
+                            return Err(Er {
+                                message: format!("{peer} has closed the connection"),
+                            })
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<WsStream> for Transport {
+    fn from(value: WsStream) -> Self {
+        let (sink, stream) = value.split();
+        Transport::WebSocket {
+            sink: Arc::new(Mutex::new(sink)),
+            stream: Arc::new(Mutex::new(stream)),
+        }
+    }
+}
+
+/// A WebSocket message carries a whole frame, with its length
+fn websocket_config(limits: &ConnectionLimits) -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(limits.max_frame_size + 4),
+        max_frame_size: Some(limits.max_frame_size + 4),
+        ..Default::default()
+    }
+}
+
+/// Whether the client starts with an HTTP request, i.e. asks for a WebSocket connection.
+/// Raw TCP clients start with the length of the first frame
+async fn starts_with_http(stream: &TcpStream) -> Res<bool> {
+    const HTTP_GET: &[u8] = b"GET ";
+    let mut buf = [0u8; 4];
+    loop {
+        let received = stream.peek(&mut buf).await?;
+        if received == 0 {
+            return Err(Er {
+                message: "The client has closed the connection".to_owned(),
+            });
+        }
+        if received == buf.len() || buf[..received] != HTTP_GET[..received] {
+            return Ok(buf == HTTP_GET);
+        }
+        // Only a part of the request has arrived so far
+        task::sleep(Duration::from_millis(10)).await;
+    }
+}
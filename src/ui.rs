@@ -1,14 +1,22 @@
 use async_channel::Sender;
+use serde::{Deserialize, Serialize};
 
 pub mod cli;
 pub mod gui;
+pub mod headless;
+pub mod tui;
 
 use crate::{
-    logic::GameState,
+    crypto::key_store::KeysInfo,
+    logic::{
+        chat::ChatMessage, dispute::Verdict, spectator::SpectatorState, statistics::Statistics,
+        GameState, SessionScore,
+    },
     model::{IncompleteBoard, Ship},
+    net::discovery::LanGame,
     utils::{
         async_receiver::AsyncReceiver,
-        log::{Log, Logger},
+        log::{background_logger, Level, Log, Logger, Record},
         result::Res,
     },
 };
@@ -18,7 +26,15 @@ pub type UiReceiver = AsyncReceiver<UiInput>;
 
 impl Log for UiSender {
     fn log_message(&self, msg: &str) -> Res<()> {
-        self.send_blocking(UiMessage::Log(msg.to_owned()))?;
+        self.info(msg)
+    }
+
+    /// The UI shows only the messages, from the `Info` level. Every record goes to the background logger
+    fn log(&self, record: &Record) -> Res<()> {
+        let _ = background_logger().log(record);
+        if record.level >= Level::Info {
+            self.send_blocking(UiMessage::Log(record.message.clone()))?;
+        }
         Ok(())
     }
 }
@@ -30,24 +46,71 @@ impl From<UiSender> for Logger {
 }
 
 /// Message (state) that can be send to the UI
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum UiMessage {
     Log(String),
     MainScreen,
     Lobby,
     BoardConstruction(IncompleteBoard),
     PrintGameState(GameState),
+    Verdict(Verdict),
+    GameOver {
+        won: bool,
+        score: SessionScore,
+    },
+    Statistics(Statistics),
+    Chat(ChatMessage),
+    /// Rooms found in the local network
+    LanGames(Vec<LanGame>),
+    /// Listening for the rooms in the local network failed
+    LanDiscoveryFailed(String),
+    /// Game watched as a spectator
+    Spectating(SpectatorState),
+    /// State of the zk-SNARK keys, also sent on start when they are missing
+    Keys(KeysInfo),
     Exit,
 }
 
 /// Input received from the UI
+#[derive(Serialize, Deserialize)]
 pub enum UiInput {
-    HostGame { addr: String, passwd: String },
-    JoinGame { addr: String, passwd: String },
-    SendMessage(String, String),
+    HostGame {
+        addr: String,
+        passwd: String,
+    },
+    JoinGame {
+        addr: String,
+        passwd: String,
+    },
+    /// Create a room on the relay server, for players who cannot accept direct connections
+    HostViaRelay {
+        relay: String,
+        room: String,
+        passwd: String,
+    },
+    JoinViaRelay {
+        relay: String,
+        room: String,
+        passwd: String,
+    },
+    /// Watch the game hosted at the address
+    Spectate {
+        addr: String,
+        passwd: String,
+    },
+    SendMessage(String),
     PutShip(Ship),
     ResetBoard,
     Shoot(u8, u8),
+    Rematch,
+    /// Prove all the answers given in the finished game at once, unless it is already done
+    ProveHistory,
+    SetPlayerName(String),
+    ShowStatistics,
+    ShowKeys,
+    /// Generate the missing or new keys in the key directory
+    GenerateKeys,
+    ListLanGames,
     Esc,
     Exit,
 }
@@ -1,167 +1,187 @@
-use std::io::Write;
-use std::{cell::RefCell, sync::Arc};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
 
 use async_channel::{Receiver, Sender};
 use async_std::task::block_on;
-use rustyline_async::{Readline, SharedWriter};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, CompletionType, Context, Editor, ExternalPrinter,
+    Helper,
+};
 
-use crate::utils::threads::select_first;
+use crate::net::discovery::LanGame;
 use crate::{
-    model::{Direction, FieldState, Ship, SHIP_SIZES},
+    model::{FieldState, Ship},
     utils::{
         log::Log,
-        result::Res,
+        result::{Er, Res},
         ship_helpers::{Point, Rectangle},
     },
 };
 
 use super::{UiInput, UiMessage};
 
-const MAIN_SCREEN: &str = "
+pub mod parser;
 
-Witamy w grze w statki!
+use parser::{complete_line, help, parse_command, CliContext};
 
-Command list:
-    Establishing connection:
-        create address:port password => create game
-        join address:port password => join game
-        msg name info => send msg to the second player
-    Creating board
-        put x y (right/down) => put a ship on your board (coordinates in range from 1 to 10)
-        clear => clear the board
-    Main game:
-        shoot x y => shoot at the position (x, y)
-    Navigating:
-        Ctrl-C => Interrupt
-        Ctrl-D => Exit
+const MAIN_SCREEN: &str = "
 
+Witamy w grze w statki!
 ";
 
+type LineEditor = Editor<CommandHelper, DefaultHistory>;
+
 pub fn run_cli(receiver: Receiver<UiMessage>, sender: Sender<UiInput>) {
-    let (mut reader, writer) = Readline::new("> ".to_string()).unwrap();
+    let config = rustyline::Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = LineEditor::with_config(config).unwrap();
+    let printer = editor.create_external_printer().unwrap();
+    let state = Arc::new(Mutex::new(UiMessage::MainScreen));
+    editor.set_helper(Some(CommandHelper {
+        state: Arc::clone(&state),
+    }));
 
     let _ = std::thread::Builder::new()
         .stack_size(1024 * 1024)
-        .spawn(|| {
+        .spawn(move || {
             let mut cli = Cli {
-                writer,
-                state: Arc::new(RefCell::new(UiMessage::MainScreen)),
+                printer: Arc::new(Mutex::new(Box::new(printer))),
+                state,
+                lan_games: Arc::new(Mutex::new(vec![])),
             };
 
-            let mut cli_c = cli.clone();
-            let sending_task = async move {
-                loop {
-                    sender
-                        .send(get_input(&mut reader, &mut cli_c).await?)
-                        .await?;
+            // The editor blocks while reading a line, so it gets its own thread
+            let cli_c = cli.clone();
+            let reading_thread = thread::spawn(move || {
+                while let Ok(input) = get_input(&mut editor, &cli_c) {
+                    let exit = matches!(input, UiInput::Exit);
+                    if sender.send_blocking(input).is_err() || exit {
+                        break;
+                    }
                 }
-                Res::Ok(())
-            };
+            });
 
-            let receiving_task = async move {
+            let _ = block_on(async {
                 loop {
                     match receiver.recv().await? {
                         UiMessage::Log(m) => {
                             cli.log_message(&m)?;
                         }
+                        UiMessage::Chat(message) => {
+                            cli.log_message(&message.to_string())?;
+                        }
+                        UiMessage::LanGames(games) => {
+                            cli.show_lan_games(&games)?;
+                            *cli.lan_games.lock().unwrap() = games;
+                        }
+                        UiMessage::LanDiscoveryFailed(_) => {}
+                        UiMessage::Verdict(verdict) => {
+                            cli.log_message(&format!("\n!!! {verdict} !!!\n"))?;
+                        }
                         UiMessage::Exit => {
                             return Res::Ok(());
                         }
                         s => {
-                            cli.state.replace_with(|_| s);
+                            *cli.state.lock().unwrap() = s;
                             cli.draw();
                         }
                     }
                 }
-            };
-            let _ = block_on(select_first(sending_task, receiving_task));
+            });
+            // Leaving while a line is read would keep the terminal in raw mode
+            if !reading_thread.is_finished() {
+                let _ = cli.log_message("Press enter to exit");
+            }
+            let _ = reading_thread.join();
         })
         .unwrap()
         .join();
 }
-async fn get_input(reader: &mut Readline, cli: &mut Cli) -> Res<UiInput> {
+
+fn get_input(editor: &mut LineEditor, cli: &Cli) -> Res<UiInput> {
     loop {
-        let input = reader.readline().await.unwrap();
-        match input {
-            rustyline_async::ReadlineEvent::Line(line) => {
-                reader.add_history_entry(line.clone());
-                let words = line.split(' ').collect::<Vec<&str>>();
-                if words.len() == 0 {
-                    cli.log_message("Invalid command")?;
-                    continue;
-                }
-                if words[0] == "clear" {
-                    return Ok(UiInput::ResetBoard);
-                }
-                if words.len() < 3 {
-                    cli.log_message("Invalid command")?;
-                    continue;
-                }
-                if words[0] == "create" {
-                    return Ok(UiInput::HostGame {
-                        addr: words[1].to_owned(),
-                        passwd: words[2].to_owned(),
-                    });
-                }
-                if words[0] == "join" {
-                    return Ok(UiInput::JoinGame {
-                        addr: words[1].to_owned(),
-                        passwd: words[2].to_owned(),
-                    });
-                }
-                if words[0] == "msg" {
-                    return Ok(UiInput::SendMessage(
-                        words[1].to_owned(),
-                        words[2..].join(" ").to_owned(),
-                    ));
-                }
-                if words[0] == "shoot" {
-                    return Ok(UiInput::Shoot(
-                        words[1].parse().unwrap(),
-                        words[2].parse().unwrap(),
-                    ));
-                }
-                if words[0] == "put" {
-                    if words.len() != 4 {
-                        cli.log_message("Invalid command")?;
-                        continue;
-                    }
-                    if let UiMessage::BoardConstruction(inc_board) =
-                        cli.state.as_ref().borrow().clone()
-                    {
-                        return Ok(UiInput::PutShip(Ship {
-                            x: words[1].parse().unwrap(),
-                            y: words[2].parse().unwrap(),
-                            size: SHIP_SIZES[inc_board.0.len()],
-                            direction: {
-                                if words[3] == "down" {
-                                    Direction::Vertical
-                                } else {
-                                    Direction::Horizontal
-                                }
-                            },
-                        }));
-                    }
-                }
-                cli.log_message("Invalid command")?;
-            }
-            rustyline_async::ReadlineEvent::Eof => {
-                return Ok(UiInput::Exit);
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => return Ok(UiInput::Esc),
+            Err(ReadlineError::Eof) => return Ok(UiInput::Exit),
+            Err(e) => {
+                return Err(Er {
+                    message: e.to_string(),
+                })
             }
-            rustyline_async::ReadlineEvent::Interrupted => return Ok(UiInput::Esc),
+        };
+        if line.trim().is_empty() {
+            continue;
         }
+        let _ = editor.add_history_entry(line.clone());
+
+        let state = cli.state.lock().unwrap().clone();
+        let input = parse_command(&line, CliContext::from(&state))
+            .map_err(|e| e.to_string())
+            .and_then(|command| command.into_input(&state, &cli.lan_games.lock().unwrap()));
+        match input {
+            Ok(input) => return Ok(input),
+            Err(text) => cli.log_message(&text)?,
+        }
+    }
+}
+
+/// Completes the names of the commands available in the current state with Tab
+struct CommandHelper {
+    state: Arc<Mutex<UiMessage>>,
+}
+
+impl Completer for CommandHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let context = CliContext::from(&*self.state.lock().unwrap());
+        let (start, names) = complete_line(&line[..pos], context);
+        // A single match is completed together with the space before the arguments
+        let candidates = match names.as_slice() {
+            [name] => vec![format!("{name} ")],
+            names => names.iter().map(|name| name.to_string()).collect(),
+        };
+        Ok((start, candidates))
     }
 }
 
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
 #[derive(Clone)]
 struct Cli {
-    writer: SharedWriter,
-    state: Arc<RefCell<UiMessage>>,
+    /// Prints above the line being edited
+    printer: Arc<Mutex<Box<dyn ExternalPrinter + Send>>>,
+    state: Arc<Mutex<UiMessage>>,
+    /// Games shown by the last `list` command
+    lan_games: Arc<Mutex<Vec<LanGame>>>,
 }
 impl Log for Cli {
     fn log_message(&self, msg: &str) -> Res<()> {
-        self.writer.clone().write_fmt(format_args!("{msg}\n"))?;
-        Ok(())
+        self.printer
+            .lock()
+            .unwrap()
+            .print(format!("{msg}\n"))
+            .map_err(|e| Er {
+                message: e.to_string(),
+            })
     }
 }
 
@@ -223,13 +243,45 @@ impl Screen {
 }
 
 impl Cli {
+    fn show_lan_games(&self, games: &[LanGame]) -> Res<()> {
+        if games.is_empty() {
+            return self.log_message("No games found in the local network");
+        }
+        let mut text = "Games in the local network:\n".to_owned();
+        for (i, game) in games.iter().enumerate() {
+            text += &format!(
+                "    {}. {} at {}{}{}\n",
+                i + 1,
+                game.announcement.room,
+                game.addr,
+                if game.announcement.password_required {
+                    ", password required"
+                } else {
+                    ""
+                },
+                if game.compatible {
+                    ""
+                } else {
+                    ", DIFFERENT KEYS"
+                }
+            );
+        }
+        text += "Type `join <number> [password]` to join";
+        self.log_message(&text)
+    }
+
     fn draw(&mut self) {
-        match &*self.state.as_ref().borrow() {
+        match &*self.state.lock().unwrap() {
             UiMessage::MainScreen => {
                 self.log_message(MAIN_SCREEN).unwrap();
+                self.log_message(&help(None, CliContext::MainScreen))
+                    .unwrap();
+                self.log_message("Ctrl-C => Interrupt, Ctrl-D => Exit")
+                    .unwrap();
             }
             UiMessage::Lobby => {
-                self.log_message("\n\nLobby\n").unwrap();
+                self.log_message("\n\nLobby\n\nType `help` to list available commands\n")
+                    .unwrap();
             }
             UiMessage::BoardConstruction(board) => {
                 // self.log_message(&format!("{:#?}", board)).unwrap();
@@ -245,6 +297,40 @@ impl Cli {
                 s.draw_shots(state.their_shots.clone(), (3, 3).into());
                 self.log_message(&s.to_string()).unwrap();
             }
+            UiMessage::Spectating(state) => {
+                let mut s = Screen::new(30, 15);
+                s.draw_board(vec![], (3, 3).into());
+                s.draw_board(vec![], (18, 3).into());
+                s.draw_shots(state.host_board.clone(), (3, 3).into());
+                s.draw_shots(state.client_board.clone(), (18, 3).into());
+                self.log_message(&format!(
+                    "{:<15}{}\n{}",
+                    state.host,
+                    state.client,
+                    s.to_string()
+                ))
+                .unwrap();
+            }
+            UiMessage::Statistics(stats) => {
+                self.log_message(&format!("\n\nStatistics\n\n{}", stats.summary()))
+                    .unwrap();
+            }
+            UiMessage::Keys(info) => {
+                self.log_message(&format!("\n\nKeys\n\n{info}")).unwrap();
+                if !info.is_complete() {
+                    self.log_message("Type `generate-keys` to generate the missing keys")
+                        .unwrap();
+                }
+            }
+            UiMessage::GameOver { won, score } => {
+                self.log_message(&format!(
+                    "\n\n{}\nSession score: {} : {} (us : opponent)\nType `rematch` to play again\n",
+                    if *won { "You won!" } else { "You lost..." },
+                    score.our_wins,
+                    score.their_wins
+                ))
+                .unwrap();
+            }
             _ => {}
         }
     }
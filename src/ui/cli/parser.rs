@@ -0,0 +1,481 @@
+use std::fmt::Display;
+
+use crate::{
+    model::{Direction, Ship, SHIP_SIZES},
+    net::discovery::LanGame,
+    ui::{UiInput, UiMessage},
+};
+
+/// Part of the game the user currently is in. Decides which commands are available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliContext {
+    MainScreen,
+    Lobby,
+    BoardConstruction,
+    Game,
+    GameOver,
+    Spectating,
+}
+
+impl From<&UiMessage> for CliContext {
+    fn from(value: &UiMessage) -> Self {
+        match value {
+            UiMessage::Lobby => CliContext::Lobby,
+            UiMessage::BoardConstruction(_) => CliContext::BoardConstruction,
+            UiMessage::PrintGameState(_) => CliContext::Game,
+            UiMessage::GameOver { .. } => CliContext::GameOver,
+            UiMessage::Spectating(_) => CliContext::Spectating,
+            _ => CliContext::MainScreen,
+        }
+    }
+}
+
+/// Command typed by the user
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Create {
+        addr: String,
+        passwd: String,
+    },
+    /// `addr` may also be a number of the game shown by `list`
+    Join {
+        addr: String,
+        passwd: String,
+    },
+    List,
+    Watch {
+        addr: String,
+        passwd: String,
+    },
+    CreateViaRelay {
+        relay: String,
+        room: String,
+        passwd: String,
+    },
+    JoinViaRelay {
+        relay: String,
+        room: String,
+        passwd: String,
+    },
+    Msg(String),
+    Name(String),
+    Stats,
+    Keys,
+    GenerateKeys,
+    Put {
+        x: u8,
+        y: u8,
+        direction: Direction,
+    },
+    Clear,
+    Shoot {
+        x: u8,
+        y: u8,
+    },
+    Rematch,
+    ProveHistory,
+    Help(Option<String>),
+}
+
+impl Command {
+    /// Input for the logic, given the screen shown to the user and the games found by the last `list`.
+    /// Returns the text to show instead if the command is handled by the UI alone
+    pub fn into_input(self, state: &UiMessage, lan_games: &[LanGame]) -> Result<UiInput, String> {
+        Ok(match self {
+            Command::Create { addr, passwd } => UiInput::HostGame { addr, passwd },
+            Command::Join { addr, passwd } => {
+                let lan_game = addr
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| lan_games.get(n.wrapping_sub(1)));
+                match lan_game {
+                    Some(game) => UiInput::JoinGame {
+                        addr: game.addr.clone(),
+                        passwd,
+                    },
+                    None => UiInput::JoinGame { addr, passwd },
+                }
+            }
+            Command::List => UiInput::ListLanGames,
+            Command::Watch { addr, passwd } => UiInput::Spectate { addr, passwd },
+            Command::CreateViaRelay {
+                relay,
+                room,
+                passwd,
+            } => UiInput::HostViaRelay {
+                relay,
+                room,
+                passwd,
+            },
+            Command::JoinViaRelay {
+                relay,
+                room,
+                passwd,
+            } => UiInput::JoinViaRelay {
+                relay,
+                room,
+                passwd,
+            },
+            Command::Msg(text) => UiInput::SendMessage(text),
+            Command::Name(name) => UiInput::SetPlayerName(name),
+            Command::Stats => UiInput::ShowStatistics,
+            Command::Keys => UiInput::ShowKeys,
+            Command::GenerateKeys => UiInput::GenerateKeys,
+            Command::Clear => UiInput::ResetBoard,
+            Command::Rematch => UiInput::Rematch,
+            Command::ProveHistory => UiInput::ProveHistory,
+            Command::Shoot { x, y } => UiInput::Shoot(x, y),
+            Command::Put { x, y, direction } => {
+                let size = match state {
+                    UiMessage::BoardConstruction(board) => SHIP_SIZES.get(board.0.len()),
+                    _ => None,
+                };
+                let Some(&size) = size else {
+                    return Err("All the ships are already placed".to_owned());
+                };
+                UiInput::PutShip(Ship {
+                    x,
+                    y,
+                    size,
+                    direction,
+                })
+            }
+            Command::Help(command) => return Err(help(command.as_deref(), state.into())),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub contexts: &'static [CliContext],
+}
+
+const ANYWHERE: &[CliContext] = &[
+    CliContext::MainScreen,
+    CliContext::Lobby,
+    CliContext::BoardConstruction,
+    CliContext::Game,
+    CliContext::GameOver,
+    CliContext::Spectating,
+];
+const CONNECTED: &[CliContext] = &[
+    CliContext::Lobby,
+    CliContext::BoardConstruction,
+    CliContext::Game,
+    CliContext::GameOver,
+];
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "create",
+        usage: "create address:port [password]",
+        description: "create a game and wait for the opponent",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "create-relay",
+        usage: "create-relay relay:port room [password]",
+        description: "create a room on the relay server and wait for the opponent",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "join-relay",
+        usage: "join-relay relay:port room [password]",
+        description: "join a room created on the relay server",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "watch",
+        usage: "watch address:port [password]",
+        description: "watch the game hosted at the address as a spectator",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "list",
+        usage: "list",
+        description: "look for games created in the local network",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "join",
+        usage: "join address:port|number [password]",
+        description: "join a game created by the opponent, or the listed game with the number",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "name",
+        usage: "name player_name",
+        description: "set the name presented to other players",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "stats",
+        usage: "stats",
+        description: "show statistics of the played games",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "keys",
+        usage: "keys",
+        description: "show the key directory, which keys are present and their fingerprint",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "generate-keys",
+        usage: "generate-keys",
+        description: "generate new keys in the key directory, takes several minutes",
+        contexts: &[CliContext::MainScreen],
+    },
+    CommandSpec {
+        name: "msg",
+        usage: "msg text",
+        description: "send a message to the opponent",
+        contexts: CONNECTED,
+    },
+    CommandSpec {
+        name: "put",
+        usage: "put B7 right|down",
+        description: "put the next ship on your board, starting at the given field",
+        contexts: &[CliContext::BoardConstruction],
+    },
+    CommandSpec {
+        name: "clear",
+        usage: "clear",
+        description: "remove all ships from your board",
+        contexts: &[CliContext::BoardConstruction],
+    },
+    CommandSpec {
+        name: "shoot",
+        usage: "shoot B7",
+        description: "shoot at the given field of the opponent's board",
+        contexts: &[CliContext::Game],
+    },
+    CommandSpec {
+        name: "rematch",
+        usage: "rematch",
+        description: "offer or accept another game with the same opponent",
+        contexts: &[CliContext::GameOver],
+    },
+    CommandSpec {
+        name: "prove-history",
+        usage: "prove-history",
+        description:
+            "prove all your answers of the game at once, for the opponent and the spectators",
+        contexts: &[CliContext::GameOver],
+    },
+    CommandSpec {
+        name: "help",
+        usage: "help [command]",
+        description: "show available commands or details of one command",
+        contexts: ANYWHERE,
+    },
+];
+
+pub fn find_command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+fn matching_commands(prefix: &str, context: CliContext) -> Vec<&'static CommandSpec> {
+    COMMANDS
+        .iter()
+        .filter(|c| c.contexts.contains(&context) && c.name.starts_with(prefix))
+        .collect()
+}
+
+/// Names of the commands available in the context and starting with `prefix`
+pub fn complete(prefix: &str, context: CliContext) -> Vec<&'static str> {
+    matching_commands(prefix, context)
+        .into_iter()
+        .map(|c| c.name)
+        .collect()
+}
+
+/// Completions of the command name typed at the end of `line`: the position where the name starts and
+/// the names of the matching commands. Arguments are not completed
+pub fn complete_line(line: &str, context: CliContext) -> (usize, Vec<&'static str>) {
+    let start = line.len() - line.trim_start().len();
+    let name = &line[start..];
+    if name.contains(char::is_whitespace) {
+        return (line.len(), vec![]);
+    }
+    (start, complete(&name.to_lowercase(), context))
+}
+
+/// Help text. Lists the commands available in the context, or describes the given command
+pub fn help(command: Option<&str>, context: CliContext) -> String {
+    match command.map(|name| (name, find_command(name))) {
+        Some((_, Some(spec))) => format!("{}\n    {}", spec.usage, spec.description),
+        Some((name, None)) => format!("Unknown command `{name}`"),
+        None => {
+            let mut text = "Available commands:\n".to_owned();
+            for spec in COMMANDS.iter().filter(|c| c.contexts.contains(&context)) {
+                text += &format!("    {:<30} {}\n", spec.usage, spec.description);
+            }
+            text += "Fields can be given as B7 (column B, row 7) or as 2,7";
+            text
+        }
+    }
+}
+
+/// Parse the whole line typed by the user
+pub fn parse_command(line: &str, context: CliContext) -> Result<Command, ParseError> {
+    let words = line.split_whitespace().collect::<Vec<&str>>();
+    let Some((name, args)) = words.split_first() else {
+        return Err(ParseError(
+            "Empty command. Type `help` to list commands".to_owned(),
+        ));
+    };
+    let name = name.to_lowercase();
+
+    // unambiguous prefixes of the available commands are accepted as well
+    let matching = matching_commands(&name, context);
+    let spec = match (find_command(&name), matching.as_slice()) {
+        (Some(spec), _) => spec,
+        (None, [only]) => *only,
+        (None, []) => {
+            return Err(ParseError(format!(
+                "Unknown command `{name}`. Type `help` to list commands"
+            )))
+        }
+        (None, _) => {
+            return Err(ParseError(format!(
+                "Ambiguous command `{name}`. Did you mean: {}?",
+                complete(&name, context).join(", ")
+            )))
+        }
+    };
+    if !spec.contexts.contains(&context) {
+        return Err(ParseError(format!(
+            "`{name}` is not available now. Type `help` to list available commands"
+        )));
+    }
+    let usage_error = || ParseError(format!("Usage: {}", spec.usage));
+
+    match (spec.name, args) {
+        ("create", [addr, passwd @ ..]) if passwd.len() <= 1 => Ok(Command::Create {
+            addr: addr.to_string(),
+            passwd: passwd.join(""),
+        }),
+        ("join", [addr, passwd @ ..]) if passwd.len() <= 1 => Ok(Command::Join {
+            addr: addr.to_string(),
+            passwd: passwd.join(""),
+        }),
+        ("list", []) => Ok(Command::List),
+        ("watch", [addr, passwd @ ..]) if passwd.len() <= 1 => Ok(Command::Watch {
+            addr: addr.to_string(),
+            passwd: passwd.join(""),
+        }),
+        ("create-relay", [relay, room, passwd @ ..]) if passwd.len() <= 1 => {
+            Ok(Command::CreateViaRelay {
+                relay: relay.to_string(),
+                room: room.to_string(),
+                passwd: passwd.join(""),
+            })
+        }
+        ("join-relay", [relay, room, passwd @ ..]) if passwd.len() <= 1 => {
+            Ok(Command::JoinViaRelay {
+                relay: relay.to_string(),
+                room: room.to_string(),
+                passwd: passwd.join(""),
+            })
+        }
+        ("name", [_, ..]) => Ok(Command::Name(args.join(" "))),
+        ("msg", [_, ..]) => Ok(Command::Msg(args.join(" "))),
+        ("stats", []) => Ok(Command::Stats),
+        ("keys", []) => Ok(Command::Keys),
+        ("generate-keys", []) => Ok(Command::GenerateKeys),
+        ("clear", []) => Ok(Command::Clear),
+        ("rematch", []) => Ok(Command::Rematch),
+        ("prove-history", []) => Ok(Command::ProveHistory),
+        ("help", []) => Ok(Command::Help(None)),
+        ("help", [command]) => Ok(Command::Help(Some(command.to_lowercase()))),
+        ("shoot", [_, ..]) => {
+            let ((x, y), rest) = parse_field(args)?;
+            if !rest.is_empty() {
+                return Err(usage_error());
+            }
+            Ok(Command::Shoot { x, y })
+        }
+        ("put", [_, ..]) => {
+            let ((x, y), rest) = parse_field(args)?;
+            match rest {
+                [direction] => Ok(Command::Put {
+                    x,
+                    y,
+                    direction: parse_direction(direction)?,
+                }),
+                _ => Err(usage_error()),
+            }
+        }
+        _ => Err(usage_error()),
+    }
+}
+
+/// Parse a field given as `B7`, `2,7` or `2 7`. Returns the field and the remaining words
+pub fn parse_field<'a, 'b>(words: &'a [&'b str]) -> Result<((u8, u8), &'a [&'b str]), ParseError> {
+    let Some(first) = words.first() else {
+        return Err(ParseError("Missing field".to_owned()));
+    };
+
+    let mut chars = first.chars();
+    let field = match chars.next() {
+        Some(column) if column.is_ascii_alphabetic() => {
+            let x = column.to_ascii_uppercase() as u8 - b'A' + 1;
+            let y = parse_number(chars.as_str(), first)?;
+            ((x, y), &words[1..])
+        }
+        _ if first.contains(',') => {
+            let (x, y) = first.split_once(',').unwrap_or_default();
+            (
+                (parse_number(x, first)?, parse_number(y, first)?),
+                &words[1..],
+            )
+        }
+        _ => match words.get(1) {
+            Some(second) => (
+                (parse_number(first, first)?, parse_number(second, second)?),
+                &words[2..],
+            ),
+            None => {
+                return Err(ParseError(format!(
+                    "Invalid field `{first}`, use B7 or 2,7"
+                )))
+            }
+        },
+    };
+
+    let ((x, y), _) = field;
+    if !(1..=10).contains(&x) || !(1..=10).contains(&y) {
+        return Err(ParseError(format!(
+            "Field `{first}` is outside of the board. Columns are A-J, rows 1-10"
+        )));
+    }
+    Ok(field)
+}
+
+fn parse_number(text: &str, field: &str) -> Result<u8, ParseError> {
+    text.trim()
+        .parse()
+        .map_err(|_| ParseError(format!("Invalid field `{field}`, use B7 or 2,7")))
+}
+
+fn parse_direction(word: &str) -> Result<Direction, ParseError> {
+    match word.to_lowercase().as_str() {
+        "right" | "r" | "horizontal" | "h" => Ok(Direction::Horizontal),
+        "down" | "d" | "vertical" | "v" => Ok(Direction::Vertical),
+        _ => Err(ParseError(format!(
+            "Invalid direction `{word}`, use `right` or `down`"
+        ))),
+    }
+}
@@ -2,23 +2,39 @@ use async_channel::{Receiver, Sender};
 use dioxus::prelude::*;
 use dioxus_desktop::*;
 
+use chat::ChatHistory;
+
 use crate::{
-    logic::GameState,
+    config::{config, Theme},
+    crypto::key_store::KeysInfo,
+    logic::{
+        dispute::Verdict, spectator::SpectatorState, statistics::Statistics, GameState,
+        SessionScore,
+    },
     model::IncompleteBoard,
+    net::discovery::LanGame,
     ui::{UiInput, UiMessage},
 };
 
 mod boards;
+mod chat;
 mod common;
 mod lobby;
 mod main_menu;
+mod settings;
+mod spectator;
+mod statistics;
+mod verdict;
 
-pub static ASSETS_DIR: &str = "assets";
+/// Directory of the stylesheets, fonts and images, `assets` by default
+pub fn assets_dir() -> &'static str {
+    &config().gui.assets_dir
+}
 pub static GAME_TITLE: &str = "Battleships";
 
 pub fn run_gui(receiver: Receiver<UiMessage>, sender: Sender<UiInput>) {
     let window_config = WindowBuilder::new()
-        .with_maximized(true)
+        .with_maximized(config().gui.maximized)
         .with_title("Battleships");
     let config = Config::new().with_window(window_config);
 
@@ -29,11 +45,17 @@ pub fn run_gui(receiver: Receiver<UiMessage>, sender: Sender<UiInput>) {
         .launch(App);
 }
 
+#[derive(Clone, Copy)]
+struct KeysGenerating(bool);
+
 #[derive(Clone)]
 enum GameScreenType {
     MainMenu,
     Lobby,
     Boards,
+    Statistics,
+    Spectator,
+    Settings,
 }
 
 #[component]
@@ -42,33 +64,89 @@ fn App() -> Element {
     use_context_provider(|| Signal::new(Vec::<String>::new()));
     use_context_provider(|| Signal::new(IncompleteBoard(vec![])));
     use_context_provider(|| Signal::<Option<GameState>>::new(None));
+    use_context_provider(|| Signal::<Option<Verdict>>::new(None));
+    use_context_provider(|| Signal::new(SessionScore::default()));
+    // Some(true) if we won the last game, None while the game is still in progress
+    use_context_provider(|| Signal::<Option<bool>>::new(None));
+    use_context_provider(|| Signal::new(Statistics::default()));
+    use_context_provider(|| Signal::new(ChatHistory::default()));
+    use_context_provider(|| Signal::<Option<Result<Vec<LanGame>, String>>>::new(None));
+    use_context_provider(|| Signal::new(SpectatorState::default()));
+    use_context_provider(|| Signal::new(KeysInfo::default()));
+    // True from asking for new keys until the state of the keys is sent back
+    use_context_provider(|| Signal::new(KeysGenerating(false)));
     use_coroutine(|_: UnboundedReceiver<String>| {
         let mut screen_type = use_context::<Signal<GameScreenType>>();
         let receiver = use_context::<Receiver<UiMessage>>();
         let mut logs = use_context::<Signal<Vec<String>>>();
         let mut inc_board = use_context::<Signal<IncompleteBoard>>();
         let mut versus_state = use_context::<Signal<Option<GameState>>>();
+        let mut verdict = use_context::<Signal<Option<Verdict>>>();
+        let mut score = use_context::<Signal<SessionScore>>();
+        let mut game_result = use_context::<Signal<Option<bool>>>();
+        let mut stats = use_context::<Signal<Statistics>>();
+        let mut chat = use_context::<Signal<ChatHistory>>();
+        let mut lan_games = use_context::<Signal<Option<Result<Vec<LanGame>, String>>>>();
+        let mut spectator_state = use_context::<Signal<SpectatorState>>();
+        let mut keys = use_context::<Signal<KeysInfo>>();
+        let mut keys_generating = use_context::<Signal<KeysGenerating>>();
         async move {
             loop {
                 match receiver.recv().await.expect("") {
-                    UiMessage::MainScreen => screen_type.set(GameScreenType::MainMenu),
-                    UiMessage::Lobby => screen_type.set(GameScreenType::Lobby),
+                    UiMessage::MainScreen => {
+                        screen_type.set(GameScreenType::MainMenu);
+                        score.set(SessionScore::default());
+                        chat.set(ChatHistory::default());
+                    }
+                    UiMessage::Lobby => {
+                        screen_type.set(GameScreenType::Lobby);
+                        game_result.set(None);
+                    }
                     UiMessage::Log(s) => logs.push(s),
                     UiMessage::BoardConstruction(board) => inc_board.set(board),
                     UiMessage::PrintGameState(state) => {
                         screen_type.set(GameScreenType::Boards);
                         versus_state.set(Some(state));
                     }
+                    UiMessage::Verdict(v) => verdict.set(Some(v)),
+                    UiMessage::GameOver {
+                        won,
+                        score: new_score,
+                    } => {
+                        game_result.set(Some(won));
+                        score.set(new_score);
+                    }
+                    UiMessage::Statistics(new_stats) => {
+                        screen_type.set(GameScreenType::Statistics);
+                        stats.set(new_stats);
+                    }
+                    UiMessage::Chat(message) => chat.write().push(message),
+                    UiMessage::LanGames(games) => lan_games.set(Some(Ok(games))),
+                    UiMessage::LanDiscoveryFailed(error) => lan_games.set(Some(Err(error))),
+                    UiMessage::Spectating(state) => {
+                        screen_type.set(GameScreenType::Spectator);
+                        spectator_state.set(state);
+                    }
+                    UiMessage::Keys(info) => {
+                        screen_type.set(GameScreenType::Settings);
+                        keys.set(info);
+                        keys_generating.set(KeysGenerating(false));
+                    }
                     UiMessage::Exit => window().close(),
                 }
             }
         }
     });
 
+    let assets = assets_dir();
     rsx! {
-        link { rel: "stylesheet", href: "{ASSETS_DIR}/style.css" }
+        link { rel: "stylesheet", href: "{assets}/style.css" }
+        if config().gui.theme == Theme::Dark {
+            link { rel: "stylesheet", href: "{assets}/dark.css" }
+        }
         GameScreen {}
         LogsScreen {}
+        crate::ui::gui::verdict::VerdictDialog {}
     }
 }
 
@@ -80,6 +158,9 @@ fn GameScreen() -> Element {
         GameScreenType::MainMenu => rsx! { crate::ui::gui::main_menu::MainMenu {} },
         GameScreenType::Lobby => rsx! { crate::ui::gui::lobby::Lobby {} },
         GameScreenType::Boards => rsx! { crate::ui::gui::boards::Boards {} },
+        GameScreenType::Statistics => rsx! { crate::ui::gui::statistics::StatisticsScreen {} },
+        GameScreenType::Spectator => rsx! { crate::ui::gui::spectator::Spectator {} },
+        GameScreenType::Settings => rsx! { crate::ui::gui::settings::SettingsScreen {} },
     }
 }
 
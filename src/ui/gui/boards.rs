@@ -3,14 +3,19 @@ use async_std::task::block_on;
 use dioxus::prelude::*;
 
 use crate::{
+    config::config,
     logic::GameState,
-    ui::gui::common::{BoardData, FieldState},
+    ui::gui::chat::ChatPanel,
+    ui::gui::common::{BoardData, ControlPanelStyle, FieldState, SessionScoreBar},
     ui::UiInput,
 };
 
 #[component]
 pub fn Boards() -> Element {
     rsx! {
+        SessionScoreBar {}
+        ChatPanel {}
+        GameResult {}
         div {
             style: "display: flex; align-items: center",
             OpponentsBoard { style: "margin: 3em auto" }
@@ -19,6 +24,50 @@ pub fn Boards() -> Element {
     }
 }
 
+#[component]
+fn GameResult() -> Element {
+    let game_result = use_context::<Signal<Option<bool>>>();
+    let mut rematch_requested = use_signal(|| false);
+    // With `history_proof` enabled the answers are proven without asking
+    let mut history_requested = use_signal(|| config().history_proof);
+
+    let title = match game_result() {
+        None => return rsx! {},
+        Some(true) => "You won!",
+        Some(false) => "You lost...",
+    };
+
+    rsx! {
+        ControlPanelStyle {
+            style: "margin: 1em auto",
+            h2 {
+                style: "margin: 0 auto; font-size: 3em",
+                "{title}"
+            }
+            button {
+                class: "ok-button",
+                disabled: rematch_requested(),
+                onclick: move |_| {
+                    let sender = use_context::<Sender<UiInput>>();
+                    block_on(sender.send(UiInput::Rematch)).expect("");
+                    rematch_requested.set(true);
+                },
+                if rematch_requested() { "waiting for the opponent" } else { "rematch" }
+            }
+            button {
+                class: "ok-button",
+                disabled: history_requested(),
+                onclick: move |_| {
+                    let sender = use_context::<Sender<UiInput>>();
+                    block_on(sender.send(UiInput::ProveHistory)).expect("");
+                    history_requested.set(true);
+                },
+                "prove answers"
+            }
+        }
+    }
+}
+
 fn determine_opponents_board(state: Signal<Option<GameState>>) -> Vec<Vec<FieldState>> {
     let mut board_data = BoardData::new(vec![]);
     if state().is_none() {
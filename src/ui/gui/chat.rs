@@ -0,0 +1,106 @@
+use async_channel::Sender;
+use async_std::task::block_on;
+use dioxus::prelude::*;
+
+use crate::{logic::chat::ChatMessage, ui::UiInput};
+
+/// Chat with the current opponent
+#[derive(Clone, Default)]
+pub struct ChatHistory {
+    pub messages: Vec<ChatMessage>,
+    /// Number of messages already seen by the user
+    pub read: usize,
+    pub open: bool,
+}
+
+impl ChatHistory {
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+        if self.open {
+            self.read = self.messages.len();
+        }
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.read = self.messages.len();
+    }
+
+    fn unread(&self) -> usize {
+        self.messages[self.read..]
+            .iter()
+            .filter(|m| !m.ours)
+            .count()
+    }
+}
+
+#[component]
+pub fn ChatPanel() -> Element {
+    let mut history = use_context::<Signal<ChatHistory>>();
+    let mut text = use_signal(|| "".to_string());
+
+    let mut send = move || {
+        if text().trim().is_empty() {
+            return;
+        }
+        let sender = use_context::<Sender<UiInput>>();
+        block_on(sender.send(UiInput::SendMessage(text()))).expect("");
+        text.set("".to_string());
+    };
+
+    if !history().open {
+        let unread = history().unread();
+        return rsx! {
+            button {
+                class: "chat-toggle",
+                onclick: move |_| history.write().set_open(true),
+                if unread > 0 { "chat ({unread} new)" } else { "chat" }
+            }
+        };
+    }
+
+    rsx! {
+        div {
+            class: "chat-panel",
+            div {
+                style: "display: flex; align-items: center",
+                h2 { style: "margin: 0.3em auto 0.3em 0", "Chat" }
+                button {
+                    class: "abort-button",
+                    onclick: move |_| history.write().set_open(false),
+                    "hide"
+                }
+            }
+            div {
+                class: "chat-messages",
+                for message in history().messages.iter().rev() {
+                    p {
+                        class: if message.ours { "chat-message chat-message-ours" } else { "chat-message" },
+                        span { class: "chat-time", "{message.time()} " }
+                        b { "{message.sender}: " }
+                        "{message.text}"
+                    }
+                }
+            }
+            div {
+                style: "display: flex",
+                input {
+                    style: "flex-grow: 1",
+                    value: "{text}",
+                    oninput: move |event| text.set(event.value()),
+                    onkeydown: move |event| {
+                        if event.key() == Key::Enter {
+                            send();
+                        }
+                    }
+                }
+                button {
+                    class: "ok-button",
+                    disabled: text().trim().is_empty(),
+                    onclick: move |_| send(),
+                    "send"
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use dioxus::prelude::*;
 
 use crate::{
+    logic::SessionScore,
     model::{self, Direction, Ship},
-    ui::gui::ASSETS_DIR,
+    ui::gui::assets_dir,
 };
 
 #[component]
 pub fn ControlPanelStyle(style: String, children: Element) -> Element {
+    let assets = assets_dir();
     rsx! {
         div {
             class: "control-panel",
@@ -16,11 +18,11 @@ pub fn ControlPanelStyle(style: String, children: Element) -> Element {
                 style: "position: relative; height: 36px",
                 img {
                     style: "position: absolute; top: 5px; left: 5px",
-                    src: "{ASSETS_DIR}/screw.svg"
+                    src: "{assets}/screw.svg"
                 }
                 img {
                     style: "position: absolute; top: 5px; right: 5px",
-                    src: "{ASSETS_DIR}/screw.svg"
+                    src: "{assets}/screw.svg"
                 }
             }
 
@@ -33,17 +35,34 @@ pub fn ControlPanelStyle(style: String, children: Element) -> Element {
                 style: "position: relative; height: 36px",
                 img {
                     style: "position: absolute; bottom: 5px; left: 5px",
-                    src: "{ASSETS_DIR}/screw.svg"
+                    src: "{assets}/screw.svg"
                 }
                 img {
                     style: "position: absolute; bottom: 5px; right: 5px",
-                    src: "{ASSETS_DIR}/screw.svg"
+                    src: "{assets}/screw.svg"
                 }
             }
         }
     }
 }
 
+/// Score of the games played with the current opponent
+#[component]
+pub fn SessionScoreBar() -> Element {
+    let score = use_context::<Signal<SessionScore>>();
+
+    if score().games_played() == 0 {
+        return rsx! {};
+    }
+
+    rsx! {
+        h2 {
+            class: "session-score",
+            "Score {score().our_wins} : {score().their_wins}"
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub enum FieldState {
     Empty,
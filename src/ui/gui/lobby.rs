@@ -4,7 +4,8 @@ use dioxus::prelude::*;
 
 use crate::{
     model::{Direction, IncompleteBoard, Ship, SHIP_SIZES},
-    ui::gui::common::{BoardData, ControlPanelStyle, FieldState},
+    ui::gui::chat::ChatPanel,
+    ui::gui::common::{BoardData, ControlPanelStyle, FieldState, SessionScoreBar},
     ui::UiInput,
 };
 
@@ -13,6 +14,8 @@ pub fn Lobby() -> Element {
     use_context_provider(|| Signal::new(Direction::Horizontal));
 
     rsx! {
+        SessionScoreBar {}
+        ChatPanel {}
         div {
             style: "display: flex; align-items: center",
             ControlPanelStyle {
@@ -2,7 +2,11 @@ use async_channel::Sender;
 use async_std::task::block_on;
 use dioxus::prelude::*;
 
-use crate::ui::{gui::common::ControlPanelStyle, UiInput};
+use crate::{
+    config::config,
+    net::discovery::LanGame,
+    ui::{gui::common::ControlPanelStyle, UiInput},
+};
 
 #[component]
 pub fn MainMenu() -> Element {
@@ -10,8 +14,12 @@ pub fn MainMenu() -> Element {
     let mut buttons_display_style = use_signal(|| "".to_string());
     let mut details_title = use_signal(|| "".to_string());
 
-    let mut url = use_signal(|| "".to_string());
+    let mut url = use_signal(|| config().default_address.clone());
     let mut passwd = use_signal(|| "".to_string());
+    let mut relay = use_signal(|| "".to_string());
+
+    let mut lan_games = use_context::<Signal<Option<Result<Vec<LanGame>, String>>>>();
+    let mut browsing = use_signal(|| false);
 
     rsx! {
         h1 { class: "main-title", "Battleships" }
@@ -35,6 +43,116 @@ pub fn MainMenu() -> Element {
             },
             "Join room"
         }
+        button {
+            class: "torpedo-button",
+            style: "{buttons_display_style}",
+            onclick: move |_| {
+                *details_display_style.write() = "".to_string();
+                *buttons_display_style.write() = "display: none".to_string();
+                *details_title.write() = "Watch game".to_string();
+            },
+            "Watch game"
+        }
+        button {
+            class: "torpedo-button",
+            style: "{buttons_display_style}",
+            onclick: move |_| {
+                *buttons_display_style.write() = "display: none".to_string();
+                browsing.set(true);
+                lan_games.set(None);
+                let sender = use_context::<Sender<UiInput>>();
+                block_on(sender.send(UiInput::ListLanGames)).expect("");
+            },
+            "Browse LAN games"
+        }
+        button {
+            class: "torpedo-button",
+            style: "{buttons_display_style}",
+            onclick: move |_| {
+                let sender = use_context::<Sender<UiInput>>();
+                block_on(sender.send(UiInput::ShowStatistics)).expect("");
+            },
+            "Statistics"
+        }
+        button {
+            class: "torpedo-button",
+            style: "{buttons_display_style}",
+            onclick: move |_| {
+                let sender = use_context::<Sender<UiInput>>();
+                block_on(sender.send(UiInput::ShowKeys)).expect("");
+            },
+            "Settings"
+        }
+        if browsing() {
+            ControlPanelStyle {
+                style: "margin: 3em auto",
+                h2 {
+                    style: "margin: 0 auto; font-size: 2em",
+                    "LAN games"
+                }
+                match lan_games() {
+                    None => rsx! { p { class: "statistics-value", "Searching..." } },
+                    Some(Err(error)) => rsx! { p { class: "statistics-value", "{error}" } },
+                    Some(Ok(games)) if games.is_empty() => rsx! {
+                        p { class: "statistics-value", "No games found" }
+                    },
+                    Some(Ok(games)) => rsx! {
+                        div {
+                            class: "form-inputs",
+                            for game in games {
+                                p {
+                                    class: "statistics-value",
+                                    "{game.announcement.room} ({game.addr})"
+                                    if game.announcement.password_required { " [password]" }
+                                    if !game.compatible { " [different keys]" }
+                                }
+                                button {
+                                    class: "ok-button",
+                                    disabled: !game.compatible,
+                                    onclick: move |_| {
+                                        browsing.set(false);
+                                        if game.announcement.password_required {
+                                            *details_display_style.write() = "".to_string();
+                                            *details_title.write() = "Join room".to_string();
+                                            url.set(game.addr.clone());
+                                        } else {
+                                            let sender = use_context::<Sender<UiInput>>();
+                                            block_on(sender.send(UiInput::JoinGame {
+                                                addr: game.addr.clone(),
+                                                passwd: "".to_string(),
+                                            })).expect("");
+                                        }
+                                    },
+                                    "join"
+                                }
+                            }
+                        }
+                    },
+                }
+                div {
+                    style: "margin: 0 auto;",
+                    button {
+                        class: "abort-button",
+                        style: "margin: 0 1em 0 auto; display: inline",
+                        onclick: move |_| {
+                            browsing.set(false);
+                            *buttons_display_style.write() = "".to_string();
+                        },
+                        "back"
+                    }
+                    button {
+                        class: "ok-button",
+                        style: "display: inline",
+                        onclick: move |_| {
+                            lan_games.set(None);
+                            let sender = use_context::<Sender<UiInput>>();
+                            block_on(sender.send(UiInput::ListLanGames)).expect("");
+                        },
+                        "refresh"
+                    }
+                }
+            }
+        }
         button {
             class: "torpedo-button",
             style: "{buttons_display_style}",
@@ -57,7 +175,7 @@ pub fn MainMenu() -> Element {
                     class: "form-inputs",
                     label {
                         r#for: "url-input",
-                        "URL"
+                        if relay().is_empty() { "URL" } else { "room" }
                     }
                     input {
                         id: "url-input",
@@ -67,6 +185,17 @@ pub fn MainMenu() -> Element {
                         oninput: move |event| url.set(event.value())
                     }
 
+                    label {
+                        r#for: "relay-input",
+                        "relay"
+                    }
+                    input {
+                        id: "relay-input",
+                        placeholder: "optional",
+                        value: "{relay}",
+                        oninput: move |event| relay.set(event.value())
+                    }
+
                     label {
                         r#for: "pwd-input",
                         "password"
@@ -89,6 +218,7 @@ pub fn MainMenu() -> Element {
                             *buttons_display_style.write() = "".to_string();
                             *url.write() = "".to_string();
                             *passwd.write() = "".to_string();
+                            *relay.write() = "".to_string();
                         },
                         "cancel"
                     }
@@ -97,16 +227,34 @@ pub fn MainMenu() -> Element {
                         style: "display: inline",
                         onclick: move |_| {
                             let sender = use_context::<Sender<UiInput>>();
-                            block_on(sender.send(if details_title().to_lowercase().contains("create") {
-                                UiInput::HostGame {
+                            let title = details_title().to_lowercase();
+                            if title.contains("watch") {
+                                block_on(sender.send(UiInput::Spectate {
                                     addr: url(),
                                     passwd: passwd(),
-                                }
-                            } else {
-                                UiInput::JoinGame {
+                                })).expect("");
+                                return;
+                            }
+                            let create = title.contains("create");
+                            block_on(sender.send(match (create, relay().is_empty()) {
+                                (true, true) => UiInput::HostGame {
                                     addr: url(),
                                     passwd: passwd(),
-                                }
+                                },
+                                (false, true) => UiInput::JoinGame {
+                                    addr: url(),
+                                    passwd: passwd(),
+                                },
+                                (true, false) => UiInput::HostViaRelay {
+                                    relay: relay(),
+                                    room: url(),
+                                    passwd: passwd(),
+                                },
+                                (false, false) => UiInput::JoinViaRelay {
+                                    relay: relay(),
+                                    room: url(),
+                                    passwd: passwd(),
+                                },
                             })).expect("");
                         },
                         "continue"
@@ -0,0 +1,76 @@
+use async_channel::Sender;
+use async_std::task::block_on;
+use dioxus::prelude::*;
+
+use crate::{
+    crypto::key_store::KeysInfo,
+    ui::{
+        gui::{common::ControlPanelStyle, GameScreenType, KeysGenerating},
+        UiInput,
+    },
+};
+
+#[component]
+pub fn SettingsScreen() -> Element {
+    let keys = use_context::<Signal<KeysInfo>>();
+    let mut screen_type = use_context::<Signal<GameScreenType>>();
+    let mut generating = use_context::<Signal<KeysGenerating>>();
+    let info = keys();
+    let fingerprint = info.fingerprint.clone().unwrap_or("-".to_string());
+    let complete = info.is_complete();
+
+    rsx! {
+        h1 { class: "main-title", "Settings" }
+        ControlPanelStyle {
+            style: "margin: 3em auto",
+            div {
+                class: "form-inputs",
+                label { "key directory" }
+                p { class: "statistics-value", "{info.dir}" }
+                for status in info.circuits.iter() {
+                    label { "{status.circuit}" }
+                    p {
+                        class: "statistics-value",
+                        if status.missing.is_empty() {
+                            "present"
+                        } else if status.is_usable() {
+                            "not generated (optional)"
+                        } else {
+                            "missing {status.missing.join(\", \")}"
+                        }
+                        if !status.has_checksums { ", no checksums" }
+                        if let Some(meta) = status.meta { ", {meta}" }
+                    }
+                }
+                label { "fingerprint" }
+                p { class: "statistics-value", "{fingerprint}" }
+            }
+            if !complete {
+                p {
+                    class: "statistics-value",
+                    "The keys are needed to play. Generate them, or download them into the key directory."
+                }
+            }
+            div {
+                style: "margin: 0 auto;",
+                button {
+                    class: "abort-button",
+                    style: "margin: 0 1em 0 auto; display: inline",
+                    onclick: move |_| screen_type.set(GameScreenType::MainMenu),
+                    "back"
+                }
+                button {
+                    class: "ok-button",
+                    style: "display: inline",
+                    disabled: generating().0,
+                    onclick: move |_| {
+                        generating.set(KeysGenerating(true));
+                        let sender = use_context::<Sender<UiInput>>();
+                        block_on(sender.send(UiInput::GenerateKeys)).expect("");
+                    },
+                    if generating().0 { "generating..." } else { "generate keys" }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use async_channel::Sender;
+use async_std::task::block_on;
+use dioxus::prelude::*;
+
+use crate::{
+    logic::spectator::SpectatorState,
+    ui::{
+        gui::common::{BoardData, FieldState},
+        UiInput,
+    },
+};
+
+#[component]
+pub fn Spectator() -> Element {
+    let state = use_context::<Signal<SpectatorState>>();
+    let host_board = {
+        let mut board_data = BoardData::new(vec![]);
+        board_data.add_shots(state().host_board, FieldState::Hit);
+        board_data.board
+    };
+    let client_board = {
+        let mut board_data = BoardData::new(vec![]);
+        board_data.add_shots(state().client_board, FieldState::Hit);
+        board_data.board
+    };
+
+    rsx! {
+        h2 {
+            class: "session-score",
+            "{state().host} vs {state().client}"
+        }
+        if state().invalid_proofs > 0 {
+            p { "Invalid proofs: {state().invalid_proofs}" }
+        }
+        button {
+            class: "abort-button",
+            onclick: move |_| {
+                let sender = use_context::<Sender<UiInput>>();
+                block_on(sender.send(UiInput::Esc)).expect("");
+            },
+            "leave"
+        }
+        div {
+            style: "display: flex; align-items: center",
+            WatchedBoard { style: "margin: 3em auto", board: host_board }
+            WatchedBoard { style: "margin: 3em auto", board: client_board }
+        }
+    }
+}
+
+#[component]
+fn WatchedBoard(style: String, board: Vec<Vec<FieldState>>) -> Element {
+    rsx! {
+        div {
+            class: "board",
+            style: "{style}",
+            p { class: "column-labels-padding" }
+            for i in 1..11 {
+                p {
+                    class: "column-label",
+                    "{i}"
+                }
+            }
+            for i in 1..11 {
+                p {
+                    class: "row-label",
+                    "{i}"
+                }
+                for j in 1..11 {
+                    button {
+                        class: board[i][j].to_class_name(),
+                        disabled: true,
+                    }
+                }
+            }
+        }
+    }
+}
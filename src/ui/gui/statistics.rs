@@ -0,0 +1,90 @@
+use async_channel::Sender;
+use async_std::task::block_on;
+use dioxus::prelude::*;
+
+use crate::{
+    logic::statistics::Statistics,
+    ui::{
+        gui::{common::ControlPanelStyle, GameScreenType},
+        UiInput,
+    },
+};
+
+#[component]
+pub fn StatisticsScreen() -> Element {
+    let stats = use_context::<Signal<Statistics>>();
+    let mut screen_type = use_context::<Signal<GameScreenType>>();
+    let mut name = use_signal(|| "".to_string());
+    let summary = stats().summary();
+
+    let format_optional = |value: Option<String>| value.unwrap_or("-".to_string());
+
+    rsx! {
+        h1 { class: "main-title", "Statistics" }
+        ControlPanelStyle {
+            style: "margin: 3em auto",
+            div {
+                class: "form-inputs",
+                label { "wins" }
+                p { class: "statistics-value", "{summary.wins}" }
+                label { "losses" }
+                p { class: "statistics-value", "{summary.losses}" }
+                label { "shots to win" }
+                p {
+                    class: "statistics-value",
+                    {format_optional(summary.average_shots_to_win.map(|v| format!("{v:.1}")))}
+                }
+                label { "hit ratio" }
+                p {
+                    class: "statistics-value",
+                    {format_optional(summary.hit_ratio.map(|v| format!("{:.1}%", v * 100.0)))}
+                }
+                label { "proof time" }
+                p {
+                    class: "statistics-value",
+                    {format_optional(summary.average_proof_time_ms.map(|v| format!("{v} ms")))}
+                }
+                label { "opponents" }
+                div {
+                    for (opponent, games) in summary.opponents.iter() {
+                        p { class: "statistics-value", "{opponent}: {games}" }
+                    }
+                }
+            }
+            form {
+                onsubmit: move |_| {},
+                div {
+                    class: "form-inputs",
+                    label {
+                        r#for: "name-input",
+                        "name"
+                    }
+                    input {
+                        id: "name-input",
+                        value: "{name}",
+                        oninput: move |event| name.set(event.value())
+                    }
+                }
+                div {
+                    style: "margin: 0 auto;",
+                    button {
+                        class: "abort-button",
+                        style: "margin: 0 1em 0 auto; display: inline",
+                        onclick: move |_| screen_type.set(GameScreenType::MainMenu),
+                        "back"
+                    }
+                    button {
+                        class: "ok-button",
+                        style: "display: inline",
+                        disabled: name().is_empty(),
+                        onclick: move |_| {
+                            let sender = use_context::<Sender<UiInput>>();
+                            block_on(sender.send(UiInput::SetPlayerName(name()))).expect("");
+                        },
+                        "change name"
+                    }
+                }
+            }
+        }
+    }
+}
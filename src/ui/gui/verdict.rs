@@ -0,0 +1,48 @@
+use dioxus::prelude::*;
+
+use crate::{logic::dispute::Verdict, ui::gui::common::ControlPanelStyle};
+
+/// Dialog shown on top of the current screen until dismissed
+#[component]
+pub fn VerdictDialog() -> Element {
+    let mut verdict = use_context::<Signal<Option<Verdict>>>();
+
+    let (title, details) = match verdict() {
+        Some(Verdict::OpponentCheated {
+            reason,
+            evidence_path,
+        }) => (
+            "Cheating detected!".to_string(),
+            format!(
+                "The opponent sent an {reason}. The evidence has been saved to {evidence_path}."
+            ),
+        ),
+        Some(Verdict::AccusedByOpponent(reason)) => (
+            "Dispute reported".to_string(),
+            format!("The opponent claims that we have sent an {reason}."),
+        ),
+        None => return rsx! {},
+    };
+
+    rsx! {
+        div {
+            class: "dialog-backdrop",
+            ControlPanelStyle {
+                style: "margin: 10em auto; width: 50%",
+                h2 {
+                    style: "margin: 0 auto; font-size: 3em",
+                    "{title}"
+                }
+                p {
+                    style: "font-size: 2em",
+                    "{details}"
+                }
+                button {
+                    class: "ok-button",
+                    onclick: move |_| verdict.set(None),
+                    "dismiss"
+                }
+            }
+        }
+    }
+}
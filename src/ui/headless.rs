@@ -0,0 +1,46 @@
+use std::io::BufRead;
+
+use async_channel::{Receiver, Sender};
+
+use super::{UiInput, UiMessage};
+
+/// Run the game without any terminal or window.
+/// Every line of the standard input is a JSON encoded `UiInput`,
+/// every line written to the standard output is a JSON encoded `UiMessage`.
+pub fn run_headless(receiver: Receiver<UiMessage>, sender: Sender<UiInput>) {
+    let _ = std::thread::Builder::new()
+        .stack_size(1024 * 1024)
+        .spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<UiInput>(&line) {
+                    Ok(input) => {
+                        if sender.send_blocking(input).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => print_event(&UiMessage::Log(format!("Invalid input: {e}"))),
+                }
+            }
+            let _ = sender.send_blocking(UiInput::Exit);
+        });
+
+    while let Ok(message) = receiver.recv_blocking() {
+        print_event(&message);
+        if let UiMessage::Exit = message {
+            return;
+        }
+    }
+}
+
+fn print_event(message: &UiMessage) {
+    match serde_json::to_string(message) {
+        Ok(json) => println!("{json}"),
+        Err(e) => eprintln!("Could not serialize the event: {e}"),
+    }
+}
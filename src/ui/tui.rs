@@ -0,0 +1,518 @@
+use std::{
+    io::{stdout, Stdout},
+    time::Duration,
+};
+
+use async_channel::{Receiver, Sender};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction as LayoutDirection, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame, Terminal,
+};
+
+use crate::{
+    logic::SessionScore,
+    model::{Direction, FieldState, Ship, SHIP_SIZES},
+    net::discovery::LanGame,
+    utils::coordinates::{column_label, field_name},
+};
+
+use super::{
+    cli::parser::{complete_line, help, parse_command, CliContext},
+    UiInput, UiMessage,
+};
+
+const KEYS_HELP: &str = "Keys:
+  arrows  move the cursor       enter  place a ship / shoot
+  r       rotate the ship       c      clear the board
+  :       type a command        m      write a chat message
+  esc     interrupt             q      exit
+  tab     complete the name of the typed command";
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Water,
+    Ship,
+    Miss,
+    Hit,
+}
+
+/// What the bottom line of the screen is currently used for
+enum InputMode {
+    Navigation,
+    Command(String),
+    Chat(String),
+}
+
+struct Tui {
+    state: UiMessage,
+    logs: Vec<String>,
+    chat: Vec<String>,
+    cursor: (u8, u8),
+    direction: Direction,
+    input: InputMode,
+    score: SessionScore,
+    game_result: Option<bool>,
+    /// Games shown by the last `list` command
+    lan_games: Vec<LanGame>,
+}
+
+pub fn run_tui(receiver: Receiver<UiMessage>, sender: Sender<UiInput>) {
+    let mut terminal = match setup_terminal() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Could not initialize the terminal: {e}");
+            let _ = sender.send_blocking(UiInput::Exit);
+            return;
+        }
+    };
+
+    let mut tui = Tui {
+        state: UiMessage::MainScreen,
+        logs: vec![],
+        chat: vec![],
+        cursor: (1, 1),
+        direction: Direction::Horizontal,
+        input: InputMode::Navigation,
+        score: SessionScore::default(),
+        game_result: None,
+        lan_games: vec![],
+    };
+
+    'main: loop {
+        while let Ok(message) = receiver.try_recv() {
+            if !tui.update(message) {
+                break 'main;
+            }
+        }
+
+        if terminal.draw(|frame| tui.draw(frame)).is_err() {
+            break;
+        }
+
+        match event::poll(Duration::from_millis(50)) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => break,
+        }
+        if let Ok(Event::Key(key)) = event::read() {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(input) = tui.handle_key(key.code) {
+                if sender.send_blocking(input).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    restore_terminal(&mut terminal);
+}
+
+fn setup_terminal() -> std::io::Result<Term> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(stdout()))
+}
+
+fn restore_terminal(terminal: &mut Term) {
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal.show_cursor();
+}
+
+impl Tui {
+    /// Apply the message received from the logic. Returns false if the UI should exit
+    fn update(&mut self, message: UiMessage) -> bool {
+        match message {
+            UiMessage::Log(m) => self.logs.push(m),
+            UiMessage::Verdict(verdict) => self.logs.push(format!("!!! {verdict} !!!")),
+            UiMessage::Chat(message) => self.chat.push(message.to_string()),
+            UiMessage::LanGames(games) => {
+                self.logs
+                    .push(format!("Found {} games in the local network", games.len()));
+                for (i, game) in games.iter().enumerate() {
+                    self.logs.push(format!(
+                        "    {}. {} at {}{}",
+                        i + 1,
+                        game.announcement.room,
+                        game.addr,
+                        if game.compatible {
+                            ""
+                        } else {
+                            " (different keys)"
+                        }
+                    ));
+                }
+                self.lan_games = games;
+            }
+            UiMessage::LanDiscoveryFailed(_) => {}
+            UiMessage::GameOver { won, score } => {
+                self.score = score;
+                self.game_result = Some(won);
+            }
+            UiMessage::Exit => return false,
+            UiMessage::MainScreen => {
+                self.score = SessionScore::default();
+                self.chat.clear();
+                self.state = UiMessage::MainScreen;
+            }
+            UiMessage::Lobby => {
+                self.game_result = None;
+                self.state = UiMessage::Lobby;
+            }
+            s => self.state = s,
+        }
+        true
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> Option<UiInput> {
+        let context = self.context();
+        match &mut self.input {
+            InputMode::Command(text) if code == KeyCode::Tab => {
+                let (start, names) = complete_line(text, context);
+                match names.as_slice() {
+                    [] => {}
+                    [name] => text.replace_range(start.., &format!("{name} ")),
+                    names => self
+                        .logs
+                        .push(format!("Matching commands: {}", names.join(", "))),
+                }
+                None
+            }
+            InputMode::Command(text) | InputMode::Chat(text) => match code {
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    None
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    None
+                }
+                KeyCode::Esc => {
+                    self.input = InputMode::Navigation;
+                    None
+                }
+                KeyCode::Enter => {
+                    let input = std::mem::replace(&mut self.input, InputMode::Navigation);
+                    match input {
+                        InputMode::Command(text) => self.parse_command(&text),
+                        InputMode::Chat(text) if !text.is_empty() => {
+                            Some(UiInput::SendMessage(text))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            InputMode::Navigation => match code {
+                KeyCode::Left => {
+                    self.cursor.0 = (self.cursor.0 - 1).max(1);
+                    None
+                }
+                KeyCode::Right => {
+                    self.cursor.0 = (self.cursor.0 + 1).min(10);
+                    None
+                }
+                KeyCode::Up => {
+                    self.cursor.1 = (self.cursor.1 - 1).max(1);
+                    None
+                }
+                KeyCode::Down => {
+                    self.cursor.1 = (self.cursor.1 + 1).min(10);
+                    None
+                }
+                KeyCode::Enter => self.act_on_cursor(),
+                KeyCode::Char('r') => {
+                    self.direction = match self.direction {
+                        Direction::Horizontal => Direction::Vertical,
+                        Direction::Vertical => Direction::Horizontal,
+                    };
+                    None
+                }
+                KeyCode::Char('c') => Some(UiInput::ResetBoard),
+                KeyCode::Char(':') => {
+                    self.input = InputMode::Command(String::new());
+                    None
+                }
+                KeyCode::Char('m') => {
+                    self.input = InputMode::Chat(String::new());
+                    None
+                }
+                KeyCode::Esc => Some(UiInput::Esc),
+                KeyCode::Char('q') => Some(UiInput::Exit),
+                _ => None,
+            },
+        }
+    }
+
+    fn act_on_cursor(&mut self) -> Option<UiInput> {
+        let (x, y) = self.cursor;
+        match &self.state {
+            UiMessage::BoardConstruction(board) if board.0.len() < SHIP_SIZES.len() => {
+                Some(UiInput::PutShip(Ship {
+                    x,
+                    y,
+                    size: SHIP_SIZES[board.0.len()],
+                    direction: self.direction,
+                }))
+            }
+            UiMessage::PrintGameState(_) if self.game_result.is_none() => {
+                Some(UiInput::Shoot(x, y))
+            }
+            _ => None,
+        }
+    }
+
+    /// Part of the game deciding which commands are available
+    fn context(&self) -> CliContext {
+        match self.state {
+            UiMessage::PrintGameState(_) if self.game_result.is_some() => CliContext::GameOver,
+            ref state => state.into(),
+        }
+    }
+
+    fn parse_command(&mut self, text: &str) -> Option<UiInput> {
+        let input = parse_command(text, self.context())
+            .map_err(|e| e.to_string())
+            .and_then(|command| command.into_input(&self.state, &self.lan_games));
+        match input {
+            Ok(input) => Some(input),
+            Err(text) => {
+                self.logs.extend(text.lines().map(str::to_owned));
+                None
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(LayoutDirection::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(14),
+                Constraint::Min(5),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+
+        frame.render_widget(Paragraph::new(self.status_line()), rows[0]);
+        self.draw_main_area(frame, rows[1]);
+
+        let bottom = Layout::default()
+            .direction(LayoutDirection::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[2]);
+        draw_scrolling(frame, bottom[0], "Logs", &self.logs);
+        draw_scrolling(frame, bottom[1], "Chat", &self.chat);
+
+        let input_line = match &self.input {
+            InputMode::Navigation => Line::from(Span::styled(
+                "Press : to type a command, m to chat, q to exit",
+                Style::default().fg(Color::DarkGray),
+            )),
+            InputMode::Command(text) => Line::from(format!(":{text}")),
+            InputMode::Chat(text) => Line::from(format!("say: {text}")),
+        };
+        frame.render_widget(Paragraph::new(input_line), rows[3]);
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let mut spans = vec![Span::styled(
+            " Battleships ",
+            Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED),
+        )];
+        spans.push(Span::raw(format!(
+            "  cursor {}  ship direction {}",
+            field_name(self.cursor.0, self.cursor.1),
+            match self.direction {
+                Direction::Horizontal => "horizontal",
+                Direction::Vertical => "vertical",
+            }
+        )));
+        if self.score.games_played() > 0 {
+            spans.push(Span::raw(format!(
+                "  score {} : {}",
+                self.score.our_wins, self.score.their_wins
+            )));
+        }
+        match self.game_result {
+            Some(true) => spans.push(Span::styled(
+                "  You won! Type :rematch to play again",
+                Style::default().fg(Color::Green),
+            )),
+            Some(false) => spans.push(Span::styled(
+                "  You lost... Type :rematch to play again",
+                Style::default().fg(Color::Red),
+            )),
+            None => {}
+        }
+        Line::from(spans)
+    }
+
+    fn draw_main_area(&self, frame: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(LayoutDirection::Horizontal)
+            .constraints([
+                Constraint::Length(28),
+                Constraint::Length(28),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        match &self.state {
+            UiMessage::BoardConstruction(board) => {
+                let cells = board_cells(&board.0, &[]);
+                frame.render_widget(
+                    board_widget("Your board", &cells, Some(self.cursor)),
+                    columns[0],
+                );
+                let remaining = SHIP_SIZES.len() - board.0.len();
+                let next = match SHIP_SIZES.get(board.0.len()) {
+                    Some(size) => format!("Next ship size: {size}"),
+                    None => "Board is complete, generating proof...".to_owned(),
+                };
+                frame.render_widget(
+                    Paragraph::new(format!("Ships left: {remaining}\n{next}"))
+                        .block(Block::default().borders(Borders::ALL).title("Fleet")),
+                    columns[1],
+                );
+            }
+            UiMessage::PrintGameState(state) => {
+                let ours = board_cells(&state.board.board.ships, &state.their_shots);
+                let theirs = board_cells(&[], &state.our_shots);
+                frame.render_widget(board_widget("Your board", &ours, None), columns[0]);
+                frame.render_widget(
+                    board_widget("Opponent's board", &theirs, Some(self.cursor)),
+                    columns[1],
+                );
+            }
+            UiMessage::Spectating(state) => {
+                let host = board_cells(&[], &state.host_board);
+                let client = board_cells(&[], &state.client_board);
+                frame.render_widget(board_widget(&state.host, &host, None), columns[0]);
+                frame.render_widget(board_widget(&state.client, &client, None), columns[1]);
+            }
+            UiMessage::Lobby => {
+                frame.render_widget(
+                    Paragraph::new("Connected, waiting for the game to start...")
+                        .block(Block::default().borders(Borders::ALL).title("Lobby")),
+                    area,
+                );
+            }
+            UiMessage::Statistics(stats) => {
+                frame.render_widget(
+                    Paragraph::new(stats.summary().to_string())
+                        .block(Block::default().borders(Borders::ALL).title("Statistics")),
+                    area,
+                );
+            }
+            UiMessage::Keys(info) => {
+                frame.render_widget(
+                    Paragraph::new(info.to_string())
+                        .block(Block::default().borders(Borders::ALL).title("Keys")),
+                    area,
+                );
+            }
+            _ => {
+                frame.render_widget(
+                    Paragraph::new(format!(
+                        "{}\n\n{KEYS_HELP}",
+                        help(None, CliContext::MainScreen)
+                    ))
+                    .block(Block::default().borders(Borders::ALL).title("Main menu")),
+                    area,
+                );
+            }
+        }
+    }
+}
+
+fn draw_scrolling(frame: &mut Frame, area: Rect, title: &str, lines: &[String]) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let text = lines.iter().flat_map(|l| l.lines()).collect::<Vec<&str>>();
+    let start = text.len().saturating_sub(visible);
+    frame.render_widget(
+        Paragraph::new(text[start..].join("\n"))
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title.to_owned()),
+            ),
+        area,
+    );
+}
+
+/// Compute the content of the fields. Index [y][x], fields are numbered from 1
+fn board_cells(ships: &[Ship], shots: &[(u8, u8, FieldState)]) -> [[Cell; 11]; 11] {
+    let mut cells = [[Cell::Water; 11]; 11];
+    for ship in ships {
+        for i in 0..ship.size {
+            let (x, y) = ship.direction.transpose(ship.x, ship.y, i);
+            if (1..=10).contains(&x) && (1..=10).contains(&y) {
+                cells[y as usize][x as usize] = Cell::Ship;
+            }
+        }
+    }
+    // Shots come from the opponent, so coordinates outside of the board are not drawn
+    for &(x, y, state) in shots {
+        if !(1..=10).contains(&x) || !(1..=10).contains(&y) {
+            continue;
+        }
+        cells[y as usize][x as usize] = match state {
+            FieldState::Empty => Cell::Miss,
+            FieldState::Occupied => Cell::Hit,
+        };
+    }
+    cells
+}
+
+fn board_widget(
+    title: &str,
+    cells: &[[Cell; 11]; 11],
+    cursor: Option<(u8, u8)>,
+) -> Paragraph<'static> {
+    let mut lines = vec![Line::from(format!(
+        "   {}",
+        (1..=10)
+            .map(|x| format!("{} ", column_label(x)))
+            .collect::<String>()
+    ))];
+
+    for y in 1..=10u8 {
+        let mut spans = vec![Span::raw(format!("{y:>2} "))];
+        for x in 1..=10u8 {
+            let (symbol, colour) = match cells[y as usize][x as usize] {
+                Cell::Water => ("~", Color::Blue),
+                Cell::Ship => ("#", Color::Green),
+                Cell::Miss => ("o", Color::DarkGray),
+                Cell::Hit => ("X", Color::Red),
+            };
+            let mut style = Style::default().fg(colour);
+            if cursor == Some((x, y)) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::styled(symbol, style));
+            spans.push(Span::raw(" "));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title.to_owned()),
+    )
+}
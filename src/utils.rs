@@ -1,7 +1,9 @@
 pub mod async_receiver;
+pub mod coordinates;
 // pub mod input;
 // pub mod interruptible;
 pub mod log;
 pub mod result;
 pub mod ship_helpers;
 pub mod threads;
+pub mod worker_pool;
@@ -0,0 +1,14 @@
+/// True if (x, y) is a field of the 10x10 board, counted from 1
+pub fn is_on_board(x: u8, y: u8) -> bool {
+    (1..=10).contains(&x) && (1..=10).contains(&y)
+}
+
+/// Label of the board column `x` (1 => 'A', 10 => 'J')
+pub fn column_label(x: u8) -> char {
+    (b'A' + x - 1) as char
+}
+
+/// Human readable name of the field, e.g. "B7" for (2, 7)
+pub fn field_name(x: u8, y: u8) -> String {
+    format!("{}{}", column_label(x), y)
+}
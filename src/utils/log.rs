@@ -1,26 +1,195 @@
-use std::sync::Arc;
+use std::{
+    fmt::Display,
+    sync::{Arc, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
 
 use super::result::Res;
 
+pub mod file_sink;
+pub mod tracing_sink;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trace" => Ok(Level::Trace),
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            _ => Err(format!("Unknown log level `{s}`")),
+        }
+    }
+}
+
+/// Single logged event
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: Level,
+    pub message: String,
+    /// Structured context, e.g. `component` or `game`
+    pub fields: Vec<(String, String)>,
+    /// Milliseconds since the Unix epoch
+    pub time: u128,
+}
+
+impl Record {
+    pub fn new(level: Level, message: &str) -> Self {
+        Record {
+            level,
+            message: message.to_owned(),
+            fields: vec![],
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{:03} {:<5}",
+            self.time / 1000,
+            self.time % 1000,
+            self.level
+        )?;
+        for (key, value) in &self.fields {
+            write!(f, " {key}={value}")?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
+
 pub trait Log {
     fn log_message(&self, msg: &str) -> Res<()>;
+
+    /// Sinks ignoring the level and the fields only need to implement `log_message`
+    fn log(&self, record: &Record) -> Res<()> {
+        self.log_message(&record.message)
+    }
+
+    fn debug(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Debug, msg))
+    }
+
+    fn info(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Info, msg))
+    }
+
+    fn warn(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Warn, msg))
+    }
+
+    fn error(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Error, msg))
+    }
 }
 
-#[derive(Clone)]
+/// Sends every record to all the sinks accepting its level, with the fields of the logger attached
+#[derive(Clone, Default)]
 pub struct Logger {
-    pub(crate) log: Arc<dyn Log + Send + Sync>,
+    sinks: Arc<Vec<(Level, Arc<dyn Log + Send + Sync>)>>,
+    fields: Vec<(String, String)>,
 }
 
 impl Log for Logger {
     fn log_message(&self, msg: &str) -> Res<()> {
-        self.log.log_message(msg)
+        self.info(msg)
+    }
+
+    fn log(&self, record: &Record) -> Res<()> {
+        let mut record = record.clone();
+        for (key, value) in &self.fields {
+            if record.field(key).is_none() {
+                record.fields.push((key.clone(), value.clone()));
+            }
+        }
+        // A failing sink does not keep the record from the others
+        let mut result = Ok(());
+        for (min_level, sink) in self.sinks.iter() {
+            if record.level >= *min_level {
+                let logged = sink.log(&record);
+                if result.is_ok() {
+                    result = logged;
+                }
+            }
+        }
+        result
     }
 }
 
 impl Logger {
     pub fn new(l: impl Log + Send + Sync + 'static) -> Self {
-        Self { log: Arc::new(l) }
+        Logger::default().with_sink(l, Level::Trace)
     }
+
+    /// Add a sink receiving the records of at least `min_level`
+    pub fn with_sink(mut self, l: impl Log + Send + Sync + 'static, min_level: Level) -> Self {
+        let mut sinks = self.sinks.as_ref().clone();
+        sinks.push((min_level, Arc::new(l)));
+        self.sinks = Arc::new(sinks);
+        self
+    }
+
+    /// Attach the field to every record, replacing the previous value of the key
+    pub fn with_field(mut self, key: &str, value: &str) -> Self {
+        self.fields.retain(|(k, _)| k != key);
+        self.fields.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn without_field(mut self, key: &str) -> Self {
+        self.fields.retain(|(k, _)| k != key);
+        self
+    }
+}
+
+static BACKGROUND: OnceLock<Logger> = OnceLock::new();
+
+/// Set the sinks receiving every record shown in the UI, e.g. log files. Can be done only once
+pub fn set_background_logger(logger: Logger) {
+    let _ = BACKGROUND.set(logger);
+}
+
+pub fn background_logger() -> Logger {
+    BACKGROUND.get().cloned().unwrap_or_default()
 }
 
 struct PrintLogger {}
@@ -33,5 +202,7 @@ impl Log for PrintLogger {
 }
 
 pub fn get_print_logger() -> Logger {
-    Logger::new(PrintLogger {})
+    Logger::default()
+        .with_sink(PrintLogger {}, Level::Info)
+        .with_sink(background_logger(), Level::Trace)
 }
@@ -0,0 +1,78 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use crate::utils::result::{Er, Res};
+
+use super::{Level, Log, Record};
+
+pub static LOG_DIR: &str = "logs";
+
+/// Log file renamed to `name.1` when it grows over `max_size` bytes.
+/// Older files are shifted to `name.2`, `name.3`... and only `max_files` of them are kept
+pub struct FileLog {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: Mutex<File>,
+}
+
+impl FileLog {
+    pub fn new(dir: &str, name: &str, max_size: u64, max_files: usize) -> Res<Self> {
+        fs::create_dir_all(dir)?;
+        let path = PathBuf::from(dir).join(name);
+        Ok(FileLog {
+            file: Mutex::new(open(&path)?),
+            path,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}"));
+        name.into()
+    }
+
+    fn rotate(&self, file: &mut File) -> Res<()> {
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        *file = open(&self.path)?;
+        Ok(())
+    }
+}
+
+fn open(path: &PathBuf) -> Res<File> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+impl Log for FileLog {
+    fn log_message(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Info, msg))
+    }
+
+    fn log(&self, record: &Record) -> Res<()> {
+        let mut file = self.file.lock().map_err(|_| Er {
+            message: "Log file is unavailable".to_owned(),
+        })?;
+        // Multi-line messages are kept in one line of the file
+        writeln!(file, "{}", record.to_string().replace('\n', " | "))?;
+        if file.metadata()?.len() > self.max_size {
+            self.rotate(&mut file)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::utils::result::Res;
+
+use super::{Level, Log, Record};
+
+/// Forwards the records as `tracing` events, with the `component` and `game` fields
+pub struct TracingLog;
+
+macro_rules! event {
+    ($macro:ident, $record:expr) => {
+        tracing::$macro!(
+            component = $record.field("component").unwrap_or_default(),
+            game = $record.field("game").unwrap_or_default(),
+            "{}",
+            $record.message
+        )
+    };
+}
+
+impl Log for TracingLog {
+    fn log_message(&self, msg: &str) -> Res<()> {
+        self.log(&Record::new(Level::Info, msg))
+    }
+
+    fn log(&self, record: &Record) -> Res<()> {
+        match record.level {
+            Level::Trace => event!(trace, record),
+            Level::Debug => event!(debug, record),
+            Level::Info => event!(info, record),
+            Level::Warn => event!(warn, record),
+            Level::Error => event!(error, record),
+        }
+        Ok(())
+    }
+}
+
+/// Print the `tracing` events of at least the given level to the standard error
+pub fn init_tracing(level: Level) {
+    let level = match level {
+        Level::Trace => tracing::Level::TRACE,
+        Level::Debug => tracing::Level::DEBUG,
+        Level::Info => tracing::Level::INFO,
+        Level::Warn => tracing::Level::WARN,
+        Level::Error => tracing::Level::ERROR,
+    };
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
@@ -27,6 +27,22 @@ impl From<serde_json::Error> for Er {
     }
 }
 
+impl From<bincode::Error> for Er {
+    fn from(value: bincode::Error) -> Self {
+        Er {
+            message: format!("Parsing error: {}", value),
+        }
+    }
+}
+
+impl From<async_tungstenite::tungstenite::Error> for Er {
+    fn from(value: async_tungstenite::tungstenite::Error) -> Self {
+        Er {
+            message: format!("WebSocket error: {}", value),
+        }
+    }
+}
+
 impl From<async_channel::RecvError> for Er {
     fn from(value: async_channel::RecvError) -> Self {
         Er {
@@ -51,6 +67,14 @@ impl From<SynthesisError> for Er {
     }
 }
 
+impl From<ark_poly_commit::Error> for Er {
+    fn from(value: ark_poly_commit::Error) -> Self {
+        Er {
+            message: format!("Polynomial commitment error: {}", value),
+        }
+    }
+}
+
 impl From<SerializationError> for Er {
     fn from(value: SerializationError) -> Self {
         Er {
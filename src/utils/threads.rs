@@ -1,5 +1,3 @@
-use std::thread;
-
 use futures::{select, FutureExt};
 
 use futures::{
@@ -9,20 +7,6 @@ use futures::{
 
 use super::result::Res;
 
-pub async fn spawn_thread_async<F, T>(f: F) -> Res<T>
-where
-    F: FnOnce() -> T + Send + 'static,
-    T: Send + 'static,
-{
-    let (sender, receiver) = async_channel::unbounded::<T>();
-
-    let _ = thread::spawn(move || {
-        let _ = sender.send_blocking(f());
-    });
-
-    Ok(receiver.recv().await?)
-}
-
 pub async fn select_first<K, L>(
     f1: impl Future<Output = Res<K>>,
     f2: impl Future<Output = Res<L>>,
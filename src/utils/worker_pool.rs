@@ -0,0 +1,273 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fmt::Display,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Condvar, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use async_channel::Receiver;
+
+use crate::config::config;
+
+use super::result::{Er, Res};
+
+/// Jobs of a higher priority are started first, jobs of the same priority in the order of submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Work that may turn out to be unnecessary
+    Speculative,
+    /// Our own proofs and other work awaited by the player
+    Normal,
+    /// Verification of the opponent's proofs
+    High,
+    /// Loading of the keys, which other jobs wait for. Also run by a worker reserved for them
+    KeyLoading,
+}
+
+/// Statistics of the pool since its creation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolMetrics {
+    pub workers: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: u64,
+    pub cancelled: u64,
+    /// Total time the completed jobs waited in the queue
+    pub waiting_time: Duration,
+    /// Total time spent on running the completed jobs
+    pub running_time: Duration,
+}
+
+impl Display for PoolMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} workers, {} queued, {} running, {} completed, {} cancelled, waiting {:.2?}, running {:.2?}",
+            self.workers,
+            self.queued,
+            self.running,
+            self.completed,
+            self.cancelled,
+            self.waiting_time,
+            self.running_time
+        )
+    }
+}
+
+struct Job {
+    priority: Priority,
+    /// Number of the job, keeping the order of submission within a priority
+    number: u64,
+    submitted_at: Instant,
+    cancelled: Arc<AtomicBool>,
+    task: Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then(other.number.cmp(&self.number))
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BinaryHeap<Job>,
+    submitted: u64,
+    metrics: PoolMetrics,
+}
+
+struct Inner {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+/// Fixed number of threads running CPU-heavy work, such as generating and verifying proofs
+#[derive(Clone)]
+pub struct WorkerPool {
+    inner: Arc<Inner>,
+}
+
+/// Result of a submitted job. Dropping the handle cancels the job if it has not started yet
+pub struct JobHandle<T> {
+    receiver: Receiver<T>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T> JobHandle<T> {
+    pub async fn result(&self) -> Res<T> {
+        self.receiver.recv().await.map_err(|_| Er {
+            message: "The job has been cancelled or has failed".to_owned(),
+        })
+    }
+
+    /// Skip the job if it has not started yet. Started jobs run to completion, but their result is dropped
+    pub fn cancel(&self) {
+        self.cancelled.store(true, AtomicOrdering::Relaxed);
+    }
+}
+
+impl<T> Drop for JobHandle<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl WorkerPool {
+    /// Start the workers, at least one, so the jobs always run. Another worker only loads keys,
+    /// so jobs waiting for the keys cannot take every worker and starve the loading
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let pool = WorkerPool {
+            inner: Arc::new(Inner {
+                queue: Mutex::new(Queue::default()),
+                available: Condvar::new(),
+            }),
+        };
+        pool.with_queue(|queue| queue.metrics.workers = workers);
+        for _ in 0..workers {
+            let pool = pool.clone();
+            thread::spawn(move || pool.work(false));
+        }
+        let key_loader = pool.clone();
+        thread::spawn(move || key_loader.work(true));
+        pool
+    }
+
+    fn with_queue<R>(&self, f: impl FnOnce(&mut Queue) -> R) -> R {
+        let mut queue = self.inner.queue.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut queue)
+    }
+
+    pub fn spawn<F, T>(&self, priority: Priority, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = async_channel::bounded(1);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = Box::new(move || {
+            let _ = sender.send_blocking(f());
+        });
+        self.push(priority, task, cancelled.clone());
+
+        JobHandle {
+            receiver,
+            cancelled,
+        }
+    }
+
+    /// Submit a job whose result is not awaited
+    pub fn execute(&self, priority: Priority, f: impl FnOnce() + Send + 'static) {
+        self.push(priority, Box::new(f), Arc::new(AtomicBool::new(false)));
+    }
+
+    fn push(&self, priority: Priority, task: Box<dyn FnOnce() + Send>, cancelled: Arc<AtomicBool>) {
+        self.with_queue(|queue| {
+            queue.submitted += 1;
+            queue.jobs.push(Job {
+                priority,
+                number: queue.submitted,
+                submitted_at: Instant::now(),
+                cancelled,
+                task,
+            });
+            queue.metrics.queued = queue.jobs.len();
+        });
+        // The woken worker may be the one reserved for the keys, unable to run the job
+        self.inner.available.notify_all();
+    }
+
+    /// Run the job in the pool and wait for its result
+    pub async fn run<F, T>(&self, priority: Priority, f: F) -> Res<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawn(priority, f).result().await
+    }
+
+    pub fn metrics(&self) -> PoolMetrics {
+        self.with_queue(|queue| queue.metrics)
+    }
+
+    /// Run the queued jobs, only the loading of the keys if `keys_only`
+    fn work(&self, keys_only: bool) {
+        loop {
+            let job = {
+                let mut queue = self.inner.queue.lock().unwrap_or_else(|e| e.into_inner());
+                loop {
+                    // Loading of the keys has the highest priority, so it is always on top of the queue
+                    match queue.jobs.peek() {
+                        Some(job) if job.cancelled.load(AtomicOrdering::Relaxed) => {
+                            queue.jobs.pop();
+                            queue.metrics.cancelled += 1;
+                        }
+                        Some(job) if !keys_only || job.priority == Priority::KeyLoading => {
+                            break queue.jobs.pop().unwrap();
+                        }
+                        next => {
+                            if next.is_none() {
+                                queue.metrics.queued = 0;
+                            }
+                            queue = self
+                                .inner
+                                .available
+                                .wait(queue)
+                                .unwrap_or_else(|e| e.into_inner());
+                        }
+                    }
+                }
+            };
+            let started_at = Instant::now();
+            self.with_queue(|queue| {
+                queue.metrics.queued = queue.jobs.len();
+                queue.metrics.running += 1;
+                queue.metrics.waiting_time += started_at - job.submitted_at;
+            });
+
+            // A panicking job drops its result, but the worker stays alive
+            let _ = catch_unwind(AssertUnwindSafe(job.task));
+
+            self.with_queue(|queue| {
+                queue.metrics.running -= 1;
+                queue.metrics.completed += 1;
+                queue.metrics.running_time += started_at.elapsed();
+            });
+        }
+    }
+}
+
+static POOL: OnceLock<WorkerPool> = OnceLock::new();
+
+/// Pool shared by the whole game, with the configured number of workers or a worker per CPU core (at least two)
+pub fn worker_pool() -> &'static WorkerPool {
+    POOL.get_or_init(|| {
+        let workers = config().proof_workers.unwrap_or_else(|| {
+            let cores = thread::available_parallelism().map_or(2, |n| n.get());
+            cores.max(2)
+        });
+        WorkerPool::new(workers)
+    })
+}
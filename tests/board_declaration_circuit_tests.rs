@@ -93,7 +93,7 @@ mod tests {
             hash: hash_result.into(),
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -126,7 +126,7 @@ mod tests {
             hash: hash_result.into(),
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -159,7 +159,7 @@ mod tests {
             hash: hash_result.into(),
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use ark_bls12_377::Fr;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use battleships::{
+        circuit::{
+            board_declaration_circuit::BoardDeclarationCircuit,
+            game_certificate_circuit::{
+                players_digest, CertificateBackend, CertificateField, CertificateInput,
+                CertifiedBoard, GameCertificateCircuit, InnerBackend,
+            },
+            report::{count_constraints, sample_board, sample_shot_history},
+            shot_history_circuit::{ShotHistoryCircuit, ShotHistoryInput},
+        },
+        crypto::{backend::ProvingBackend, proofs::PublicInput},
+        logic::certificate::game_id,
+        model::FieldState,
+    };
+
+    /// Stands in for a game circuit: has its public inputs and no constraints, so any input can be proven
+    #[derive(Clone)]
+    struct Inputs(Vec<Fr>);
+
+    impl ConstraintSynthesizer<Fr> for Inputs {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            for value in self.0 {
+                cs.new_input_variable(|| Ok(value))?;
+            }
+            Ok(())
+        }
+    }
+
+    type Keys = (
+        <InnerBackend as ProvingBackend>::ProvingKey,
+        <InnerBackend as ProvingBackend>::PreparedVerifyingKey,
+    );
+
+    fn keys(inputs: usize, rng: &mut StdRng) -> Keys {
+        let (pk, vk) = InnerBackend::setup(Inputs(vec![Fr::from(0u8); inputs]), rng).unwrap();
+        (pk, InnerBackend::prepare(&vk))
+    }
+
+    fn certified_board(
+        board: BoardDeclarationCircuit,
+        shots: Vec<(u8, u8, FieldState)>,
+        (board_keys, history_keys): (&Keys, &Keys),
+        rng: &mut StdRng,
+    ) -> CertifiedBoard {
+        let board_input: PublicInput<Fr> = board.hash.to_vec().into();
+        let history_input = ShotHistoryInput {
+            hash: board.hash,
+            shots: shots.clone(),
+        }
+        .into_public_input::<Fr>()
+        .unwrap();
+        CertifiedBoard {
+            hash: board.hash,
+            board_proof: InnerBackend::prove(&board_keys.0, Inputs(board_input.0), rng).unwrap(),
+            shots,
+            history_proof: InnerBackend::prove(&history_keys.0, Inputs(history_input.0), rng)
+                .unwrap(),
+        }
+    }
+
+    /// The host's ships have all been sunk, the client was shot ten times
+    fn sample_certificate() -> GameCertificateCircuit {
+        let mut rng = StdRng::seed_from_u64(1);
+        let board_keys = keys(256, &mut rng);
+        let history_keys = keys(256 + 300, &mut rng);
+        let keys = (&board_keys, &history_keys);
+
+        let host = BoardDeclarationCircuit::from(sample_board());
+        let client = BoardDeclarationCircuit::from(sample_board());
+        let host_shots = sample_shot_history(host).shots;
+        let mut client_shots = sample_shot_history(client).shots;
+        client_shots.truncate(10);
+
+        GameCertificateCircuit {
+            board_vk: board_keys.1.clone(),
+            history_vk: history_keys.1.clone(),
+            host: certified_board(host, host_shots, keys, &mut rng),
+            client: certified_board(client, client_shots, keys, &mut rng),
+            host_won: false,
+            players: players_digest("sample", "Alice", "Bob"),
+        }
+    }
+
+    #[test]
+    fn certificate_of_a_finished_game_is_satisfied_test() {
+        assert!(count_constraints::<CertificateField, _>(sample_certificate()).is_ok());
+    }
+
+    #[test]
+    fn certificate_with_a_wrong_winner_is_not_satisfied_test() {
+        let certificate = GameCertificateCircuit {
+            host_won: true,
+            ..sample_certificate()
+        };
+        assert!(count_constraints::<CertificateField, _>(certificate).is_err());
+    }
+
+    #[test]
+    fn names_cannot_be_shifted_between_players_test() {
+        assert_ne!(
+            players_digest("game", "Alice", "Bob"),
+            players_digest("game", "AliceB", "ob")
+        );
+        assert_ne!(
+            players_digest("game", "Alice", "Bob"),
+            players_digest("game", "Bob", "Alice")
+        );
+    }
+
+    #[test]
+    #[ignore = "sets up and proves every circuit, takes about eight minutes in a release build"]
+    fn proofs_of_a_real_game_are_certified_test() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let board = BoardDeclarationCircuit::from(sample_board());
+        let all_shots = sample_shot_history(board).shots;
+
+        let (board_pk, board_vk) = InnerBackend::setup(board, &mut rng).unwrap();
+        let (history_pk, history_vk) =
+            InnerBackend::setup(ShotHistoryCircuit::from((board, vec![])), &mut rng).unwrap();
+        let board_proof = InnerBackend::prove(&board_pk, board, &mut rng).unwrap();
+        let mut certified = |shots: Vec<(u8, u8, FieldState)>| {
+            let history = ShotHistoryCircuit::from((board, shots.clone()));
+            CertifiedBoard {
+                hash: board.hash,
+                board_proof: board_proof.clone(),
+                shots,
+                history_proof: InnerBackend::prove(&history_pk, history, &mut rng).unwrap(),
+            }
+        };
+        // Both players declared the sample board, the host's ships have all been sunk
+        let host = certified(all_shots.clone());
+        let client = certified(all_shots[..10].to_vec());
+
+        let id = game_id(&board.hash, &board.hash);
+        let circuit = GameCertificateCircuit {
+            board_vk: InnerBackend::prepare(&board_vk),
+            history_vk: InnerBackend::prepare(&history_vk),
+            host,
+            client,
+            host_won: false,
+            players: players_digest(&id, "Alice", "Bob"),
+        };
+        let (pk, vk) = CertificateBackend::setup(circuit.clone(), &mut rng).unwrap();
+        let proof = CertificateBackend::prove(&pk, circuit, &mut rng).unwrap();
+        let vk = CertificateBackend::prepare(&vk);
+
+        let verify = |host_won: bool, client: &str| {
+            let input: PublicInput<CertificateField> = CertificateInput {
+                host_hash: board.hash,
+                client_hash: board.hash,
+                host_won,
+                players: players_digest(&id, "Alice", client),
+            }
+            .into();
+            CertificateBackend::verify(&vk, &input.0, &proof).unwrap()
+        };
+        assert!(verify(false, "Bob"));
+        assert!(!verify(true, "Bob"));
+        assert!(!verify(false, "Mallory"));
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_channel::unbounded;
+    use async_std::task::{self, block_on};
+    use battleships::{
+        logic::{chat::ChatLog, main::route_chat, GameMessage},
+        net::{
+            connection::{ConnectionLimits, Endpoint},
+            message::Message,
+        },
+        ui::UiMessage,
+        utils::log::get_print_logger,
+    };
+
+    #[test]
+    fn game_messages_arrive_after_chat_test() {
+        block_on(async {
+            let addr = "127.0.0.1:34351";
+            let host = task::spawn(Endpoint::<GameMessage>::accept_incoming_connection(
+                addr,
+                "",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            let client = Endpoint::<GameMessage>::create_connection_to(
+                addr,
+                "",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let (host_sender, _host_receiver, _) = host.await.unwrap().as_channel_pair();
+            let (_client_sender, client_receiver, _) = client.as_channel_pair();
+
+            let (ui_sender, ui_receiver) = unbounded();
+            let chat = ChatLog::default();
+            let (receiver, _task, _reclaim) =
+                route_chat(client_receiver, ui_sender, chat.clone(), "Alice".to_owned());
+
+            // The sender named in the message is not trusted
+            host_sender
+                .send(Message::Info {
+                    sender: "Bob".to_owned(),
+                    info: "good luck".to_owned(),
+                })
+                .await
+                .unwrap();
+            host_sender
+                .send(Message::Value(GameMessage::RematchOffer))
+                .await
+                .unwrap();
+
+            assert!(matches!(
+                receiver.get().await,
+                Ok(Message::Value(GameMessage::RematchOffer))
+            ));
+            assert!(matches!(
+                ui_receiver.recv().await,
+                Ok(UiMessage::Chat(message)) if message.text == "good luck" && message.sender == "Alice"
+            ));
+            assert_eq!(chat.take().len(), 1);
+        });
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use battleships::{
+        model::{Direction, IncompleteBoard, Ship, SHIP_SIZES},
+        ui::{
+            cli::parser::{complete, complete_line, help, parse_command, CliContext, Command},
+            UiInput, UiMessage,
+        },
+    };
+
+    #[test]
+    fn fields_are_parsed_in_all_notations_test() {
+        for line in ["shoot B7", "shoot b7", "shoot 2,7", "shoot 2 7", "SHOOT B7"] {
+            assert_eq!(
+                parse_command(line, CliContext::Game),
+                Ok(Command::Shoot { x: 2, y: 7 }),
+                "{line}"
+            );
+        }
+        assert_eq!(
+            parse_command("shoot J10", CliContext::Game),
+            Ok(Command::Shoot { x: 10, y: 10 })
+        );
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected_test() {
+        for line in [
+            "shoot",
+            "shoot K1",
+            "shoot A0",
+            "shoot A11",
+            "shoot 0,5",
+            "shoot 3",
+            "shoot x,y",
+            "shoot 300 1",
+            "shoot B7 B8",
+        ] {
+            assert!(parse_command(line, CliContext::Game).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn ships_are_put_in_both_directions_test() {
+        assert_eq!(
+            parse_command("put C3 down", CliContext::BoardConstruction),
+            Ok(Command::Put {
+                x: 3,
+                y: 3,
+                direction: Direction::Vertical
+            })
+        );
+        assert_eq!(
+            parse_command("put 1 10 right", CliContext::BoardConstruction),
+            Ok(Command::Put {
+                x: 1,
+                y: 10,
+                direction: Direction::Horizontal
+            })
+        );
+        assert!(parse_command("put C3 diagonal", CliContext::BoardConstruction).is_err());
+        assert!(parse_command("put C3", CliContext::BoardConstruction).is_err());
+    }
+
+    #[test]
+    fn commands_depend_on_the_context_test() {
+        assert!(parse_command("shoot B7", CliContext::MainScreen).is_err());
+        assert!(parse_command("create 127.0.0.1:4000 secret", CliContext::Game).is_err());
+        assert_eq!(
+            parse_command("create 127.0.0.1:4000 secret", CliContext::MainScreen),
+            Ok(Command::Create {
+                addr: "127.0.0.1:4000".to_owned(),
+                passwd: "secret".to_owned()
+            })
+        );
+        assert_eq!(
+            parse_command("join 1", CliContext::MainScreen),
+            Ok(Command::Join {
+                addr: "1".to_owned(),
+                passwd: "".to_owned()
+            })
+        );
+        assert_eq!(
+            parse_command("list", CliContext::MainScreen),
+            Ok(Command::List)
+        );
+        assert_eq!(
+            parse_command("help", CliContext::Lobby),
+            Ok(Command::Help(None))
+        );
+        assert_eq!(
+            parse_command("prove-history", CliContext::GameOver),
+            Ok(Command::ProveHistory)
+        );
+        assert!(parse_command("prove-history", CliContext::Game).is_err());
+        assert!(help(None, CliContext::MainScreen).contains("create"));
+        assert!(!help(None, CliContext::MainScreen).contains("shoot"));
+    }
+
+    #[test]
+    fn commands_are_completed_test() {
+        assert_eq!(
+            complete("c", CliContext::MainScreen),
+            vec!["create", "create-relay"]
+        );
+        assert_eq!(complete("c", CliContext::BoardConstruction), vec!["clear"]);
+        assert_eq!(
+            parse_command("sh B7", CliContext::Game),
+            Ok(Command::Shoot { x: 2, y: 7 })
+        );
+        assert!(parse_command("", CliContext::Game).is_err());
+        assert!(parse_command("fire B7", CliContext::Game).is_err());
+    }
+
+    #[test]
+    fn command_name_of_the_line_is_completed_test() {
+        assert_eq!(complete_line("  SH", CliContext::Game), (2, vec!["shoot"]));
+        assert_eq!(
+            complete_line("c", CliContext::MainScreen),
+            (0, vec!["create", "create-relay"])
+        );
+        // Only the name is completed, not the arguments
+        assert_eq!(complete_line("shoot B", CliContext::Game), (7, vec![]));
+    }
+
+    #[test]
+    fn commands_are_turned_into_inputs_test() {
+        let state = UiMessage::BoardConstruction(IncompleteBoard(vec![]));
+        let put = Command::Put {
+            x: 2,
+            y: 7,
+            direction: Direction::Vertical,
+        };
+        assert!(matches!(
+            put.clone().into_input(&state, &[]),
+            Ok(UiInput::PutShip(Ship {
+                x: 2,
+                y: 7,
+                size,
+                direction: Direction::Vertical,
+            })) if size == SHIP_SIZES[0]
+        ));
+        assert!(put.into_input(&UiMessage::MainScreen, &[]).is_err());
+        assert!(matches!(
+            Command::Help(None).into_input(&state, &[]),
+            Err(text) if text.contains("put")
+        ));
+    }
+
+    #[test]
+    fn messages_keep_all_words_test() {
+        assert_eq!(
+            parse_command("msg good  luck", CliContext::Lobby),
+            Ok(Command::Msg("good luck".to_owned()))
+        );
+        assert!(parse_command("msg", CliContext::Lobby).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ark_serialize::CanonicalSerialize;
+    use async_std::task::{self, block_on};
+    use battleships::{
+        circuit::field_declaration_circuit::FieldDeclarationCircuit,
+        crypto::{backend::Proof, proofs::CorrectnessProof},
+        net::{
+            codec::{Hello, WireFormat, PROTOCOL_VERSION},
+            connection::{ConnectionLimits, Endpoint},
+            message::Message,
+        },
+        utils::log::get_print_logger,
+    };
+
+    #[test]
+    fn negotiation_prefers_bincode_test() {
+        let ours = Hello::ours();
+        let json_only = Hello {
+            version: PROTOCOL_VERSION,
+            features: ours
+                .features
+                .iter()
+                .filter(|f| *f != "bincode")
+                .cloned()
+                .collect(),
+        };
+        assert_eq!(ours.negotiate(&ours.clone()).unwrap(), WireFormat::Bincode);
+        assert_eq!(ours.negotiate(&json_only).unwrap(), WireFormat::Json);
+
+        let old = Hello {
+            version: PROTOCOL_VERSION - 1,
+            features: ours.features.clone(),
+        };
+        assert!(ours.negotiate(&old).is_err());
+    }
+
+    #[test]
+    fn negotiation_requires_same_curve_test() {
+        let ours = Hello::ours();
+        let no_curve = Hello {
+            version: PROTOCOL_VERSION,
+            features: vec!["bincode".to_owned()],
+        };
+        assert!(ours.negotiate(&no_curve).is_err());
+
+        let mut other_curve = ours.clone();
+        other_curve.features.retain(|f| !f.starts_with("curve:"));
+        other_curve.features.push("curve:bn128".to_owned());
+        let error = ours.negotiate(&other_curve).unwrap_err();
+        assert!(error.message.contains("bn128"));
+    }
+
+    #[test]
+    fn negotiation_requires_same_proving_backend_test() {
+        let ours = Hello::ours();
+        let mut other_backend = ours.clone();
+        other_backend.features.retain(|f| !f.starts_with("proof:"));
+        other_backend.features.push("proof:plonk".to_owned());
+        let error = ours.negotiate(&other_backend).unwrap_err();
+        assert!(error.message.contains("plonk"));
+
+        // Players announcing no proof system prove with Groth16
+        let mut unannounced = ours.clone();
+        unannounced.features.retain(|f| !f.starts_with("proof:"));
+        assert_eq!(
+            ours.negotiate(&unannounced).is_ok(),
+            ours.features.iter().any(|f| f == "proof:groth16")
+        );
+    }
+
+    #[test]
+    fn proofs_are_compressed_test() {
+        let mut uncompressed = vec![];
+        Proof::default()
+            .serialize_uncompressed(&mut uncompressed)
+            .unwrap();
+        let mut compressed = vec![];
+        Proof::default()
+            .serialize_compressed(&mut compressed)
+            .unwrap();
+        let proof: CorrectnessProof<FieldDeclarationCircuit> =
+            serde_json::from_slice(&serde_json::to_vec(&compressed).unwrap()).unwrap();
+
+        let encoded = WireFormat::Bincode.encode(&proof).unwrap();
+        assert!(encoded.len() < uncompressed.len() / 2 + 16);
+        let decoded: CorrectnessProof<FieldDeclarationCircuit> =
+            WireFormat::Bincode.decode(&encoded).unwrap();
+        assert_eq!(decoded.0, proof.0);
+    }
+
+    #[test]
+    fn unchecked_points_are_rejected_test() {
+        let mut uncompressed = vec![];
+        Proof::default()
+            .serialize_uncompressed(&mut uncompressed)
+            .unwrap();
+        let legacy = serde_json::to_vec(&uncompressed).unwrap();
+        assert!(
+            serde_json::from_slice::<CorrectnessProof<FieldDeclarationCircuit>>(&legacy).is_err()
+        );
+
+        // Coordinates which are not points of the curve
+        let garbage =
+            serde_json::to_vec(&vec![0x5au8; Proof::default().compressed_size()]).unwrap();
+        assert!(
+            serde_json::from_slice::<CorrectnessProof<FieldDeclarationCircuit>>(&garbage).is_err()
+        );
+    }
+
+    #[test]
+    fn endpoints_agree_on_bincode_test() {
+        let addr = "127.0.0.1:34321";
+        block_on(async {
+            let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+                addr,
+                "secret",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            let mut client = Endpoint::<String>::create_connection_to(
+                addr,
+                "secret",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let mut host = host.await.unwrap();
+            assert_eq!(client.format, WireFormat::Bincode);
+            assert_eq!(host.format, WireFormat::Bincode);
+
+            client
+                .send(&Message::Value("hello".to_owned()))
+                .await
+                .unwrap();
+            assert!(matches!(host.receive().await.unwrap(), Message::Value(v) if v == "hello"));
+        });
+    }
+}
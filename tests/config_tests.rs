@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use battleships::{
+        config::{Config, Theme, UiKind},
+        logic::profile::PlayerProfile,
+        net::connection::ConnectionLimits,
+        utils::log::Level,
+    };
+
+    #[test]
+    fn missing_entries_take_defaults_test() {
+        let config = Config::parse(
+            r#"
+            ui = "tui"
+            proof_workers = 3
+
+            [gui]
+            theme = "dark"
+
+            [log]
+            level = "warn"
+
+            [net]
+            read_timeout = 5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.ui, UiKind::Tui);
+        assert_eq!(config.proof_workers, Some(3));
+        assert_eq!(config.gui.theme, Theme::Dark);
+        assert!(config.gui.maximized);
+        assert_eq!(config.log.level, Level::Warn);
+        assert_eq!(config.log.max_files, 5);
+        let limits = config.net.limits();
+        assert_eq!(limits.read_timeout, Duration::from_secs(5));
+        assert_eq!(
+            limits.idle_timeout,
+            ConnectionLimits::default().idle_timeout
+        );
+        assert_eq!(config.default_address, Config::default().default_address);
+
+        assert_eq!(Config::parse(&config.to_toml().unwrap()).unwrap(), config);
+        assert!(Config::parse("ui = \"web\"").is_err());
+    }
+
+    #[test]
+    fn missing_file_gives_default_config_test() {
+        let path = std::env::temp_dir().join("battleships-missing-config.toml");
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn configured_name_replaces_stored_one_test() {
+        let stored: PlayerProfile =
+            serde_json::from_str(r#"{"name": "Stored", "keys_dir": "old-keys"}"#).unwrap();
+        assert_eq!(stored.keys_dir.as_deref(), Some("old-keys"));
+
+        let profile = stored.clone().configured(&Config::default());
+        assert_eq!(profile.name, "Stored");
+
+        let config = Config {
+            player_name: Some("Configured".to_owned()),
+            ..Config::default()
+        };
+        assert_eq!(stored.configured(&config).name, "Configured");
+    }
+}
@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_std::{
+        future::timeout,
+        io::{ReadExt, WriteExt},
+        net::TcpStream,
+        task::{self, block_on},
+    };
+    use battleships::{
+        net::{
+            codec::{decode_frame, encode_frame, Hello, WireFormat, DEFAULT_MAX_FRAME_SIZE},
+            connection::{ConnectionLimits, Endpoint},
+            message::Message,
+        },
+        utils::log::get_print_logger,
+    };
+
+    fn short_limits() -> ConnectionLimits {
+        ConnectionLimits {
+            idle_timeout: Duration::from_millis(300),
+            heartbeat_interval: Duration::from_millis(100),
+            ..Default::default()
+        }
+    }
+
+    async fn connected_pair(
+        addr: &'static str,
+        limits: ConnectionLimits,
+    ) -> (Endpoint<String>, Endpoint<String>) {
+        let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+            addr,
+            "",
+            "Alice",
+            limits,
+            get_print_logger(),
+        ));
+        task::sleep(Duration::from_millis(200)).await;
+        let client =
+            Endpoint::<String>::create_connection_to(addr, "", "Bob", limits, get_print_logger())
+                .await
+                .unwrap();
+        (host.await.unwrap(), client)
+    }
+
+    #[test]
+    fn oversized_frames_are_refused_test() {
+        let message = Message::Value("hello".to_owned());
+        let frame = encode_frame(&message, WireFormat::Bincode, 64).unwrap();
+        let decoded: Message<String> = decode_frame(&frame, WireFormat::Bincode, 64).unwrap();
+        assert!(matches!(decoded, Message::Value(v) if v == "hello"));
+
+        assert!(decode_frame::<Message<String>>(&frame, WireFormat::Bincode, 8).is_err());
+        assert!(encode_frame(&message, WireFormat::Bincode, 8).is_err());
+        let huge = [0xff, 0xff, 0xff, 0xff, 0];
+        assert!(decode_frame::<Message<String>>(&huge, WireFormat::Bincode, 64).is_err());
+    }
+
+    #[test]
+    fn heartbeat_keeps_idle_connection_alive_test() {
+        block_on(async {
+            let (host, client) = connected_pair("127.0.0.1:34331", short_limits()).await;
+            let (_host_sender, host_receiver, _) = host.as_channel_pair();
+            let (client_sender, _client_receiver, _) = client.as_channel_pair();
+
+            task::sleep(Duration::from_secs(1)).await;
+            client_sender
+                .send(Message::Value("still here".to_owned()))
+                .await
+                .unwrap();
+            let received = host_receiver.get().await.unwrap();
+            assert!(matches!(received, Message::Value(v) if v == "still here"));
+        });
+    }
+
+    #[test]
+    fn silent_peer_is_disconnected_test() {
+        block_on(async {
+            let (mut host, _client) = connected_pair("127.0.0.1:34332", short_limits()).await;
+            assert!(host.receive().await.is_err());
+        });
+    }
+
+    #[test]
+    fn websocket_client_connects_to_host_test() {
+        let addr = "127.0.0.1:34333";
+        block_on(async {
+            let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+                addr,
+                "secret",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            let client = Endpoint::<String>::create_connection_to(
+                "ws://127.0.0.1:34333/",
+                "secret",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let host = host.await.unwrap();
+            assert_eq!(host.second_name, "Bob");
+            assert_eq!(client.second_name, "Alice");
+            assert_eq!(client.format, WireFormat::Bincode);
+
+            let (host_sender, host_receiver, _) = host.as_channel_pair();
+            let (client_sender, client_receiver, _) = client.as_channel_pair();
+            client_sender
+                .send(Message::Value("hello".to_owned()))
+                .await
+                .unwrap();
+            let received = host_receiver.get().await.unwrap();
+            assert!(matches!(received, Message::Value(v) if v == "hello"));
+            host_sender
+                .send(Message::Value("hi".to_owned()))
+                .await
+                .unwrap();
+            let received = client_receiver.get().await.unwrap();
+            assert!(matches!(received, Message::Value(v) if v == "hi"));
+        });
+    }
+
+    #[test]
+    fn peer_without_password_is_refused_test() {
+        let addr = "127.0.0.1:34334";
+        block_on(async {
+            let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+                addr,
+                "secret",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+
+            // Skips the password and sends a game message right after the versions
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let mut hello = Hello::ours();
+            hello
+                .features
+                .retain(|f| f != WireFormat::Bincode.feature());
+            let hello = Message::<String>::Hello(hello);
+            let frame = encode_frame(&hello, WireFormat::Json, 1024).unwrap();
+            stream.write_all(&frame).await.unwrap();
+            let mut response = [0; 1024];
+            let _ = stream.read(&mut response).await.unwrap();
+            let value = Message::Value("shoot".to_owned());
+            let frame = encode_frame(&value, WireFormat::Json, 1024).unwrap();
+            stream.write_all(&frame).await.unwrap();
+
+            assert!(timeout(Duration::from_secs(1), host).await.is_err());
+        });
+    }
+
+    #[test]
+    fn silent_peer_does_not_block_the_player_test() {
+        let addr = "127.0.0.1:34335";
+        block_on(async {
+            let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+                addr,
+                "",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+
+            // Connects first and never says a word
+            let _silent = TcpStream::connect(addr).await.unwrap();
+            task::sleep(Duration::from_millis(200)).await;
+            let client = Endpoint::<String>::create_connection_to(
+                addr,
+                "",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            );
+            let limit = ConnectionLimits::default().read_timeout + Duration::from_secs(5);
+            let client = timeout(limit, client).await.unwrap().unwrap();
+            assert_eq!(client.second_name, "Alice");
+            assert!(timeout(limit, host).await.unwrap().is_ok());
+        });
+    }
+
+    #[test]
+    fn websocket_messages_follow_the_frame_limit_test() {
+        let addr = "127.0.0.1:34336";
+        let limits = ConnectionLimits {
+            max_frame_size: 4 * DEFAULT_MAX_FRAME_SIZE,
+            ..Default::default()
+        };
+        block_on(async {
+            let host = task::spawn(Endpoint::<String>::accept_incoming_connection(
+                addr,
+                "",
+                "Alice",
+                limits,
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            let mut client = Endpoint::<String>::create_connection_to(
+                "ws://127.0.0.1:34336/",
+                "",
+                "Bob",
+                limits,
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let mut host = host.await.unwrap();
+
+            // Above the default limit, but within the one of the connection
+            let long = "x".repeat(2 * DEFAULT_MAX_FRAME_SIZE);
+            client.send(&Message::Value(long.clone())).await.unwrap();
+            assert!(matches!(host.receive().await.unwrap(), Message::Value(v) if v == long));
+        });
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use battleships::{
+        circuit::{
+            commons::CircuitField,
+            report::{count_constraints, sample_circuits},
+        },
+        crypto::backend::{Backend, ProvingBackend},
+    };
+
+    #[test]
+    fn board_constraints_do_not_grow_test() {
+        let baseline: HashMap<String, usize> =
+            toml::from_str(include_str!("constraints_baseline.toml")).unwrap();
+        let curve = Backend::CURVE.name();
+        let expected = *baseline.get(curve).unwrap_or_else(|| {
+            panic!("tests/constraints_baseline.toml has no entry for {curve}, add the count for {curve}")
+        });
+
+        let (board, _) = sample_circuits();
+        let count = count_constraints::<CircuitField, _>(board).unwrap();
+        assert!(
+            count.constraints <= expected,
+            "BoardDeclarationCircuit has {} constraints on {curve}, the baseline is {expected}",
+            count.constraints
+        );
+    }
+}
@@ -113,7 +113,7 @@ mod tests {
             field_state: field_state,
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -152,7 +152,7 @@ mod tests {
             field_state: field_state,
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -191,7 +191,7 @@ mod tests {
             field_state: field_state,
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
@@ -230,7 +230,7 @@ mod tests {
             field_state: field_state,
         };
 
-        let cs = ConstraintSystem::new_ref();
+        let cs = ConstraintSystem::<CircuitField>::new_ref();
         real_circuit
             .clone()
             .generate_constraints(cs.clone())
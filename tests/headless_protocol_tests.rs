@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use battleships::{
+        model::{Direction, IncompleteBoard, Ship},
+        ui::{UiInput, UiMessage},
+    };
+
+    #[test]
+    fn inputs_are_parsed_from_json_test() {
+        let input: UiInput =
+            serde_json::from_str(r#"{"HostGame":{"addr":"127.0.0.1:4000","passwd":"secret"}}"#)
+                .unwrap();
+        assert!(
+            matches!(input, UiInput::HostGame { addr, passwd } if addr == "127.0.0.1:4000" && passwd == "secret")
+        );
+
+        let input: UiInput =
+            serde_json::from_str(r#"{"PutShip":{"x":1,"y":2,"size":5,"direction":"Vertical"}}"#)
+                .unwrap();
+        assert!(matches!(
+            input,
+            UiInput::PutShip(Ship {
+                x: 1,
+                y: 2,
+                size: 5,
+                direction: Direction::Vertical
+            })
+        ));
+
+        let input: UiInput = serde_json::from_str(r#"{"Shoot":[3,4]}"#).unwrap();
+        assert!(matches!(input, UiInput::Shoot(3, 4)));
+
+        let input: UiInput = serde_json::from_str(r#""Esc""#).unwrap();
+        assert!(matches!(input, UiInput::Esc));
+    }
+
+    #[test]
+    fn messages_are_written_as_json_test() {
+        let json = serde_json::to_string(&UiMessage::MainScreen).unwrap();
+        assert_eq!(json, r#""MainScreen""#);
+
+        let json =
+            serde_json::to_string(&UiMessage::BoardConstruction(IncompleteBoard(vec![Ship {
+                x: 1,
+                y: 1,
+                size: 1,
+                direction: Direction::Horizontal,
+            }])))
+            .unwrap();
+        assert_eq!(
+            json,
+            r#"{"BoardConstruction":[{"x":1,"y":1,"size":1,"direction":"Horizontal"}]}"#
+        );
+    }
+}
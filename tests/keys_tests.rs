@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Barrier},
+        time::Duration,
+    };
+
+    use ark_ff::PrimeField;
+    use ark_relations::{
+        lc,
+        r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+    };
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use async_std::{future::timeout, task::block_on};
+    use battleships::{
+        crypto::{
+            backend::{Backend, Curve, Groth16Backend, MarlinBackend, ProvingBackend, Selected},
+            key_store::{
+                keys_dir, resolve_keys_dir, set_keys_dir, KeysInfo, BOARD_KEYS_SUBDIR,
+                DEFAULT_KEYS_DIR, FIELD_KEYS_SUBDIR, HISTORY_KEYS_SUBDIR, KEYS_DIR_ENV,
+            },
+            keys::{write_keys, ArkKeys, KeyMeta, CHECKSUM_FILE},
+        },
+        utils::{
+            log::Logger,
+            worker_pool::{worker_pool, Priority},
+        },
+    };
+
+    /// Smallest circuit having keys: x * x = y
+    struct Square;
+
+    impl<F: PrimeField> ConstraintSynthesizer<F> for Square {
+        fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+            let x = cs.new_witness_variable(|| Ok(F::from(3u8)))?;
+            let y = cs.new_input_variable(|| Ok(F::from(9u8)))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + y)
+        }
+    }
+
+    /// The curve the game is not built with
+    #[cfg(not(feature = "bn254"))]
+    type OtherBackend = Groth16Backend<ark_bn254::Bn254>;
+    #[cfg(feature = "bn254")]
+    type OtherBackend = Groth16Backend<ark_bls12_381::Bls12_381>;
+
+    fn key_dir(name: &str) -> String {
+        key_dir_for::<Backend>(name)
+    }
+
+    fn key_dir_for<B: ProvingBackend>(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("battleships-keys-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let dir = dir.to_str().unwrap().to_owned();
+        let (pk, vk) = B::setup(Square, &mut StdRng::seed_from_u64(1)).unwrap();
+        write_keys::<B>(&dir, &vk, &pk).unwrap();
+        dir
+    }
+
+    #[test]
+    fn keys_are_loaded_and_checked_test() {
+        let dir = key_dir("valid");
+        assert!(fs::read_to_string(format!("{dir}/{CHECKSUM_FILE}"))
+            .unwrap()
+            .contains("  pk.bin"));
+
+        let mut keys = ArkKeys::load(Logger::default(), &dir);
+        let pvk = keys.verifying_key().unwrap();
+        match (&*keys.proving_key().unwrap(), &*pvk) {
+            (Selected::Groth16(pk), Selected::Groth16(pvk)) => assert_eq!(pk.vk, pvk.vk),
+            (Selected::Marlin(pk), Selected::Marlin(vk)) => assert_eq!(pk.vk, *vk),
+            _ => panic!("The keys are of different proving backends"),
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keys_are_loaded_while_the_pool_is_busy_test() {
+        let dir = key_dir("busy-pool");
+        // Every worker waits for keys, none is left to load them
+        let workers = worker_pool().metrics().workers;
+        let all_busy = Arc::new(Barrier::new(workers));
+        let jobs: Vec<_> = (0..workers)
+            .map(|_| {
+                let (dir, all_busy) = (dir.clone(), all_busy.clone());
+                worker_pool().spawn(Priority::Normal, move || {
+                    all_busy.wait();
+                    let mut keys = ArkKeys::load(Logger::default(), &dir);
+                    keys.preload_proving_key();
+                    keys.proving_key_checked()?;
+                    keys.verifying_key().map(|_| ())
+                })
+            })
+            .collect();
+        for job in jobs {
+            assert!(block_on(timeout(Duration::from_secs(30), job.result()))
+                .unwrap()
+                .unwrap()
+                .is_ok());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keys_of_another_curve_are_refused_test() {
+        let dir = key_dir_for::<OtherBackend>("other-curve");
+        assert_eq!(
+            KeyMeta::read(&dir).unwrap(),
+            Some(KeyMeta::of::<OtherBackend>())
+        );
+
+        let error = ArkKeys::load(Logger::default(), &dir)
+            .verifying_key()
+            .unwrap_err();
+        assert!(error.message.contains("proves with"), "{}", error.message);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keys_of_another_proving_backend_are_refused_test() {
+        let dir = key_dir_for::<MarlinBackend<Curve>>("other-backend");
+        assert_eq!(
+            KeyMeta::read(&dir).unwrap(),
+            Some(KeyMeta::of::<MarlinBackend<Curve>>())
+        );
+
+        let error = ArkKeys::load(Logger::default(), &dir)
+            .verifying_key()
+            .unwrap_err();
+        assert!(error.message.contains("marlin on"), "{}", error.message);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupted_proving_key_is_detected_test() {
+        let dir = key_dir("corrupted");
+        let pk_path = format!("{dir}/pk.bin");
+        let mut pk = fs::read(&pk_path).unwrap();
+        let last = pk.len() - 1;
+        pk[last] ^= 1;
+        fs::write(&pk_path, pk).unwrap();
+
+        let mut keys = ArkKeys::load(Logger::default(), &dir);
+        assert!(keys.verifying_key().is_ok());
+        let error = keys.proving_key().unwrap_err();
+        assert!(error.message.contains("checksum"), "{}", error.message);
+
+        // Preloading reports the corruption before the key is needed
+        let keys = ArkKeys::load(Logger::default(), &dir);
+        keys.preload_proving_key();
+        let error = keys.proving_key_checked().unwrap_err();
+        assert!(error.message.contains("checksum"), "{}", error.message);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keys_dir_is_resolved_and_checked_test() {
+        std::env::remove_var(KEYS_DIR_ENV);
+        assert_eq!(resolve_keys_dir(None, None), DEFAULT_KEYS_DIR);
+        assert_eq!(resolve_keys_dir(None, Some("configured")), "configured");
+        std::env::set_var(KEYS_DIR_ENV, "from-env");
+        assert_eq!(resolve_keys_dir(None, Some("configured")), "from-env");
+        assert_eq!(resolve_keys_dir(Some("flag"), Some("configured")), "flag");
+        std::env::remove_var(KEYS_DIR_ENV);
+
+        let dir =
+            std::env::temp_dir().join(format!("battleships-keys-missing-{}", std::process::id()));
+        set_keys_dir(dir.to_str().unwrap());
+        assert_eq!(keys_dir(), dir.to_str().unwrap());
+        let info = KeysInfo::collect();
+        assert!(!info.is_complete());
+        assert_eq!(info.circuits[0].missing, vec!["vk.bin", "pk.bin"]);
+        assert!(info.fingerprint.is_none());
+
+        // Every verifying key present is part of the fingerprint
+        let (pk, vk) = Backend::setup(Square, &mut StdRng::seed_from_u64(1)).unwrap();
+        for subdir in [BOARD_KEYS_SUBDIR, FIELD_KEYS_SUBDIR] {
+            write_keys::<Backend>(&format!("{}/{subdir}", keys_dir()), &vk, &pk).unwrap();
+        }
+        let required_only = KeysInfo::collect().fingerprint.unwrap();
+        write_keys::<Backend>(&format!("{}/{HISTORY_KEYS_SUBDIR}", keys_dir()), &vk, &pk).unwrap();
+        assert_ne!(KeysInfo::collect().fingerprint.unwrap(), required_only);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
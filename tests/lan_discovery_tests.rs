@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_std::task::block_on;
+    use battleships::{
+        net::discovery::{announce_room, discover_games, RoomAnnouncement},
+        utils::{
+            log::get_print_logger,
+            threads::{merge, select_first},
+        },
+    };
+    use futures::future::Either;
+
+    #[test]
+    fn announced_room_is_discovered_test() {
+        let announcement = RoomAnnouncement {
+            room: "Alice".to_owned(),
+            port: 4000,
+            password_required: true,
+            key_fingerprint: "0123456789abcdef".to_owned(),
+        };
+        let discovery_port = 34299;
+
+        let result = block_on(select_first(
+            announce_room(announcement.clone(), discovery_port, get_print_logger()),
+            discover_games(
+                discovery_port,
+                Duration::from_millis(2500),
+                "0123456789abcdef",
+            ),
+        ))
+        .unwrap();
+
+        let Either::Right(games) = result else {
+            panic!("announcing should never finish");
+        };
+        assert_eq!(games.len(), 1);
+        assert!(games[0].addr.ends_with(":4000"));
+        assert_eq!(games[0].announcement, announcement);
+        assert!(games[0].compatible);
+    }
+
+    #[test]
+    fn two_players_on_one_machine_browse_at_once_test() {
+        let announcement = RoomAnnouncement {
+            room: "Alice".to_owned(),
+            port: 4001,
+            password_required: false,
+            key_fingerprint: "0123456789abcdef".to_owned(),
+        };
+        let discovery_port = 34298;
+        let browse = || discover_games(discovery_port, Duration::from_millis(2500), "");
+
+        let result = block_on(select_first(
+            announce_room(announcement.clone(), discovery_port, get_print_logger()),
+            merge(browse(), browse()),
+        ))
+        .unwrap();
+
+        let Either::Right((first, second)) = result else {
+            panic!("announcing should never finish");
+        };
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first[0].announcement, announcement);
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use battleships::utils::{
+        log::{file_sink::FileLog, Level, Log, Logger, Record},
+        result::{Er, Res},
+    };
+
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<Record>>>);
+
+    impl Log for Collect {
+        fn log_message(&self, msg: &str) -> Res<()> {
+            self.log(&Record::new(Level::Info, msg))
+        }
+
+        fn log(&self, record: &Record) -> Res<()> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logger_filters_sinks_by_level_test() {
+        let (everything, important) = (Collect::default(), Collect::default());
+        let logger = Logger::default()
+            .with_sink(everything.clone(), Level::Debug)
+            .with_sink(important.clone(), Level::Warn)
+            .with_field("component", "game")
+            .with_field("game", "1")
+            .with_field("game", "2");
+
+        logger.debug("details").unwrap();
+        logger.log_message("info").unwrap();
+        logger.error("failure").unwrap();
+        logger.log(&Record::new(Level::Trace, "noise")).unwrap();
+
+        let everything = everything.0.lock().unwrap();
+        assert_eq!(everything.len(), 3);
+        assert_eq!(everything[1].level, Level::Info);
+        assert_eq!(everything[0].field("component"), Some("game"));
+        assert_eq!(everything[0].field("game"), Some("2"));
+        let important = important.0.lock().unwrap();
+        assert_eq!(important.len(), 1);
+        assert_eq!(important[0].message, "failure");
+    }
+
+    #[test]
+    fn fields_are_removed_test() {
+        let records = Collect::default();
+        let logger = Logger::default()
+            .with_sink(records.clone(), Level::Debug)
+            .with_field("component", "game")
+            .with_field("game", "1")
+            .without_field("game");
+
+        logger.info("next game").unwrap();
+        let records = records.0.lock().unwrap();
+        assert_eq!(records[0].field("game"), None);
+        assert_eq!(records[0].field("component"), Some("game"));
+    }
+
+    struct Broken;
+
+    impl Log for Broken {
+        fn log_message(&self, _msg: &str) -> Res<()> {
+            Err(Er {
+                message: "broken sink".to_owned(),
+            })
+        }
+    }
+
+    #[test]
+    fn failing_sink_does_not_stop_the_others_test() {
+        let collect = Collect::default();
+        let logger = Logger::default()
+            .with_sink(Broken, Level::Debug)
+            .with_sink(collect.clone(), Level::Debug);
+
+        let error = logger.log_message("info").unwrap_err();
+        assert_eq!(error.message, "broken sink");
+        assert_eq!(collect.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn log_file_is_rotated_test() {
+        let dir = std::env::temp_dir().join(format!("battleships-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let log = FileLog::new(dir.to_str().unwrap(), "test.log", 100, 2).unwrap();
+
+        for i in 0..20 {
+            log.log_message(&format!("message number {i}")).unwrap();
+        }
+        let mut files = fs::read_dir(&dir)
+            .unwrap()
+            .map(|f| f.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["test.log", "test.log.1", "test.log.2"]);
+        let newest = fs::read_to_string(dir.join("test.log.1")).unwrap();
+        assert!(newest.contains("INFO"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use ark_ff::PrimeField;
+    use ark_relations::{
+        lc,
+        r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable},
+    };
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use battleships::{
+        circuit::commons::CircuitField,
+        crypto::backend::{Curve, MarlinBackend, ProvingBackend},
+    };
+
+    type Marlin = MarlinBackend<Curve>;
+
+    /// Knowledge of the `x` with x^3 + x + 5 = y, for every pair of the inputs
+    #[derive(Clone)]
+    struct Cubic(Vec<(u64, u64)>);
+
+    impl<F: PrimeField> ConstraintSynthesizer<F> for Cubic {
+        fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+            for (x, y) in self.0 {
+                let y = cs.new_input_variable(|| Ok(F::from(y)))?;
+                let x_value = F::from(x);
+                let x = cs.new_witness_variable(|| Ok(x_value))?;
+                let x2 = cs.new_witness_variable(|| Ok(x_value * x_value))?;
+                let x3 = cs.new_witness_variable(|| Ok(x_value * x_value * x_value))?;
+                cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x2)?;
+                cs.enforce_constraint(lc!() + x2, lc!() + x, lc!() + x3)?;
+                cs.enforce_constraint(
+                    lc!() + x3 + x + (F::from(5u8), Variable::One),
+                    lc!() + Variable::One,
+                    lc!() + y,
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    fn cubic(xs: &[u64]) -> (Cubic, Vec<CircuitField>) {
+        let pairs: Vec<_> = xs.iter().map(|&x| (x, x * x * x + x + 5)).collect();
+        let input = pairs.iter().map(|&(_, y)| CircuitField::from(y)).collect();
+        (Cubic(pairs), input)
+    }
+
+    #[test]
+    fn proofs_are_verified_test() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for xs in [vec![3], vec![2, 3, 4, 5, 6]] {
+            let (circuit, input) = cubic(&xs);
+            let (pk, vk) = Marlin::setup(circuit.clone(), &mut rng).unwrap();
+            let pvk = Marlin::prepare(&vk);
+            let proof = Marlin::prove(&pk, circuit, &mut rng).unwrap();
+            assert!(Marlin::verify(&pvk, &input, &proof).unwrap());
+
+            let mut other_input = input.clone();
+            other_input[0] += CircuitField::from(1u8);
+            assert!(!Marlin::verify(&pvk, &other_input, &proof).unwrap());
+            assert!(Marlin::verify(&pvk, &input[1..], &proof).is_err());
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_are_refused_test() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (circuit, input) = cubic(&[2, 3, 4]);
+        let (pk, vk) = Marlin::setup(circuit.clone(), &mut rng).unwrap();
+        let proof = Marlin::prove(&pk, circuit, &mut rng).unwrap();
+
+        for i in 0..proof.evaluations.len() {
+            let mut tampered = proof.clone();
+            tampered.evaluations[i] += CircuitField::from(1u8);
+            assert!(
+                !Marlin::verify(&vk, &input, &tampered).unwrap(),
+                "evaluation {i}"
+            );
+        }
+        for i in 0..proof.sums.len() {
+            let mut tampered = proof.clone();
+            tampered.sums[i] += CircuitField::from(1u8);
+            assert!(!Marlin::verify(&vk, &input, &tampered).unwrap(), "sum {i}");
+        }
+        for i in 0..proof.commitments.len() {
+            let mut tampered = proof.clone();
+            let next = (i + 1) % proof.commitments.len();
+            tampered.commitments.swap(i, next);
+            assert!(
+                !Marlin::verify(&vk, &input, &tampered).unwrap_or(false),
+                "commitment {i}"
+            );
+        }
+        let mut truncated = proof.clone();
+        truncated.openings.pop();
+        assert!(!Marlin::verify(&vk, &input, &truncated).unwrap());
+    }
+
+    #[test]
+    fn unsatisfied_circuit_is_not_proven_test() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (circuit, _) = cubic(&[3]);
+        let (pk, _) = Marlin::setup(circuit, &mut rng).unwrap();
+        assert!(Marlin::prove(&pk, Cubic(vec![(3, 36)]), &mut rng).is_err());
+    }
+
+    #[test]
+    fn circuits_share_the_universal_parameters_test() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let (small, small_input) = cubic(&[3]);
+        let (large, large_input) = cubic(&[2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        let degree = Marlin::parameters_degree(large.clone()).unwrap();
+        assert!(Marlin::parameters_degree(small.clone()).unwrap() <= degree);
+
+        let params = Marlin::parameters(degree, &mut rng).unwrap();
+        for (circuit, input) in [(small, small_input), (large.clone(), large_input)] {
+            let (pk, vk) =
+                Marlin::setup_with_parameters(circuit.clone(), &params, &mut rng).unwrap();
+            // The keys depend only on the parameters and the circuit
+            let (_, same_vk) =
+                Marlin::setup_with_parameters(circuit.clone(), &params, &mut rng).unwrap();
+            assert!(vk == same_vk);
+            let proof = Marlin::prove(&pk, circuit, &mut rng).unwrap();
+            assert!(Marlin::verify(&vk, &input, &proof).unwrap());
+        }
+
+        let too_small = Marlin::parameters(degree / 2, &mut rng).unwrap();
+        let error = Marlin::setup_with_parameters(large, &too_small, &mut rng).unwrap_err();
+        assert!(error.message.contains("degree"), "{}", error.message);
+    }
+
+    #[test]
+    fn proofs_are_serialized_test() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (circuit, input) = cubic(&[3, 4]);
+        let (pk, vk) = Marlin::setup(circuit.clone(), &mut rng).unwrap();
+        let proof = Marlin::prove(&pk, circuit, &mut rng).unwrap();
+
+        let mut bytes = vec![];
+        proof.serialize_compressed(&mut bytes).unwrap();
+        let proof = <Marlin as ProvingBackend>::Proof::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(Marlin::verify(&vk, &input, &proof).unwrap());
+    }
+}
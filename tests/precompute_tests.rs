@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use async_std::task::block_on;
+    use battleships::{
+        circuit::{
+            board_declaration_circuit::BoardDeclarationCircuit, field_declaration_circuit,
+            report::sample_board,
+        },
+        crypto::{key_store::universal_parameters, keys::ArkKeys},
+        logic::precompute::ProofCache,
+        utils::{log::Logger, worker_pool::worker_pool},
+    };
+
+    #[test]
+    fn disabled_cache_serves_nothing_test() {
+        let board = BoardDeclarationCircuit::from(sample_board());
+        let keys = ArkKeys::load(Logger::default(), "missing-keys");
+        let mut cache = ProofCache::new(false);
+
+        cache.start(board, keys);
+        assert_eq!(cache.ready(), 0);
+        assert!(block_on(cache.take(1, 1)).is_none());
+    }
+
+    #[test]
+    #[ignore = "generates the field keys, takes about ten minutes in a debug build"]
+    fn precomputed_proof_is_served_test() {
+        let dir = std::env::temp_dir().join(format!(
+            "battleships-precompute-keys-{}",
+            std::process::id()
+        ));
+        let dir = dir.to_str().unwrap().to_owned();
+        let params = universal_parameters(&Logger::default()).unwrap();
+        field_declaration_circuit::generate_keys(&Logger::default(), &dir, &params).unwrap();
+
+        let board = BoardDeclarationCircuit::from(sample_board());
+        let mut cache = ProofCache::new(true);
+        cache.start(board, ArkKeys::load(Logger::default(), &dir));
+
+        // The first field is proven right away, asking for it waits for that proof
+        if worker_pool().metrics().workers > 1 {
+            thread::sleep(Duration::from_millis(100));
+            assert!(block_on(cache.take(1, 1)).is_some());
+        }
+        let started = Instant::now();
+        while cache.ready() == 0 {
+            assert!(started.elapsed() < Duration::from_secs(600));
+            thread::sleep(Duration::from_millis(100));
+        }
+        let field = (1..=10)
+            .flat_map(|x| (1..=10).map(move |y| (x, y)))
+            .find(|&(x, y)| block_on(cache.take(x, y)).is_some());
+        let (x, y) = field.expect("a ready proof is served");
+        // Each proof is served once, later the field is proven on demand
+        assert!(block_on(cache.take(x, y)).is_none());
+
+        cache.clear();
+        assert_eq!(cache.ready(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
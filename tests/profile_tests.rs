@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use battleships::logic::{
+        profile::{load_profile_file, PlayerProfile},
+        statistics::Statistics,
+    };
+
+    fn profile_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("battleships-profile-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn missing_file_gives_nothing_test() {
+        let dir = profile_dir("missing");
+        let path = format!("{dir}/statistics.json");
+        assert!(load_profile_file::<Statistics>(&path).is_none());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stored_file_is_loaded_test() {
+        let dir = profile_dir("stored");
+        let path = format!("{dir}/profile.json");
+        fs::write(&path, r#"{"name": "Stored"}"#).unwrap();
+        let profile = load_profile_file::<PlayerProfile>(&path).unwrap();
+        assert_eq!(profile.name, "Stored");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn invalid_file_is_moved_aside_test() {
+        let dir = profile_dir("invalid");
+        let path = format!("{dir}/statistics.json");
+        let content = r#"{"games": [{"opponent": "Opponent", "won": tru"#;
+        fs::write(&path, content).unwrap();
+
+        assert!(load_profile_file::<Statistics>(&path).is_none());
+        assert!(fs::metadata(&path).is_err());
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let backup = backups[0].to_str().unwrap();
+        assert!(backup.contains("statistics.json.corrupt-"), "{backup}");
+        assert_eq!(fs::read_to_string(backup).unwrap(), content);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use ark_serialize::CanonicalSerialize;
+    use async_std::task::block_on;
+    use battleships::{
+        crypto::backend::Proof,
+        logic::{
+            dispute::DisputeReason,
+            history::ShotHistoryProof,
+            protocol::{
+                Classification::{self, *},
+                MessageKind, ProtocolReceiver, ProtocolState,
+            },
+            GameMessage,
+        },
+        net::message::Message,
+        utils::async_receiver::AsyncReceiver,
+    };
+
+    /// Expected classification, rows follow `ProtocolState::ALL` and columns `MessageKind::ALL`:
+    /// BoardDeclaration, AskForField, FieldProof, Dispute, RematchOffer, RematchAccept, ShotHistory
+    const TABLE: [[Classification; 7]; 4] = [
+        // AwaitingBoard
+        [
+            Expected, Premature, Illegal, Expected, Illegal, Illegal, Ignored,
+        ],
+        // AwaitingFieldProof
+        [
+            Illegal, Illegal, Expected, Expected, Illegal, Illegal, Illegal,
+        ],
+        // TheirTurn
+        [
+            Illegal, Expected, Illegal, Expected, Illegal, Illegal, Illegal,
+        ],
+        // GameOver
+        [
+            Premature, Illegal, Illegal, Expected, Expected, Expected, Expected,
+        ],
+    ];
+
+    #[test]
+    fn all_state_message_pairs_are_classified_test() {
+        for (state, row) in ProtocolState::ALL.iter().zip(TABLE) {
+            for (kind, expected) in MessageKind::ALL.iter().zip(row) {
+                assert_eq!(
+                    state.classify(*kind),
+                    expected,
+                    "{kind:?} received in state {state:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn premature_messages_are_buffered_test() {
+        let (sender, receiver) = async_channel::unbounded();
+        let mut protocol = ProtocolReceiver::new(AsyncReceiver(receiver));
+
+        block_on(async {
+            for message in [
+                GameMessage::AskForField(3, 4),
+                GameMessage::Dispute(DisputeReason::InvalidBoardProof),
+                GameMessage::RematchOffer,
+            ] {
+                sender.send(Message::Value(message)).await.unwrap();
+            }
+
+            let message = protocol.next(ProtocolState::AwaitingBoard).await.unwrap();
+            assert!(matches!(message, GameMessage::Dispute(_)));
+            // The early shot is delivered once it is the opponent's turn
+            let message = protocol.next(ProtocolState::TheirTurn).await.unwrap();
+            assert!(matches!(message, GameMessage::AskForField(3, 4)));
+            // Offering a rematch during the game breaks the protocol
+            assert!(protocol.next(ProtocolState::TheirTurn).await.is_err());
+        });
+    }
+
+    #[test]
+    fn shots_off_the_board_or_repeated_are_illegal_test() {
+        let (sender, receiver) = async_channel::unbounded();
+        let mut protocol = ProtocolReceiver::new(AsyncReceiver(receiver));
+
+        block_on(async {
+            for message in [
+                GameMessage::AskForField(3, 4),
+                GameMessage::AskForField(3, 4),
+                GameMessage::AskForField(200, 200),
+            ] {
+                sender.send(Message::Value(message)).await.unwrap();
+            }
+            assert!(protocol.next(ProtocolState::TheirTurn).await.is_ok());
+            assert!(protocol.next(ProtocolState::TheirTurn).await.is_err());
+            assert!(protocol.next(ProtocolState::TheirTurn).await.is_err());
+
+            // The same field can be shot again in the next game
+            protocol.new_game();
+            let shot = Message::Value(GameMessage::AskForField(3, 4));
+            sender.send(shot).await.unwrap();
+            assert!(protocol.next(ProtocolState::TheirTurn).await.is_ok());
+        });
+    }
+
+    #[test]
+    fn only_one_premature_message_of_a_kind_is_kept_test() {
+        let (sender, receiver) = async_channel::unbounded();
+        let mut protocol = ProtocolReceiver::new(AsyncReceiver(receiver));
+
+        block_on(async {
+            for message in [
+                GameMessage::AskForField(3, 4),
+                GameMessage::AskForField(5, 6),
+            ] {
+                sender.send(Message::Value(message)).await.unwrap();
+            }
+            assert!(protocol.next(ProtocolState::AwaitingBoard).await.is_err());
+        });
+    }
+
+    #[test]
+    fn late_shot_history_is_dropped_after_the_rematch_test() {
+        let (sender, receiver) = async_channel::unbounded();
+        let mut protocol = ProtocolReceiver::new(AsyncReceiver(receiver));
+
+        let mut bytes = vec![];
+        Proof::default().serialize_compressed(&mut bytes).unwrap();
+        let history = ShotHistoryProof {
+            hash: [0; 32],
+            shots: vec![],
+            proof: serde_json::from_slice(&serde_json::to_vec(&bytes).unwrap()).unwrap(),
+        };
+
+        block_on(async {
+            for message in [
+                GameMessage::ShotHistory(history),
+                GameMessage::Dispute(DisputeReason::InvalidBoardProof),
+            ] {
+                sender.send(Message::Value(message)).await.unwrap();
+            }
+            let message = protocol.next(ProtocolState::AwaitingBoard).await.unwrap();
+            assert!(matches!(message, GameMessage::Dispute(_)));
+        });
+    }
+}
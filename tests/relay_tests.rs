@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_std::{
+        io::WriteExt,
+        net::TcpStream,
+        task::{self, block_on},
+    };
+    use battleships::{
+        net::{
+            connection::{ConnectionLimits, Endpoint},
+            message::Message,
+            relay::{run_relay, RelayLimits, RelayRequest},
+        },
+        utils::log::get_print_logger,
+    };
+
+    const RELAY_ADDR: &str = "127.0.0.1:34301";
+
+    #[test]
+    fn players_are_paired_by_the_relay_test() {
+        task::spawn(run_relay(
+            RELAY_ADDR,
+            ConnectionLimits::default(),
+            RelayLimits::default(),
+            get_print_logger(),
+        ));
+
+        block_on(async {
+            task::sleep(Duration::from_millis(200)).await;
+            let host = task::spawn(Endpoint::<String>::host_via_relay(
+                RELAY_ADDR,
+                "room",
+                "secret",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+
+            let mut client = Endpoint::<String>::join_via_relay(
+                RELAY_ADDR,
+                "room",
+                "secret",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let mut host = host.await.unwrap();
+            assert_eq!(host.second_name, "Bob");
+            assert_eq!(client.second_name, "Alice");
+
+            client
+                .send(&Message::Value("hello".to_owned()))
+                .await
+                .unwrap();
+            assert!(matches!(host.receive().await.unwrap(), Message::Value(v) if v == "hello"));
+        });
+    }
+
+    #[test]
+    fn joining_missing_room_fails_test() {
+        let relay_addr = "127.0.0.1:34302";
+        task::spawn(run_relay(
+            relay_addr,
+            ConnectionLimits::default(),
+            RelayLimits::default(),
+            get_print_logger(),
+        ));
+
+        block_on(async {
+            task::sleep(Duration::from_millis(200)).await;
+            let result = Endpoint::<String>::join_via_relay(
+                relay_addr,
+                "missing",
+                "",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await;
+            assert!(result.is_err());
+        });
+    }
+
+    #[test]
+    fn silent_opponent_does_not_hold_the_room_test() {
+        let relay_addr = "127.0.0.1:34303";
+        task::spawn(run_relay(
+            relay_addr,
+            ConnectionLimits::default(),
+            RelayLimits::default(),
+            get_print_logger(),
+        ));
+        let limits = ConnectionLimits {
+            read_timeout: Duration::from_millis(300),
+            ..Default::default()
+        };
+
+        block_on(async {
+            task::sleep(Duration::from_millis(200)).await;
+            let host = task::spawn(Endpoint::<String>::host_via_relay(
+                relay_addr,
+                "room",
+                "",
+                "Alice",
+                limits,
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+
+            // Joins the room and never starts the handshake
+            let mut silent = TcpStream::connect(relay_addr).await.unwrap();
+            let request = serde_json::to_vec(&RelayRequest::Join {
+                room: "room".to_owned(),
+            })
+            .unwrap();
+            silent
+                .write_all(&(request.len() as u32).to_be_bytes())
+                .await
+                .unwrap();
+            silent.write_all(&request).await.unwrap();
+            task::sleep(Duration::from_secs(1)).await;
+
+            let client = Endpoint::<String>::join_via_relay(
+                relay_addr,
+                "room",
+                "",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(client.second_name, "Alice");
+            assert_eq!(host.await.unwrap().second_name, "Bob");
+        });
+    }
+
+    #[test]
+    fn rooms_are_capped_test() {
+        let relay_addr = "127.0.0.1:34304";
+        task::spawn(run_relay(
+            relay_addr,
+            ConnectionLimits::default(),
+            RelayLimits {
+                max_rooms: 1,
+                ..Default::default()
+            },
+            get_print_logger(),
+        ));
+
+        block_on(async {
+            task::sleep(Duration::from_millis(200)).await;
+            let _first = task::spawn(Endpoint::<String>::host_via_relay(
+                relay_addr,
+                "first",
+                "",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            let error = Endpoint::<String>::host_via_relay(
+                relay_addr,
+                "second",
+                "",
+                "Carol",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .err()
+            .unwrap();
+            assert!(error.message.contains("no free room"), "{}", error.message);
+        });
+    }
+
+    #[test]
+    fn pending_connections_are_capped_test() {
+        let relay_addr = "127.0.0.1:34305";
+        task::spawn(run_relay(
+            relay_addr,
+            ConnectionLimits::default(),
+            RelayLimits {
+                max_pending: 1,
+                ..Default::default()
+            },
+            get_print_logger(),
+        ));
+
+        block_on(async {
+            task::sleep(Duration::from_millis(200)).await;
+            let _host = task::spawn(Endpoint::<String>::host_via_relay(
+                relay_addr,
+                "room",
+                "",
+                "Alice",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            ));
+            task::sleep(Duration::from_millis(200)).await;
+            // Connects and never sends its request, leaving no place for the opponent
+            let _silent = TcpStream::connect(relay_addr).await.unwrap();
+            task::sleep(Duration::from_millis(200)).await;
+            let result = Endpoint::<String>::join_via_relay(
+                relay_addr,
+                "room",
+                "",
+                "Bob",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await;
+            assert!(result.is_err());
+        });
+    }
+}
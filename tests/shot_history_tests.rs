@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests {
+    use ark_serialize::CanonicalSerialize;
+    use battleships::{
+        circuit::{
+            commons::CircuitField,
+            report::{count_constraints, sample_circuits, sample_shot_history},
+            shot_history_circuit::{ShotHistoryInput, MAX_SHOTS},
+        },
+        crypto::backend::Proof,
+        logic::{history::ShotHistoryProof, main::GameKeys},
+        model::FieldState,
+        utils::log::Logger,
+    };
+
+    #[test]
+    fn honest_answers_satisfy_the_circuit_test() {
+        let (board, _) = sample_circuits();
+        let history = sample_shot_history(board);
+        assert_eq!(history.shots.len(), MAX_SHOTS);
+        assert!(count_constraints::<CircuitField, _>(history).is_ok());
+    }
+
+    #[test]
+    fn false_answer_does_not_satisfy_the_circuit_test() {
+        let (board, _) = sample_circuits();
+        let mut history = sample_shot_history(board);
+        history.shots.truncate(10);
+        // (1, 1) holds a ship of size one
+        history.shots[0] = (1, 1, FieldState::Empty);
+        assert!(count_constraints::<CircuitField, _>(history).is_err());
+    }
+
+    #[test]
+    fn shot_off_the_board_does_not_satisfy_the_circuit_test() {
+        let (board, _) = sample_circuits();
+        for (x, y) in [(11, 1), (1, 11), (0, 1), (1, 0)] {
+            let mut history = sample_shot_history(board);
+            history.shots.truncate(10);
+            history.shots[9] = (x, y, FieldState::Empty);
+            assert!(
+                count_constraints::<CircuitField, _>(history).is_err(),
+                "({x}, {y})"
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_shot_does_not_satisfy_the_circuit_test() {
+        let (board, _) = sample_circuits();
+        let mut history = sample_shot_history(board);
+        history.shots.truncate(10);
+        history.shots[9] = history.shots[0];
+        assert!(count_constraints::<CircuitField, _>(history).is_err());
+    }
+
+    #[test]
+    fn shot_after_the_padding_does_not_satisfy_the_circuit_test() {
+        let (board, _) = sample_circuits();
+        let mut history = sample_shot_history(board);
+        history.shots.truncate(10);
+        history.shots[5] = (0, 0, FieldState::Empty);
+        assert!(count_constraints::<CircuitField, _>(history).is_err());
+    }
+
+    #[test]
+    fn too_many_shots_are_refused_test() {
+        let input = ShotHistoryInput {
+            hash: [0; 32],
+            shots: vec![(1, 1, FieldState::Empty); MAX_SHOTS + 1],
+        };
+        assert!(input.into_public_input::<CircuitField>().is_err());
+    }
+
+    /// History with a proof which is never checked
+    fn history_with(shots: Vec<(u8, u8, FieldState)>) -> ShotHistoryProof {
+        let mut bytes = vec![];
+        Proof::default().serialize_compressed(&mut bytes).unwrap();
+        ShotHistoryProof {
+            hash: [0; 32],
+            shots,
+            proof: serde_json::from_slice(&serde_json::to_vec(&bytes).unwrap()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn shots_off_the_board_are_refused_test() {
+        let keys = GameKeys::load(Logger::default());
+        for (x, y) in [(0, 0), (11, 1), (1, 11)] {
+            let history = history_with(vec![(x, y, FieldState::Empty)]);
+            assert!(!history.verify(&keys).unwrap(), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn repeated_shots_are_refused_test() {
+        let keys = GameKeys::load(Logger::default());
+        let history = history_with(vec![(1, 1, FieldState::Occupied); 2]);
+        assert!(!history.verify(&keys).unwrap());
+    }
+
+    #[test]
+    fn too_long_history_is_refused_test() {
+        let keys = GameKeys::load(Logger::default());
+        let shots = (1..=10)
+            .flat_map(|x| (1..=10).map(move |y| (x, y, FieldState::Empty)))
+            .chain([(1, 1, FieldState::Empty)])
+            .collect::<Vec<_>>();
+        assert_eq!(shots.len(), MAX_SHOTS + 1);
+        assert!(!history_with(shots).verify(&keys).unwrap());
+    }
+}
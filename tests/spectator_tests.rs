@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ark_serialize::CanonicalSerialize;
+    use async_std::{
+        future::timeout,
+        net::TcpListener,
+        task::{self, block_on},
+    };
+    use battleships::{
+        circuit::field_declaration_circuit::FieldDeclarationCircuit,
+        crypto::{backend::Proof, proofs::CorrectnessProof},
+        logic::{
+            main::GameKeys,
+            spectator::{spectate_game, SpectatorEvent, Spectators},
+            Player,
+        },
+        model::FieldState,
+        net::{
+            connection::{ConnectionLimits, Endpoint},
+            message::Message,
+        },
+        ui::UiMessage,
+        utils::log::{get_print_logger, Logger},
+    };
+
+    const ADDR: &str = "127.0.0.1:34311";
+
+    #[test]
+    fn spectators_receive_history_and_new_events_test() {
+        block_on(async {
+            let listener = TcpListener::bind(ADDR).await.unwrap();
+            let spectators = Spectators::default();
+            spectators.set_players("Alice", "Bob");
+            spectators.new_game();
+
+            let host = {
+                let spectators = spectators.clone();
+                task::spawn(async move {
+                    Endpoint::<SpectatorEvent>::accept_spectators(
+                        listener,
+                        "secret",
+                        "Alice",
+                        ConnectionLimits::default(),
+                        get_print_logger(),
+                        move |endpoint| spectators.add(endpoint),
+                    )
+                    .await
+                })
+            };
+
+            let mut spectator = Endpoint::<SpectatorEvent>::spectate(
+                ADDR,
+                "secret",
+                "Carol",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(spectator.second_name, "Alice");
+
+            let Message::Value(SpectatorEvent::NewGame { host: name, client }) =
+                spectator.receive().await.unwrap()
+            else {
+                panic!("the history should be replayed first");
+            };
+            assert_eq!((name.as_str(), client.as_str()), ("Alice", "Bob"));
+
+            task::sleep(Duration::from_millis(100)).await;
+            spectators.publish(SpectatorEvent::Shot {
+                shooter: Player::Host,
+                x: 2,
+                y: 7,
+            });
+            assert!(matches!(
+                spectator.receive().await.unwrap(),
+                Message::Value(SpectatorEvent::Shot { x: 2, y: 7, .. })
+            ));
+
+            spectators.close().await;
+            host.cancel().await;
+        });
+    }
+
+    #[test]
+    fn spectator_with_wrong_password_is_refused_test() {
+        let addr = "127.0.0.1:34312";
+        block_on(async {
+            let listener = TcpListener::bind(addr).await.unwrap();
+            task::spawn(async move {
+                Endpoint::<SpectatorEvent>::accept_spectators(
+                    listener,
+                    "secret",
+                    "Alice",
+                    ConnectionLimits::default(),
+                    get_print_logger(),
+                    |_| panic!("the spectator should be refused"),
+                )
+                .await
+            });
+
+            let result = Endpoint::<SpectatorEvent>::spectate(
+                addr,
+                "wrong",
+                "Carol",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await;
+            assert!(result.is_err());
+        });
+    }
+
+    #[test]
+    fn winner_claimed_by_the_host_is_not_trusted_test() {
+        let addr = "127.0.0.1:34313";
+        block_on(async {
+            let listener = TcpListener::bind(addr).await.unwrap();
+            let spectators = Spectators::default();
+            spectators.set_players("Alice", "Bob");
+            spectators.new_game();
+            let host = {
+                let spectators = spectators.clone();
+                task::spawn(async move {
+                    Endpoint::<SpectatorEvent>::accept_spectators(
+                        listener,
+                        "",
+                        "Alice",
+                        ConnectionLimits::default(),
+                        get_print_logger(),
+                        move |endpoint| spectators.add(endpoint),
+                    )
+                    .await
+                })
+            };
+
+            let endpoint = Endpoint::<SpectatorEvent>::spectate(
+                addr,
+                "",
+                "Carol",
+                ConnectionLimits::default(),
+                get_print_logger(),
+            )
+            .await
+            .unwrap();
+            let (ui_sender, ui_receiver) = async_channel::unbounded();
+            let watching = task::spawn(async move {
+                let keys = GameKeys::load(Logger::default());
+                spectate_game(endpoint, keys, &ui_sender).await
+            });
+
+            let mut bytes = vec![];
+            Proof::default().serialize_compressed(&mut bytes).unwrap();
+            let proof: CorrectnessProof<FieldDeclarationCircuit> =
+                serde_json::from_slice(&serde_json::to_vec(&bytes).unwrap()).unwrap();
+            // A shot off the board, an answer to a shot never fired and a winner without sunk ships
+            spectators.publish(SpectatorEvent::Shot {
+                shooter: Player::Host,
+                x: 200,
+                y: 1,
+            });
+            spectators.publish(SpectatorEvent::FieldProof {
+                owner: Player::Client,
+                x: 3,
+                y: 3,
+                proof,
+                state: FieldState::Occupied,
+            });
+            spectators.publish(SpectatorEvent::GameOver {
+                winner: Player::Host,
+            });
+
+            let state = timeout(Duration::from_secs(10), async {
+                loop {
+                    if let Ok(UiMessage::Spectating(state)) = ui_receiver.recv().await {
+                        if state.invalid_proofs > 0 {
+                            return state;
+                        }
+                    }
+                }
+            })
+            .await
+            .unwrap();
+            assert_eq!(state.invalid_proofs, 3);
+            assert_eq!(state.winner, None);
+            assert!(state.client_board.is_empty());
+
+            spectators.close().await;
+            watching.cancel().await;
+            host.cancel().await;
+        });
+    }
+}
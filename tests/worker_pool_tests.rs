@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use async_std::task::block_on;
+    use battleships::utils::worker_pool::{Priority, WorkerPool};
+
+    #[test]
+    fn jobs_run_by_priority_test() {
+        let pool = WorkerPool::new(1);
+        let order = Arc::new(Mutex::new(vec![]));
+        let blocker = pool.spawn(Priority::High, || thread::sleep(Duration::from_millis(200)));
+        let handles: Vec<_> = [
+            (Priority::Speculative, 1),
+            (Priority::Normal, 2),
+            (Priority::High, 3),
+            (Priority::Normal, 4),
+        ]
+        .into_iter()
+        .map(|(priority, id)| {
+            let order = order.clone();
+            pool.spawn(priority, move || order.lock().unwrap().push(id))
+        })
+        .collect();
+
+        block_on(async {
+            blocker.result().await.unwrap();
+            for handle in &handles {
+                handle.result().await.unwrap();
+            }
+        });
+        assert_eq!(*order.lock().unwrap(), vec![3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn cancelled_job_is_skipped_test() {
+        let pool = WorkerPool::new(1);
+        let blocker = pool.spawn(Priority::High, || thread::sleep(Duration::from_millis(100)));
+        let cancelled = pool.spawn(Priority::Normal, || 1);
+        cancelled.cancel();
+        drop(pool.spawn(Priority::Normal, || 2));
+        let kept = pool.spawn(Priority::Speculative, || 3);
+
+        block_on(async {
+            blocker.result().await.unwrap();
+            assert_eq!(kept.result().await.unwrap(), 3);
+            assert!(cancelled.result().await.is_err());
+        });
+        let metrics = pool.metrics();
+        assert_eq!(metrics.workers, 1);
+        assert_eq!(metrics.completed, 2);
+        assert_eq!(metrics.cancelled, 2);
+        assert_eq!(metrics.queued, 0);
+    }
+
+    #[test]
+    fn panicking_job_keeps_worker_alive_test() {
+        let pool = WorkerPool::new(1);
+        block_on(async {
+            assert!(pool
+                .run(Priority::Normal, || -> u8 { panic!("job failed") })
+                .await
+                .is_err());
+            assert_eq!(pool.run(Priority::Normal, || 5).await.unwrap(), 5);
+        });
+    }
+
+    #[test]
+    fn pool_without_workers_still_runs_jobs_test() {
+        let pool = WorkerPool::new(0);
+        assert_eq!(block_on(pool.run(Priority::Normal, || 2 + 2)).unwrap(), 4);
+    }
+}